@@ -5,10 +5,11 @@ pub use crate::parser::*;
 use simple_error::SimpleError;
 use std::cell::{RefCell, RefMut};
 use std::cmp::Ordering;
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::fs::File;
 use std::io::BufReader;
 use std::path::Path;
+use std::rc::Rc;
 
 #[derive(Debug, PartialEq)]
 enum Direction {
@@ -24,6 +25,45 @@ struct ValidityInfo {
     direction: Direction,
 }
 
+impl ValidityInfo {
+    fn new() -> Self {
+        ValidityInfo {
+            visited_pages: vec![],
+            direction: Direction::None,
+        }
+    }
+
+    fn update_for_crow(&mut self, crow: i32) {
+        if crow == ESE_MoveFirst {
+            self.visited_pages.clear(); // if we're going to the beginning, clear out any previous visited into
+            self.direction = Direction::Forward
+        } else if crow == ESE_MoveLast {
+            self.visited_pages.clear(); // if we're going to the end, clear out any previous visited into
+            self.direction = Direction::Backward
+        }
+        // We clear out the visited info if we switch direction while reading a table.
+        // Otherwise if we read forward one row, then backward one row, we are reading the same row and would trigger the circular reference error.
+        else if crow > 0 {
+            // incrementing our row
+            if self.direction == Direction::Backward {
+                self.visited_pages.clear();
+            }
+            self.direction = Direction::Forward
+        } else if crow < 0 {
+            // decrementing our row
+            if self.direction == Direction::Forward {
+                self.visited_pages.clear();
+            }
+            self.direction = Direction::Backward
+        }
+    }
+
+    fn reset_except(&mut self, page: u32) {
+        self.visited_pages.clear();
+        self.visited_pages.push(page);
+    }
+}
+
 #[derive(Default, Debug)]
 struct CurrentPage {
     current_page: Option<jet::DbPage>,
@@ -45,13 +85,52 @@ impl CurrentPage {
     }
 }
 
+// How far a record recovered from a defunct (deleted) page tag can be trusted
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RecoveryStatus {
+    // the record data is not shared with any live tag of the page and all its columns decode
+    Intact,
+    // the record data is intact, but some of its columns can't be decoded
+    // (long values freed together with the record, damaged column data)
+    Damaged,
+    // the record data overlaps data of live tags, the page was reorganized after the delete
+    // and the values may belong to other records
+    Overwritten,
+}
+
+// Long value of the table long value tree that no live record refers to,
+// the leftover of a deleted record
+#[derive(Clone, Debug)]
+pub struct OrphanedLongValue {
+    pub long_value_key: u64,
+    // size of the long value from its root (the size of the data when the root is gone)
+    pub size: u64,
+    // reassembled (and decompressed) data
+    pub data: Vec<u8>,
+}
+
+// Table dropped from the catalog
+#[derive(Debug)]
+pub struct DroppedTable {
+    pub object_identifier: u32,
+    pub name: String,
+    pub father_data_page_number: u32,
+    pub columns: Vec<ColumnInfo>,
+    // the root page of the table was not reused, its records can still be read
+    pub intact: bool,
+}
+
+// Cursor of an opened table, every open_table call gets its own one
 struct Table {
-    cat: Box<jet::TableDefinition>,
+    cat: Rc<jet::TableDefinition>,
+    // moves over the defunct tags (deleted records) instead of the live ones
+    deleted: bool,
     lv_tags: LV_tags,
     current_page: CurrentPage,
     page_tag_index: usize,
     lls: RefCell<LastLoadState>,
     validity_info: ValidityInfo,
+    index_cursor: Option<IndexCursor>,
 }
 
 impl Table {
@@ -71,28 +150,7 @@ impl Table {
     }
 
     fn update_validity_info_for_crow(&mut self, crow: i32) {
-        if crow == ESE_MoveFirst {
-            self.validity_info.visited_pages.clear(); // if we're going to the beginning, clear out any previous visited into
-            self.validity_info.direction = Direction::Forward
-        } else if crow == ESE_MoveLast {
-            self.validity_info.visited_pages.clear(); // if we're going to the end, clear out any previous visited into
-            self.validity_info.direction = Direction::Backward
-        }
-        // We clear out the visited info if we switch direction while reading a table.
-        // Otherwise if we read forward one row, then backward one row, we are reading the same row and would trigger the circular reference error.
-        else if crow > 0 {
-            // incrementing our row
-            if self.validity_info.direction == Direction::Backward {
-                self.validity_info.visited_pages.clear();
-            }
-            self.validity_info.direction = Direction::Forward
-        } else if crow < 0 {
-            // decrementing our row
-            if self.validity_info.direction == Direction::Forward {
-                self.validity_info.visited_pages.clear();
-            }
-            self.validity_info.direction = Direction::Backward
-        }
+        self.validity_info.update_for_crow(crow);
     }
 
     fn already_visited_page(&self, page: u32) -> bool {
@@ -117,16 +175,125 @@ impl Table {
     }
 
     fn reset_visited_pages_except_current(&mut self) {
-        self.validity_info.visited_pages.clear();
         self.validity_info
-            .visited_pages
-            .push(self.current_page.get().page_number);
+            .reset_except(self.current_page.get().page_number);
+    }
+
+    // row tags of the cursor: live tags, or defunct ones for the deleted records cursor
+    fn is_row_tag(&self, page: &jet::DbPage, page_tag_index: usize) -> Result<bool, SimpleError> {
+        Ok(page
+            .tag(page_tag_index)?
+            .flags()
+            .intersects(jet::PageTagFlags::FLAG_IS_DEFUNCT)
+            == self.deleted)
+    }
+
+    fn check_not_deleted(&self, operation: &str) -> Result<(), SimpleError> {
+        if self.deleted {
+            return Err(SimpleError::new(format!(
+                "{} is not supported by the deleted records cursor",
+                operation
+            )));
+        }
+        Ok(())
+    }
+
+    // positions the cursor out of its move sequence (seek, bookmark)
+    fn jump_to(&mut self, page: jet::DbPage, page_tag_index: usize) {
+        self.current_page.set(page);
+        self.page_tag_index = page_tag_index;
+        self.reset_visited_pages_except_current();
+        self.validity_info.direction = Direction::None;
+    }
+}
+
+fn column_info(col: &jet::CatalogDefinition) -> ColumnInfo {
+    ColumnInfo {
+        name: col.name.clone(),
+        id: col.identifier,
+        typ: col.column_type,
+        cbmax: col.size,
+        cp: col.codepage as u16,
+    }
+}
+
+// Cursor over the b-tree of a secondary index, its entries are resolved
+// through the primary bookmark to the data records of the table
+struct IndexCursor {
+    index: usize,
+    current_page: CurrentPage,
+    page_tag_index: usize,
+    validity_info: ValidityInfo,
+    start_key: Option<Vec<u8>>,
+    end_key: Option<Vec<u8>>,
+}
+
+impl IndexCursor {
+    fn new(index: usize) -> Self {
+        IndexCursor {
+            index,
+            current_page: CurrentPage::default(),
+            page_tag_index: 0,
+            validity_info: ValidityInfo::new(),
+            start_key: None,
+            end_key: None,
+        }
+    }
+
+    fn in_range(&self, key: &[u8]) -> bool {
+        self.start_key
+            .as_ref()
+            .is_none_or(|start| key >= start.as_slice())
+            && self
+                .end_key
+                .as_ref()
+                .is_none_or(|end| key <= end.as_slice())
+    }
+
+    fn set_current_page(&mut self, page: jet::DbPage) -> Result<(), SimpleError> {
+        let page_number = page.page_number;
+        if self.current_page.is_none() || self.current_page.get().page_number != page_number {
+            if self.validity_info.visited_pages.contains(&page_number) {
+                return Err(SimpleError::new(format!(
+                    "Circular page reference identified for page_number: {}",
+                    page_number
+                )));
+            }
+            self.validity_info.visited_pages.push(page_number);
+        }
+        self.current_page.set(page);
+        Ok(())
     }
 }
 
 pub struct EseParser<R: ReadSeek> {
     reader: Reader<R>,
-    tables: Vec<RefCell<Table>>,
+    tables: Vec<Rc<jet::TableDefinition>>,
+    // rebuilt from the defunct catalog entries
+    dropped_tables: Vec<Rc<jet::TableDefinition>>,
+    // root page of the catalog the tables were loaded from
+    catalog: jet::FixedPageNumber,
+    // set when the logs were replayed over the database (load_with_logs)
+    replay_report: Option<replay::ReplayReport>,
+    // indexed by the low 32 bits of the table id
+    cursors: RefCell<Vec<CursorSlot>>,
+    // by locale, learned from the text indexes on the first key which needs them
+    sort_weights: RefCell<HashMap<u32, Rc<key::SortWeights>>>,
+}
+
+// Table ids are made of the slot index (low 32 bits) and the generation of the slot
+// (high 32 bits), which changes when the table is closed: the slot is reused by the
+// next open_table, and the ids of closed tables are rejected instead of pointing to it
+#[derive(Default)]
+struct CursorSlot {
+    generation: u32,
+    table: Option<Table>,
+}
+
+impl CursorSlot {
+    fn table_id(&self, index: usize) -> u64 {
+        ((self.generation as u64) << 32) | index as u64
+    }
 }
 
 impl EseParser<BufReader<File>> {
@@ -151,44 +318,177 @@ impl EseParser<BufReader<File>> {
 
 impl<R: ReadSeek> EseParser<R> {
     // reserve room for cache_size recent entries, and cache_size frequent entries
+    // falls back to the shadow copy of the catalog if the catalog can't be loaded
     pub fn load(cache_size: usize, read_seek: R) -> Result<Self, SimpleError> {
         let reader = Reader::load_db(read_seek, cache_size)?;
-        let mut cat = reader.load_catalog()?;
+        let (catalog, cat) = reader.load_any_catalog(false)?;
+        Ok(Self::with_catalog(reader, catalog, cat))
+    }
+
+    // with ChecksumPolicy::Warn the pages failing their checksum are loaded,
+    // and recorded in the corruption report;
+    // with include_dropped the dropped tables are loaded as well
+    pub fn load_with_options(read_seek: R, options: ReaderOptions) -> Result<Self, SimpleError> {
+        let include_dropped = options.include_dropped;
+        let reader = Reader::load_db_with(read_seek, options)?;
+        let (catalog, cat) = reader.load_any_catalog(include_dropped)?;
+        Ok(Self::with_catalog(reader, catalog, cat))
+    }
+
+    // catalog: FixedPageNumber::Catalog or FixedPageNumber::CatalogBackup (its shadow copy)
+    pub fn load_with_catalog(
+        cache_size: usize,
+        read_seek: R,
+        catalog: jet::FixedPageNumber,
+    ) -> Result<Self, SimpleError> {
+        let reader = Reader::load_db(read_seek, cache_size)?;
+        let cat = reader.load_catalog_from(catalog, false)?;
+        Ok(Self::with_catalog(reader, catalog, cat))
+    }
 
+    // replays the committed operations of the logs of log_dir over the pages of the database,
+    // in memory, like a soft recovery (esentutl /r) without modifying the files
+    pub fn load_with_logs(
+        cache_size: usize,
+        read_seek: R,
+        log_dir: impl AsRef<Path>,
+    ) -> Result<Self, SimpleError> {
+        let reader = Reader::load_db(read_seek, cache_size)?;
+        let replay_report = replay::replay_logs(&reader, log_dir)?;
+        let (catalog, cat) = reader.load_any_catalog(false)?;
+        let mut parser = Self::with_catalog(reader, catalog, cat);
+        parser.replay_report = Some(replay_report);
+        Ok(parser)
+    }
+
+    fn with_catalog(
+        reader: Reader<R>,
+        catalog: jet::FixedPageNumber,
+        mut cat: Vec<jet::TableDefinition>,
+    ) -> Self {
         let mut tables = vec![];
+        let mut dropped_tables = vec![];
         for i in cat.drain(0..) {
-            if i.table_catalog_definition.is_some() {
-                let itrnl = Table {
-                    cat: Box::new(i),
-                    lv_tags: HashMap::new(),
-                    current_page: CurrentPage::default(),
-                    page_tag_index: 0,
-                    lls: RefCell::new(LastLoadState {
-                        ..Default::default()
-                    }),
-                    validity_info: ValidityInfo {
-                        visited_pages: vec![],
-                        direction: Direction::None,
-                    },
-                };
-                tables.push(RefCell::new(itrnl));
+            if i.dropped {
+                dropped_tables.push(Rc::new(i));
+            } else if i.table_catalog_definition.is_some() {
+                tables.push(Rc::new(i));
+            }
+        }
+
+        EseParser {
+            reader,
+            tables,
+            dropped_tables,
+            catalog,
+            replay_report: None,
+            cursors: RefCell::new(vec![]),
+            sort_weights: RefCell::new(HashMap::new()),
+        }
+    }
+
+    // the catalog the tables were loaded from
+    pub fn get_catalog(&self) -> jet::FixedPageNumber {
+        self.catalog
+    }
+
+    // None if the database was loaded without its logs
+    pub fn get_replay_report(&self) -> Option<&replay::ReplayReport> {
+        self.replay_report.as_ref()
+    }
+
+    // pages loaded so far despite their checksum errors
+    pub fn get_corruption_report(&self) -> CorruptionReport {
+        self.reader.corruption_report()
+    }
+
+    // checks every page checksum and walks every b-tree of the database
+    pub fn verify(&self) -> Result<verify::IntegrityReport, SimpleError> {
+        verify::verify_database(&self.reader)
+    }
+
+    // parses a transaction log file of the database (edbNNNNN.log, .jrs), the owner of the node
+    // operations is resolved through the catalog
+    pub fn load_log(&self, path: impl AsRef<Path>) -> Result<log::LogFile, SimpleError> {
+        let mut log = log::load_log_file(path)?;
+        let log_signature = self.reader.log_signature();
+        if log.header.log_signature != log_signature {
+            return Err(SimpleError::new(format!(
+                "log signature {:?} doesn't match the database log signature {:?}",
+                log.header.log_signature, log_signature
+            )));
+        }
+
+        let mut objects: HashMap<u32, (space::SpaceObjectType, String)> = HashMap::new();
+        for t in self.tables.iter() {
+            let table = match &t.table_catalog_definition {
+                Some(table) => table,
+                None => continue,
+            };
+            for index in t.index_definition_array.iter() {
+                objects.insert(
+                    index.identifier,
+                    (space::SpaceObjectType::Index, table.name.clone()),
+                );
+            }
+            if let Some(lv) = &t.long_value_catalog_definition {
+                objects.insert(
+                    lv.identifier,
+                    (space::SpaceObjectType::LongValue, table.name.clone()),
+                );
+            }
+            objects.insert(
+                table.identifier,
+                (space::SpaceObjectType::Table, table.name.clone()),
+            );
+        }
+        for record in log.records.iter_mut() {
+            if let Some(node) = record.operation.node_mut() {
+                if let Some((object_type, table)) = objects.get(&node.object_identifier) {
+                    node.object_type = Some(*object_type);
+                    node.table = table.clone();
+                }
             }
         }
+        Ok(log)
+    }
 
-        Ok(EseParser { reader, tables })
+    // changes of the table records from the logs of log_dir, in log order,
+    // with the parts of the logs which could not be decoded
+    pub fn get_timeline(
+        &self,
+        log_dir: impl AsRef<Path>,
+    ) -> Result<timeline::Timeline, SimpleError> {
+        timeline::load_timeline(&self.reader, log_dir)
     }
 
-    fn get_table_by_name(
+    // whether the checkpoint (edb.chk) and the logs of log_dir belong to the database,
+    // and which log generations are needed to recover it
+    pub fn check_file_set(
         &self,
-        table: &str,
-        index: &mut usize,
-    ) -> Result<RefMut<Table>, SimpleError> {
-        for i in 0..self.tables.len() {
-            let n = self.tables[i].borrow_mut();
-            if let Some(table_catalog_definition) = &n.cat.table_catalog_definition {
+        checkpoint_path: Option<impl AsRef<Path>>,
+        log_dir: impl AsRef<Path>,
+    ) -> Result<checkpoint::FileSetReport, SimpleError> {
+        let checkpoint = match checkpoint_path {
+            Some(path) => Some(checkpoint::load_checkpoint_file(path)?),
+            None => None,
+        };
+        let mut mismatches = vec![];
+        let logs = replay::load_logs(&self.reader, log_dir.as_ref(), &mut mismatches)?;
+        let mut report = checkpoint::check_file_set(
+            &self.reader,
+            checkpoint.as_ref(),
+            &logs.into_values().collect::<Vec<_>>(),
+        );
+        report.mismatches.append(&mut mismatches);
+        Ok(report)
+    }
+
+    fn get_table_by_name(&self, table: &str) -> Result<&Rc<jet::TableDefinition>, SimpleError> {
+        for t in &self.tables {
+            if let Some(table_catalog_definition) = &t.table_catalog_definition {
                 if table_catalog_definition.name == table {
-                    *index = i;
-                    return Ok(n);
+                    return Ok(t);
                 }
             }
         }
@@ -199,12 +499,43 @@ impl<R: ReadSeek> EseParser<R> {
         Ok(&self.reader)
     }
 
+    fn table_father_data_page_number(&self, table: &str) -> Result<u32, SimpleError> {
+        Ok(self
+            .get_table_by_name(table)?
+            .table_catalog_definition
+            .as_ref()
+            .ok_or_else(|| SimpleError::new("table catalog definition is missing"))?
+            .father_data_page_number)
+    }
+
+    // approximate number of rows, computed from a few pages of the table b-tree
+    pub fn estimate_rows(&self, table: &str) -> Result<u64, SimpleError> {
+        self.reader
+            .estimate_leaf_tags(self.table_father_data_page_number(table)?)
+    }
+
+    // exact number of rows, counted from the leaf page tags without loading the records
+    pub fn count_rows(&self, table: &str) -> Result<u64, SimpleError> {
+        self.reader
+            .count_leaf_tags(self.table_father_data_page_number(table)?)
+    }
+
+    // returns the primary key of the current row, and its bookmark
+    pub fn get_record_key(&self, table_id: u64) -> Result<(Vec<u8>, Bookmark), SimpleError> {
+        let bookmark = self.bookmark_helper(table_id)?;
+        Ok((bookmark.key.clone(), bookmark))
+    }
+
     fn get_table_by_id(&self, table_id: u64) -> Result<RefMut<Table>, SimpleError> {
-        let i = table_id as usize;
-        if i < self.tables.len() {
-            return Ok(self.tables[i].borrow_mut());
-        }
-        Err(SimpleError::new(format!("out of range index {}", table_id)))
+        let i = (table_id & 0xffffffff) as usize;
+        let generation = (table_id >> 32) as u32;
+        RefMut::filter_map(self.cursors.borrow_mut(), |cursors| {
+            cursors
+                .get_mut(i)
+                .filter(|c| c.generation == generation)
+                .and_then(|c| c.table.as_mut())
+        })
+        .map_err(|_| SimpleError::new(format!("table id {} is not open", table_id)))
     }
 
     fn get_column_dyn_helper(
@@ -240,6 +571,9 @@ impl<R: ReadSeek> EseParser<R> {
     }
 
     fn move_next_row(&self, table_id: u64, crow: i32) -> Result<bool, SimpleError> {
+        if self.get_table_by_id(table_id)?.index_cursor.is_some() {
+            return self.move_index_row(table_id, crow);
+        }
         let reader = self.get_reader()?;
         let mut t = self.get_table_by_id(table_id)?;
         t.update_validity_info_for_crow(crow);
@@ -266,12 +600,7 @@ impl<R: ReadSeek> EseParser<R> {
             i = 1;
         }
         loop {
-            while i < t.page().tags()
-                && t.page()
-                    .tag(i)?
-                    .flags()
-                    .intersects(jet::PageTagFlags::FLAG_IS_DEFUNCT)
-            {
+            while i < t.page().tags() && !t.is_row_tag(t.page(), i)? {
                 i += 1;
             }
             if i < t.page().tags() {
@@ -290,6 +619,9 @@ impl<R: ReadSeek> EseParser<R> {
     }
 
     fn move_previous_row(&self, table_id: u64, crow: i32) -> Result<bool, SimpleError> {
+        if self.get_table_by_id(table_id)?.index_cursor.is_some() {
+            return self.move_index_row(table_id, crow);
+        }
         let reader = self.get_reader()?;
         let mut t = self.get_table_by_id(table_id)?;
         t.update_validity_info_for_crow(crow);
@@ -300,12 +632,17 @@ impl<R: ReadSeek> EseParser<R> {
             0
         };
         if crow == ESE_MoveLast {
-            while t.page().common().next_page != 0 {
-                let page = jet::DbPage::new(reader, t.page().common().next_page)?;
-                t.set_current_page(page)?;
+            let last_leaf_page = reader.find_last_leaf_page(
+                t.cat
+                    .table_catalog_definition
+                    .as_ref()
+                    .expect("Last leaf page failed")
+                    .father_data_page_number,
+            )?;
+            if t.current_page.is_none() || t.page().page_number != last_leaf_page {
+                let page = jet::DbPage::new(reader, last_leaf_page)?;
+                t.current_page.set(page);
             }
-            // in previous step we visited all pages till the end
-            // now need to reset visited pages again, except last page
             t.reset_visited_pages_except_current();
 
             if t.page().tags() < 2 {
@@ -315,12 +652,7 @@ impl<R: ReadSeek> EseParser<R> {
             i = t.page().tags() - 1;
         }
         loop {
-            while i > 0
-                && t.page()
-                    .tag(i)?
-                    .flags()
-                    .intersects(jet::PageTagFlags::FLAG_IS_DEFUNCT)
-            {
+            while i > 0 && !t.is_row_tag(t.page(), i)? {
                 i -= 1;
             }
             if i > 0 {
@@ -343,7 +675,12 @@ impl<R: ReadSeek> EseParser<R> {
             self.move_next_row(table_id, crow)
         } else if crow == ESE_MoveLast || crow == ESE_MovePrevious {
             self.move_previous_row(table_id, crow)
+        } else if crow == 0 {
+            Ok(true)
         } else {
+            if self.get_table_by_id(table_id)?.index_cursor.is_none() {
+                return self.skip_rows(table_id, crow);
+            }
             match crow.cmp(&0) {
                 Ordering::Greater => {
                     for _ in 0..crow {
@@ -380,6 +717,714 @@ impl<R: ReadSeek> EseParser<R> {
         }
     }
 
+    // moves crow rows forward (or backward, if negative) counting the live tags of the leaf pages,
+    // pages ahead of the target are skipped without looking at their records;
+    // if there are not enough rows the cursor stays on its row and false is returned
+    fn skip_rows(&self, table_id: u64, crow: i32) -> Result<bool, SimpleError> {
+        let reader = self.get_reader()?;
+        let mut t = self.get_table_by_id(table_id)?;
+        if t.current_page.is_none() {
+            return Err(SimpleError::new(
+                "no current page, use open_table API before this",
+            ));
+        }
+        let forward = crow > 0;
+        t.update_validity_info_for_crow(if forward {
+            ESE_MoveNext
+        } else {
+            ESE_MovePrevious
+        });
+
+        let deleted = t.deleted;
+        let live_tags = |page: &jet::DbPage, from: usize| -> Result<Vec<usize>, SimpleError> {
+            let range: Vec<usize> = if forward {
+                (from..page.tags()).collect()
+            } else {
+                (1..from.min(page.tags())).rev().collect()
+            };
+            let mut tags = vec![];
+            for i in range {
+                if page
+                    .tag(i)?
+                    .flags()
+                    .intersects(jet::PageTagFlags::FLAG_IS_DEFUNCT)
+                    == deleted
+                {
+                    tags.push(i);
+                }
+            }
+            Ok(tags)
+        };
+
+        let start = (t.page().clone(), t.page_tag_index);
+        let mut rows = crow.unsigned_abs() as usize;
+        let mut tags = live_tags(
+            t.page(),
+            if forward {
+                t.page_tag_index + 1
+            } else {
+                t.page_tag_index
+            },
+        )?;
+        loop {
+            if rows <= tags.len() {
+                t.page_tag_index = tags[rows - 1];
+                return Ok(true);
+            }
+            rows -= tags.len();
+
+            let page_number = if forward {
+                t.page().common().next_page
+            } else {
+                t.page().common().previous_page
+            };
+            if page_number == 0 {
+                // no more leaf pages
+                let (page, i) = start;
+                t.current_page.set(page);
+                t.page_tag_index = i;
+                t.reset_visited_pages_except_current();
+                return Ok(false);
+            }
+            let page = jet::DbPage::new(reader, page_number)?;
+            t.set_current_page(page)?;
+            tags = live_tags(t.page(), if forward { 1 } else { t.page().tags() })?;
+        }
+    }
+
+    fn goto_position_helper(&self, table_id: u64, fraction: f64) -> Result<bool, SimpleError> {
+        if !(0.0..=1.0).contains(&fraction) {
+            return Err(SimpleError::new(format!(
+                "position {} is out of range [0, 1]",
+                fraction
+            )));
+        }
+        let reader = self.get_reader()?;
+        let mut table = self.get_table_by_id(table_id)?;
+        let t = &mut *table;
+        t.check_not_deleted("goto_position")?;
+        let father_data_page_number = match &t.index_cursor {
+            Some(cursor) => t.cat.index_definition_array[cursor.index].father_data_page_number,
+            None => {
+                t.cat
+                    .table_catalog_definition
+                    .as_ref()
+                    .ok_or_else(|| SimpleError::new("table catalog definition is missing"))?
+                    .father_data_page_number
+            }
+        };
+
+        let (page_number, fraction) =
+            reader.find_leaf_page_at(father_data_page_number, fraction)?;
+        let page = jet::DbPage::new(reader, page_number)?;
+        let live: Vec<usize> = (1..page.tags())
+            .filter(|&i| {
+                page.tag(i)
+                    .is_ok_and(|tag| !tag.flags().intersects(jet::PageTagFlags::FLAG_IS_DEFUNCT))
+            })
+            .collect();
+        let found = if live.is_empty() {
+            // the leaf page was emptied, take the nearest row
+            let last = page.tags().saturating_sub(1);
+            match reader.find_leaf_tag(page.clone(), 1, true, |_, _| Ok(true))? {
+                Some(found) => Some(found),
+                None => reader.find_leaf_tag(page, last, false, |_, _| Ok(true))?,
+            }
+        } else {
+            let n = live.len();
+            let i = ((fraction * n as f64) as usize).min(n - 1);
+            Some((page, live[i]))
+        };
+
+        match found {
+            Some((page, i)) if t.index_cursor.is_some() => self.jump_to_index_position(t, page, i),
+            Some((page, i)) => {
+                t.jump_to(page, i);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    // positions the table on the data record referred by the index entry (tag i of page)
+    fn set_index_position(
+        &self,
+        t: &mut Table,
+        page: jet::DbPage,
+        i: usize,
+    ) -> Result<bool, SimpleError> {
+        let reader = self.get_reader()?;
+        let cursor = t
+            .index_cursor
+            .as_mut()
+            .ok_or_else(|| SimpleError::new("no current secondary index"))?;
+        let (key, bookmark) = reader.load_index_entry(&page, page.tag(i)?)?;
+        if !cursor.in_range(&key) {
+            return Ok(false);
+        }
+        let father_data_page_number = t
+            .cat
+            .table_catalog_definition
+            .as_ref()
+            .ok_or_else(|| SimpleError::new("table catalog definition is missing"))?
+            .father_data_page_number;
+        let (data_page, data_page_tag_index) = reader
+            .seek_leaf_tag(father_data_page_number, &bookmark, SeekMode::Eq)?
+            .ok_or_else(|| {
+                SimpleError::new(format!(
+                    "index {}: record with bookmark {:?} not found",
+                    t.cat.index_definition_array[cursor.index].name, bookmark
+                ))
+            })?;
+        cursor.set_current_page(page)?;
+        cursor.page_tag_index = i;
+        t.current_page.set(data_page);
+        t.page_tag_index = data_page_tag_index;
+        Ok(true)
+    }
+
+    fn move_index_row(&self, table_id: u64, crow: i32) -> Result<bool, SimpleError> {
+        let reader = self.get_reader()?;
+        let mut table = self.get_table_by_id(table_id)?;
+        let t = &mut *table;
+        let cursor = t
+            .index_cursor
+            .as_mut()
+            .ok_or_else(|| SimpleError::new("no current secondary index"))?;
+        cursor.validity_info.update_for_crow(crow);
+        let father_data_page_number =
+            t.cat.index_definition_array[cursor.index].father_data_page_number;
+
+        let found = if crow == ESE_MoveFirst {
+            match &cursor.start_key {
+                Some(start_key) => {
+                    reader.seek_leaf_tag(father_data_page_number, start_key, SeekMode::Ge)?
+                }
+                None => {
+                    let page = jet::DbPage::new(
+                        reader,
+                        reader.find_first_leaf_page(father_data_page_number)?,
+                    )?;
+                    reader.find_leaf_tag(page, 1, true, |_, _| Ok(true))?
+                }
+            }
+        } else if crow == ESE_MoveLast {
+            match &cursor.end_key {
+                Some(end_key) => {
+                    reader.seek_leaf_tag(father_data_page_number, end_key, SeekMode::Le)?
+                }
+                None => {
+                    let page = jet::DbPage::new(
+                        reader,
+                        reader.find_last_leaf_page(father_data_page_number)?,
+                    )?;
+                    let last = page.tags().saturating_sub(1);
+                    reader.find_leaf_tag(page, last, false, |_, _| Ok(true))?
+                }
+            }
+        } else {
+            if cursor.current_page.is_none() {
+                return Err(SimpleError::new(
+                    "no current index entry, use ESE_MoveFirst or seek before this",
+                ));
+            }
+            let page = cursor.current_page.get().clone();
+            if crow > 0 {
+                reader.find_leaf_tag(page, cursor.page_tag_index + 1, true, |_, _| Ok(true))?
+            } else {
+                reader.find_leaf_tag(page, cursor.page_tag_index - 1, false, |_, _| Ok(true))?
+            }
+        };
+
+        match found {
+            Some((page, i)) => self.set_index_position(t, page, i),
+            None => Ok(false),
+        }
+    }
+
+    fn seek_helper(
+        &self,
+        table_id: u64,
+        key_values: &[&[u8]],
+        mode: SeekMode,
+    ) -> Result<bool, SimpleError> {
+        let reader = self.get_reader()?;
+        let (cat, index) = {
+            let t = self.get_table_by_id(table_id)?;
+            t.check_not_deleted("seek")?;
+            (
+                t.cat.clone(),
+                t.index_cursor.as_ref().map(|cursor| cursor.index),
+            )
+        };
+        let key = match index
+            .map(|i| &cat.index_definition_array[i])
+            .or(cat.primary_index())
+        {
+            Some(index) => self.make_key(&cat, index, key_values)?,
+            None => key::make_primary_key(&cat, key_values)?,
+        };
+        let mut table = self.get_table_by_id(table_id)?;
+        let t = &mut *table;
+
+        if let Some(i) = index {
+            let index = &t.cat.index_definition_array[i];
+            return match reader.seek_leaf_tag(index.father_data_page_number, &key, mode)? {
+                Some((page, i)) => self.jump_to_index_position(t, page, i),
+                None => Ok(false),
+            };
+        }
+
+        let father_data_page_number = t
+            .cat
+            .table_catalog_definition
+            .as_ref()
+            .ok_or_else(|| SimpleError::new("table catalog definition is missing"))?
+            .father_data_page_number;
+        match reader.seek_leaf_tag(father_data_page_number, &key, mode)? {
+            Some((page, i)) => {
+                t.jump_to(page, i);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    // positions the index cursor out of its move sequence,
+    // keeps the current position if the entry is out of the index range
+    fn jump_to_index_position(
+        &self,
+        t: &mut Table,
+        page: jet::DbPage,
+        i: usize,
+    ) -> Result<bool, SimpleError> {
+        let cursor = t
+            .index_cursor
+            .as_mut()
+            .ok_or_else(|| SimpleError::new("no current secondary index"))?;
+        let validity_info = std::mem::replace(&mut cursor.validity_info, ValidityInfo::new());
+        if !self.set_index_position(t, page, i)? {
+            t.index_cursor
+                .as_mut()
+                .expect("index cursor is set")
+                .validity_info = validity_info;
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    // builds the key of the index, the characters out of the sort weight table take the
+    // weights learned from the keys of the text indexes with the same locale
+    fn make_key(
+        &self,
+        tbl_def: &jet::TableDefinition,
+        index: &jet::IndexDefinition,
+        values: &[&[u8]],
+    ) -> Result<Vec<u8>, SimpleError> {
+        if !key::needs_learned_weights(tbl_def, index, values) {
+            return key::make_key(tbl_def, index, values);
+        }
+        let weights = self.learned_sort_weights(index.locale_identifier)?;
+        let values: Vec<Option<&[u8]>> = values.iter().map(|v| Some(*v)).collect();
+        key::make_key_with_weights(tbl_def, index, &values, &weights)
+    }
+
+    fn learned_sort_weights(
+        &self,
+        locale_identifier: u32,
+    ) -> Result<Rc<key::SortWeights>, SimpleError> {
+        if let Some(weights) = self.sort_weights.borrow().get(&locale_identifier) {
+            return Ok(weights.clone());
+        }
+        let mut samples: Vec<key::SortKeySample> = vec![];
+        for tbl_def in self.tables.iter() {
+            for index in tbl_def.index_definition_array.iter() {
+                if index.locale_identifier == locale_identifier
+                    && key::has_sort_key_text(tbl_def, index)
+                {
+                    self.load_sort_key_samples(tbl_def, index, &mut samples)?;
+                }
+            }
+        }
+        let weights = Rc::new(key::SortWeights::learn(&samples));
+        self.sort_weights
+            .borrow_mut()
+            .insert(locale_identifier, weights.clone());
+        Ok(weights)
+    }
+
+    // the sort keys of every entry of the index with the text values of its record
+    fn load_sort_key_samples(
+        &self,
+        tbl_def: &jet::TableDefinition,
+        index: &jet::IndexDefinition,
+        samples: &mut Vec<key::SortKeySample>,
+    ) -> Result<(), SimpleError> {
+        let table = &tbl_def
+            .table_catalog_definition
+            .as_ref()
+            .ok_or_else(|| SimpleError::new("table catalog definition is missing"))?
+            .name;
+        let table_id = self.open_table(table)?;
+        let mut load = || -> Result<(), SimpleError> {
+            let mut found = self.set_current_index(table_id, Some(&index.name))?;
+            while found {
+                let bookmark = self.bookmark_helper(table_id)?;
+                let values = index
+                    .key_columns
+                    .iter()
+                    .map(|segment| self.get_column(table_id, segment.column_identifier))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let values: Vec<Option<&[u8]>> = values.iter().map(|v| v.as_deref()).collect();
+                let key = bookmark.index_key.unwrap_or(bookmark.key);
+                samples.append(&mut key::sort_key_samples(tbl_def, index, &key, &values)?);
+                found = self.move_row(table_id, ESE_MoveNext)?;
+            }
+            Ok(())
+        };
+        let result = load();
+        self.close_table(table_id);
+        result
+    }
+
+    fn bookmark_helper(&self, table_id: u64) -> Result<Bookmark, SimpleError> {
+        let t = self.get_table_by_id(table_id)?;
+        if t.current_page.is_none() {
+            return Err(SimpleError::new(
+                "no current page, use open_table API before this",
+            ));
+        }
+        let reader = self.get_reader()?;
+        let page = t.page();
+        let index_key = match &t.index_cursor {
+            Some(cursor) if !cursor.current_page.is_none() => {
+                let index_page = cursor.current_page.get();
+                let (key, _) =
+                    reader.load_index_entry(index_page, index_page.tag(cursor.page_tag_index)?)?;
+                Some(key)
+            }
+            _ => None,
+        };
+        Ok(Bookmark {
+            key: reader.load_record_key(page, t.page_tag_index)?,
+            index_key,
+            page_number: page.page_number,
+            page_tag_index: t.page_tag_index,
+        })
+    }
+
+    fn goto_bookmark_helper(
+        &self,
+        table_id: u64,
+        bookmark: &Bookmark,
+    ) -> Result<bool, SimpleError> {
+        let reader = self.get_reader()?;
+        let mut table = self.get_table_by_id(table_id)?;
+        let t = &mut *table;
+        t.check_not_deleted("goto_bookmark")?;
+
+        if let Some(cursor) = &t.index_cursor {
+            let index = &t.cat.index_definition_array[cursor.index];
+            let index_key = bookmark.index_key.as_ref().ok_or_else(|| {
+                SimpleError::new(format!(
+                    "bookmark was not taken on a secondary index, can't go to it on index {}",
+                    index.name
+                ))
+            })?;
+            // entries with equal index keys are ordered by the primary key
+            let found = match reader.seek_leaf_tag(
+                index.father_data_page_number,
+                index_key,
+                SeekMode::Ge,
+            )? {
+                Some((page, i)) => reader.find_leaf_tag(page, i, true, |p, tag| {
+                    let (k, b) = reader.load_index_entry(p, tag)?;
+                    Ok(&k > index_key || (&k == index_key && b == bookmark.key))
+                })?,
+                None => None,
+            };
+            return match found {
+                Some((page, i))
+                    if reader.load_index_entry(&page, page.tag(i)?)?
+                        == (index_key.clone(), bookmark.key.clone()) =>
+                {
+                    self.jump_to_index_position(t, page, i)
+                }
+                _ => Ok(false),
+            };
+        }
+
+        let table_catalog_definition = t
+            .cat
+            .table_catalog_definition
+            .as_ref()
+            .ok_or_else(|| SimpleError::new("table catalog definition is missing"))?;
+        // the record may have moved since the bookmark was taken, the page is only a hint
+        let is_at = |page: &jet::DbPage, i: usize| -> Result<bool, SimpleError> {
+            Ok(page.common().father_data_page_object_identifier
+                == table_catalog_definition.father_data_page_object_identifier
+                && !page
+                    .tag(i)?
+                    .flags()
+                    .intersects(jet::PageTagFlags::FLAG_IS_DEFUNCT)
+                && reader.load_record_key(page, i)? == bookmark.key)
+        };
+        let hint = jet::DbPage::new(reader, bookmark.page_number)
+            .ok()
+            .filter(|page| is_at(page, bookmark.page_tag_index).unwrap_or(false))
+            .map(|page| (page, bookmark.page_tag_index));
+        let found = match hint {
+            Some(found) => Some(found),
+            None => reader
+                .seek_leaf_tag(
+                    table_catalog_definition.father_data_page_number,
+                    &bookmark.key,
+                    SeekMode::Eq,
+                )?
+                .filter(|(page, i)| is_at(page, *i).unwrap_or(false)),
+        };
+        match found {
+            Some((page, i)) => {
+                t.jump_to(page, i);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    fn open_cursor(
+        &self,
+        cat: Rc<jet::TableDefinition>,
+        deleted: bool,
+    ) -> Result<u64, SimpleError> {
+        let mut lv_tags = HashMap::new();
+        if let Some(long_value_catalog_definition) = &cat.long_value_catalog_definition {
+            // the long value tree of a dropped table may have been reused already
+            if !cat.dropped
+                || self.is_tree_intact(
+                    long_value_catalog_definition.father_data_page_number,
+                    long_value_catalog_definition.identifier,
+                )
+            {
+                let reader = self.get_reader()?;
+                lv_tags = reader
+                    .load_lv_metadata(long_value_catalog_definition.father_data_page_number)?;
+            }
+        }
+        let t = Table {
+            cat,
+            deleted,
+            lv_tags,
+            current_page: CurrentPage::default(),
+            page_tag_index: 0,
+            lls: RefCell::new(LastLoadState {
+                ..Default::default()
+            }),
+            validity_info: ValidityInfo::new(),
+            index_cursor: None,
+        };
+
+        // reuse the slot of a closed table
+        let (index, table_id) = {
+            let mut cursors = self.cursors.borrow_mut();
+            let index = match cursors.iter().position(|c| c.table.is_none()) {
+                Some(i) => i,
+                None => {
+                    cursors.push(CursorSlot::default());
+                    cursors.len() - 1
+                }
+            };
+            cursors[index].table = Some(t);
+            (index, cursors[index].table_id(index))
+        };
+        // ignore return result
+        if let Err(e) = self.move_row_helper(table_id, ESE_MoveFirst) {
+            self.close_slot(index);
+            return Err(e);
+        }
+
+        Ok(table_id)
+    }
+
+    // frees the cursor of the slot, the ids given out for it are no longer valid
+    fn close_slot(&self, index: usize) {
+        let slot = &mut self.cursors.borrow_mut()[index];
+        slot.table = None;
+        slot.generation = slot.generation.wrapping_add(1);
+    }
+
+    // opens a cursor over the deleted records of the table: the defunct tags of its leaf pages,
+    // which keep the record data until the page is reorganized;
+    // seek, goto_bookmark, goto_position and secondary indexes are not supported by this cursor
+    pub fn open_deleted(&self, table: &str) -> Result<u64, SimpleError> {
+        self.open_cursor(self.get_table_by_name(table)?.clone(), true)
+    }
+
+    // the root page of the tree still belongs to the object
+    fn is_tree_intact(&self, father_data_page_number: u32, object_identifier: u32) -> bool {
+        jet::DbPage::new(&self.reader, father_data_page_number).is_ok_and(|page| {
+            page.flags().contains(jet::PageFlags::IS_ROOT)
+                && page.common().father_data_page_object_identifier == object_identifier
+        })
+    }
+
+    // tables dropped from the catalog, rebuilt from its defunct entries;
+    // empty unless the database was loaded with ReaderOptions::include_dropped
+    pub fn get_dropped_tables(&self) -> Vec<DroppedTable> {
+        let mut tables = vec![];
+        for t in &self.dropped_tables {
+            if let Some(table) = &t.table_catalog_definition {
+                tables.push(DroppedTable {
+                    object_identifier: table.identifier,
+                    name: table.name.clone(),
+                    father_data_page_number: table.father_data_page_number,
+                    columns: t
+                        .column_catalog_definition_array
+                        .iter()
+                        .map(column_info)
+                        .collect(),
+                    intact: self.is_tree_intact(table.father_data_page_number, table.identifier),
+                });
+            }
+        }
+        tables
+    }
+
+    // opens a dropped table, its data tree is walked as long as its root page was not reused
+    pub fn open_dropped_table(&self, object_identifier: u32) -> Result<u64, SimpleError> {
+        let cat = self
+            .dropped_tables
+            .iter()
+            .find(|t| {
+                t.table_catalog_definition
+                    .as_ref()
+                    .is_some_and(|table| table.identifier == object_identifier)
+            })
+            .ok_or_else(|| {
+                SimpleError::new(format!("can't find dropped table {}", object_identifier))
+            })?;
+        let table = cat
+            .table_catalog_definition
+            .as_ref()
+            .expect("dropped tables are coming from table_catalog_definition");
+        if !self.is_tree_intact(table.father_data_page_number, object_identifier) {
+            return Err(SimpleError::new(format!(
+                "father data page {} of dropped table {} was reused",
+                table.father_data_page_number, table.name
+            )));
+        }
+        self.open_cursor(cat.clone(), false)
+    }
+
+    // checks the deleted record of the cursor against the live tags of its page
+    // and decodes all its columns
+    pub fn get_recovery_status(&self, table_id: u64) -> Result<RecoveryStatus, SimpleError> {
+        let columns: Vec<u32> = {
+            let t = self.get_table_by_id(table_id)?;
+            if !t.deleted {
+                return Err(SimpleError::new(
+                    "not a deleted records cursor, use open_deleted API before this",
+                ));
+            }
+            if t.current_page.is_none() || !t.is_row_tag(t.page(), t.page_tag_index)? {
+                return Err(SimpleError::new("no current deleted record"));
+            }
+            let page = t.page();
+            let tag = page.tag(t.page_tag_index)?;
+            let (start, end) = (tag.offset, tag.offset + tag.size);
+            for i in 0..page.tags() {
+                let other = page.tag(i)?;
+                if !other.flags().intersects(jet::PageTagFlags::FLAG_IS_DEFUNCT)
+                    && other.offset < end
+                    && start < other.offset + other.size
+                {
+                    return Ok(RecoveryStatus::Overwritten);
+                }
+            }
+            t.cat
+                .column_catalog_definition_array
+                .iter()
+                .map(|col| col.identifier)
+                .collect()
+        };
+        for column in columns {
+            if self.get_column_dyn_helper(table_id, column, 0).is_err() {
+                return Ok(RecoveryStatus::Damaged);
+            }
+        }
+        Ok(RecoveryStatus::Intact)
+    }
+
+    // long values of the table that are not referred by any live record of its leaf pages
+    pub fn load_orphaned_long_values(
+        &self,
+        table: &str,
+    ) -> Result<Vec<OrphanedLongValue>, SimpleError> {
+        let reader = self.get_reader()?;
+        let cat = self.get_table_by_name(table)?;
+        let long_value_catalog_definition = match &cat.long_value_catalog_definition {
+            Some(lv) => lv,
+            None => return Ok(vec![]),
+        };
+        let lv_tags =
+            reader.load_lv_metadata(long_value_catalog_definition.father_data_page_number)?;
+
+        let mut referred: BTreeSet<u64> = BTreeSet::new();
+        let mut page_number =
+            reader.find_first_leaf_page(self.table_father_data_page_number(table)?)?;
+        let mut visited_pages: BTreeSet<u32> = BTreeSet::new();
+        while page_number != 0 {
+            if !visited_pages.insert(page_number) {
+                return Err(SimpleError::new(format!(
+                    "Circular page reference identified for page_number: {}",
+                    page_number
+                )));
+            }
+            let db_page = jet::DbPage::new(reader, page_number)?;
+            for i in 1..db_page.tags() {
+                if !db_page
+                    .tag(i)?
+                    .flags()
+                    .intersects(jet::PageTagFlags::FLAG_IS_DEFUNCT)
+                {
+                    referred.extend(reader.load_record_lv_keys(&db_page, i)?);
+                }
+            }
+            page_number = db_page.next_page();
+        }
+
+        let lv_roots =
+            reader.load_lv_roots(long_value_catalog_definition.father_data_page_number)?;
+        let mut long_value_keys: Vec<u64> = lv_tags
+            .keys()
+            .filter(|key| !referred.contains(key))
+            .copied()
+            .collect();
+        long_value_keys.sort();
+        let mut orphans = vec![];
+        for long_value_key in long_value_keys {
+            let chunks = &lv_tags[&long_value_key];
+            let stored_size: u64 = chunks.values().map(|tag| tag.size as u64).sum();
+            let root = lv_roots.get(&long_value_key);
+            // the column of an orphan is unknown: its chunks are compressed if they are stored
+            // in less than the size of its root, and the data header of the first one names
+            // a compression scheme
+            let first_chunk = match chunks.get(&0) {
+                Some(tag) => reader.read_bytes(tag.offset, tag.size as usize)?,
+                None => vec![],
+            };
+            let compressed = root.is_none_or(|root| root.size as u64 != stored_size)
+                && decomp::decompress_size(&first_chunk) > 0;
+            let data = reader.load_lv_data(&lv_tags, long_value_key, compressed)?;
+            orphans.push(OrphanedLongValue {
+                long_value_key,
+                size: root.map_or(data.len() as u64, |root| root.size as u64),
+                data,
+            });
+        }
+        Ok(orphans)
+    }
+
     pub fn get_fixed_column<T: FromBytes>(
         &self,
         table: u64,
@@ -404,10 +1449,8 @@ impl<R: ReadSeek> EseDb for EseParser<R> {
     fn get_tables(&self) -> Result<Vec<String>, SimpleError> {
         let mut tables: Vec<String> = vec![];
         for i in &self.tables {
-            let n = i.borrow();
             tables.push(
-                n.cat
-                    .table_catalog_definition
+                i.table_catalog_definition
                     .as_ref()
                     .expect("tables are coming from table_catalog_definition")
                     .name
@@ -418,47 +1461,49 @@ impl<R: ReadSeek> EseDb for EseParser<R> {
     }
 
     fn open_table(&self, table: &str) -> Result<u64, SimpleError> {
-        let mut index: usize = 0;
-        {
-            // used to drop borrow mut
-            let mut t = self.get_table_by_name(table, &mut index)?;
-            if let Some(long_value_catalog_definition) = &t.cat.long_value_catalog_definition {
-                let reader = self.get_reader()?;
-                t.lv_tags = reader
-                    .load_lv_metadata(long_value_catalog_definition.father_data_page_number)?;
-            }
-        }
-        // ignore return result
-        self.move_row_helper(index as u64, ESE_MoveFirst)?;
-
-        Ok(index as u64)
+        self.open_cursor(self.get_table_by_name(table)?.clone(), false)
     }
 
     fn close_table(&self, table: u64) -> bool {
-        let tags_index = table as usize;
-        if tags_index < self.tables.len() {
-            let mut itrnl = self.tables[tags_index].borrow_mut();
-            itrnl.lv_tags.clear();
-            return true;
+        if self.get_table_by_id(table).is_err() {
+            return false;
         }
-        false
+        self.close_slot((table & 0xffffffff) as usize);
+        true
     }
 
     fn get_columns(&self, table: &str) -> Result<Vec<ColumnInfo>, SimpleError> {
-        let mut index: usize = 0;
-        let t = self.get_table_by_name(table, &mut index)?;
-        let mut columns: Vec<ColumnInfo> = vec![];
-        for i in &t.cat.column_catalog_definition_array {
-            let col_info = ColumnInfo {
+        let t = self.get_table_by_name(table)?;
+        Ok(t.column_catalog_definition_array
+            .iter()
+            .map(column_info)
+            .collect())
+    }
+
+    fn get_indexes(&self, table: &str) -> Result<Vec<IndexInfo>, SimpleError> {
+        let t = self.get_table_by_name(table)?;
+        let mut indexes: Vec<IndexInfo> = vec![];
+        for i in &t.index_definition_array {
+            let index_info = IndexInfo {
                 name: i.name.clone(),
                 id: i.identifier,
-                typ: i.column_type,
-                cbmax: i.size,
-                cp: i.codepage as u16,
+                fdp: i.father_data_page_number,
+                columns: i
+                    .key_columns
+                    .iter()
+                    .map(|seg| IndexColumnInfo {
+                        id: seg.column_identifier,
+                        descending: seg.is_descending(),
+                    })
+                    .collect(),
+                unique: i.is_unique(),
+                primary: i.is_primary(),
+                conditional: i.is_conditional(),
+                lcid: i.locale_identifier,
             };
-            columns.push(col_info);
+            indexes.push(index_info);
         }
-        Ok(columns)
+        Ok(indexes)
     }
 
     fn move_row(&self, table: u64, crow: i32) -> Result<bool, SimpleError> {
@@ -466,6 +1511,83 @@ impl<R: ReadSeek> EseDb for EseParser<R> {
             .map_err(|e| SimpleError::new(format!("move_row failed: {:?}", e)))
     }
 
+    fn seek(&self, table: u64, key_values: &[&[u8]], mode: SeekMode) -> Result<bool, SimpleError> {
+        self.seek_helper(table, key_values, mode)
+            .map_err(|e| SimpleError::new(format!("seek failed: {:?}", e)))
+    }
+
+    fn set_current_index(&self, table: u64, index: Option<&str>) -> Result<bool, SimpleError> {
+        {
+            let mut t = self.get_table_by_id(table)?;
+            t.check_not_deleted("set_current_index")?;
+            t.index_cursor = match index {
+                Some(name) => {
+                    let i = t
+                        .cat
+                        .index_definition_array
+                        .iter()
+                        .position(|idx| idx.name == name)
+                        .ok_or_else(|| SimpleError::new(format!("can't find index {}", name)))?;
+                    if t.cat.index_definition_array[i].is_primary() {
+                        // records are stored in the primary index order
+                        None
+                    } else {
+                        Some(IndexCursor::new(i))
+                    }
+                }
+                None => None,
+            };
+        }
+        self.move_row_helper(table, ESE_MoveFirst)
+    }
+
+    fn set_index_range(
+        &self,
+        table: u64,
+        start_key_values: Option<&[&[u8]]>,
+        end_key_values: Option<&[&[u8]]>,
+    ) -> Result<bool, SimpleError> {
+        {
+            let (cat, i) = {
+                let t = self.get_table_by_id(table)?;
+                let cursor = t.index_cursor.as_ref().ok_or_else(|| {
+                    SimpleError::new(
+                        "no current secondary index, use set_current_index before this",
+                    )
+                })?;
+                (t.cat.clone(), cursor.index)
+            };
+            let index = &cat.index_definition_array[i];
+            let start_key = start_key_values
+                .map(|values| self.make_key(&cat, index, values))
+                .transpose()?;
+            let end_key = end_key_values
+                .map(|values| {
+                    self.make_key(&cat, index, values)
+                        .map(key::prefix_upper_bound)
+                })
+                .transpose()?;
+            let mut t = self.get_table_by_id(table)?;
+            if let Some(cursor) = t.index_cursor.as_mut() {
+                cursor.start_key = start_key;
+                cursor.end_key = end_key;
+            }
+        }
+        self.move_row_helper(table, ESE_MoveFirst)
+    }
+
+    fn get_bookmark(&self, table: u64) -> Result<Bookmark, SimpleError> {
+        self.bookmark_helper(table)
+    }
+
+    fn goto_position(&self, table: u64, fraction: f64) -> Result<bool, SimpleError> {
+        self.goto_position_helper(table, fraction)
+    }
+
+    fn goto_bookmark(&self, table: u64, bookmark: &Bookmark) -> Result<bool, SimpleError> {
+        self.goto_bookmark_helper(table, bookmark)
+    }
+
     fn get_column(&self, table: u64, column: u32) -> Result<Option<Vec<u8>>, SimpleError> {
         self.get_column_dyn_helper(table, column, 0)
     }
@@ -556,21 +1678,22 @@ mod tests {
         let table_definition = jet::TableDefinition {
             table_catalog_definition: None,
             column_catalog_definition_array: vec![],
+            index_definition_array: vec![],
             long_value_catalog_definition: None,
+            dropped: false,
         };
 
         Table {
-            cat: Box::new(table_definition),
+            cat: Rc::new(table_definition),
+            deleted: false,
             lv_tags: HashMap::new(),
             current_page: CurrentPage::default(),
             page_tag_index: 0,
             lls: RefCell::new(LastLoadState {
                 ..Default::default()
             }),
-            validity_info: ValidityInfo {
-                visited_pages: vec![],
-                direction: Direction::None,
-            },
+            validity_info: ValidityInfo::new(),
+            index_cursor: None,
         }
     }
 
@@ -14,6 +14,24 @@ pub struct ColumnInfo {
     pub cp: u16,
 }
 
+#[derive(Debug)]
+pub struct IndexColumnInfo {
+    pub id: u32,
+    pub descending: bool,
+}
+
+#[derive(Debug)]
+pub struct IndexInfo {
+    pub name: String,
+    pub id: u32,
+    pub fdp: u32,
+    pub columns: Vec<IndexColumnInfo>,
+    pub unique: bool,
+    pub primary: bool,
+    pub conditional: bool,
+    pub lcid: u32,
+}
+
 #[derive(Debug, PartialEq)]
 pub enum ESE_CP {
     None = 0,
@@ -59,6 +77,27 @@ pub const ESE_MovePrevious: i32 = -1;
 pub const ESE_MoveNext: i32 = 1;
 pub const ESE_MoveLast: i32 = 2147483647;
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SeekMode {
+    // first row with the key equal to the given one (or starting with it, for partial keys)
+    Eq,
+    // first row with the key greater than or equal to the given one
+    Ge,
+    // last row with the key less than or equal to the given one
+    Le,
+}
+
+// Identifies a record by its primary key, which is stable across snapshots of the database,
+// and the leaf page location it was read from
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Bookmark {
+    pub key: Vec<u8>,
+    // key of the secondary index entry the record was reached through
+    pub index_key: Option<Vec<u8>>,
+    pub page_number: u32,
+    pub page_tag_index: usize,
+}
+
 pub trait EseDb {
     fn error_to_string(&self, err: i32) -> String;
 
@@ -66,12 +105,22 @@ pub trait EseDb {
         false
     }
 
+    // every open_table returns a new cursor, which lives until close_table is called
+    // with its id; the ids of closed tables are invalid, even after their slots are reused
     fn open_table(&self, table: &str) -> Result<u64, SimpleError>;
+    // false if the table id is not open
     fn close_table(&self, table: u64) -> bool;
 
     fn get_tables(&self) -> Result<Vec<String>, SimpleError>;
     fn get_columns(&self, table: &str) -> Result<Vec<ColumnInfo>, SimpleError>;
 
+    fn get_indexes(&self, table: &str) -> Result<Vec<IndexInfo>, SimpleError> {
+        Err(SimpleError::new(format!(
+            "get_indexes({}) is not supported",
+            table
+        )))
+    }
+
     fn get_column(&self, table: u64, column: u32) -> Result<Option<Vec<u8>>, SimpleError>;
     fn get_column_mv(
         &self,
@@ -82,6 +131,67 @@ pub trait EseDb {
 
     fn move_row(&self, table: u64, crow: i32) -> Result<bool, SimpleError>;
 
+    // key_values are the values of the (leading) primary index columns, as stored in the record
+    fn seek(&self, table: u64, key_values: &[&[u8]], mode: SeekMode) -> Result<bool, SimpleError> {
+        let _ = (key_values, mode);
+        Err(SimpleError::new(format!(
+            "seek({}) is not supported",
+            table
+        )))
+    }
+
+    // selects the secondary index which orders move_row and seek (None for the primary index)
+    // and moves to its first row
+    fn set_current_index(&self, table: u64, index: Option<&str>) -> Result<bool, SimpleError> {
+        let _ = index;
+        Err(SimpleError::new(format!(
+            "set_current_index({}) is not supported",
+            table
+        )))
+    }
+
+    // limits the current secondary index to the (inclusive, possibly partial) key bounds
+    // and moves to its first row
+    fn set_index_range(
+        &self,
+        table: u64,
+        start_key_values: Option<&[&[u8]]>,
+        end_key_values: Option<&[&[u8]]>,
+    ) -> Result<bool, SimpleError> {
+        let _ = (start_key_values, end_key_values);
+        Err(SimpleError::new(format!(
+            "set_index_range({}) is not supported",
+            table
+        )))
+    }
+
+    // moves to the row at the approximate fractional position (0.0 first, 1.0 last)
+    // of the current index, like JetGotoPosition
+    fn goto_position(&self, table: u64, fraction: f64) -> Result<bool, SimpleError> {
+        let _ = fraction;
+        Err(SimpleError::new(format!(
+            "goto_position({}) is not supported",
+            table
+        )))
+    }
+
+    // position of the current row, to return to it later with goto_bookmark
+    fn get_bookmark(&self, table: u64) -> Result<Bookmark, SimpleError> {
+        Err(SimpleError::new(format!(
+            "get_bookmark({}) is not supported",
+            table
+        )))
+    }
+
+    // moves to the row of the bookmark on the current index, false if the row is gone
+    fn goto_bookmark(&self, table: u64, bookmark: &Bookmark) -> Result<bool, SimpleError> {
+        let _ = bookmark;
+        Err(SimpleError::new(format!(
+            "goto_bookmark({}) is not supported",
+            table
+        )))
+    }
+
     fn get_column_date(
         &self,
         table: u64,
@@ -24,6 +24,7 @@ pub type DbState = parser::jet::DbState;
 #[cfg(test)]
 mod tests {
     use super::ese_trait::*;
+    use super::parser::{jet, key};
     use super::*;
     use std::collections::HashMap;
     use std::fs::File;
@@ -45,6 +46,91 @@ mod tests {
         }
     }
 
+    // values of all the columns of the rows of the cursor, from its first row
+    type Rows = Vec<Vec<Option<Vec<u8>>>>;
+    fn load_rows(
+        jdb: &dyn EseDb,
+        table_id: u64,
+        columns: &[ColumnInfo],
+    ) -> Result<Rows, simple_error::SimpleError> {
+        let mut rows = vec![];
+        let mut ok = jdb.move_row(table_id, ESE_MoveFirst)?;
+        while ok {
+            let mut row = vec![];
+            for col in columns.iter() {
+                row.push(jdb.get_column(table_id, col.id)?);
+            }
+            rows.push(row);
+            ok = jdb.move_row(table_id, ESE_MoveNext)?;
+        }
+        Ok(rows)
+    }
+    fn load_table_rows(jdb: &dyn EseDb, table: &str) -> Result<Rows, simple_error::SimpleError> {
+        let columns = jdb.get_columns(table)?;
+        let table_id = jdb.open_table(table)?;
+        let rows = load_rows(jdb, table_id, &columns);
+        jdb.close_table(table_id);
+        rows
+    }
+
+    // test databases edited in memory
+    fn read_test_data(db_name: &str) -> Vec<u8> {
+        std::fs::read(["testdata", db_name].join("/")).unwrap()
+    }
+    fn load_data(
+        cache_size: usize,
+        data: Vec<u8>,
+    ) -> ese_parser::EseParser<std::io::Cursor<Vec<u8>>> {
+        ese_parser::EseParser::load(cache_size, std::io::Cursor::new(data)).unwrap()
+    }
+    // the page follows the database header and its shadow copy
+    fn page_mut(data: &mut [u8], page_size: usize, page_number: u32) -> &mut [u8] {
+        let page_offset = (page_number as usize + 1) * page_size;
+        &mut data[page_offset..page_offset + page_size]
+    }
+    fn update_page_checksum(page: &mut [u8], page_number: u32) {
+        let checksum = utils::calc_new_crc(page, page_number, true).unwrap();
+        page[..8].copy_from_slice(&checksum.to_le_bytes());
+    }
+    // the database file with the page edited, and its checksum updated
+    fn edit_page(db_name: &str, page_number: u32, edit: impl FnOnce(&mut [u8])) -> Vec<u8> {
+        let mut data = read_test_data(db_name);
+        let jdb = init_tests(5, Some(db_name));
+        let page_size = jdb.get_reader().unwrap().page_size() as usize;
+        let page = page_mut(&mut data, page_size, page_number);
+        edit(page);
+        update_page_checksum(page, page_number);
+        data
+    }
+    // loads a copy of the database with page tags of a leaf page marked as deleted,
+    // optionally moving their data to another offset
+    fn delete_page_tags(
+        db_name: &str,
+        page_number: u32,
+        tags: &[(usize, Option<u16>)],
+    ) -> ese_parser::EseParser<std::io::Cursor<Vec<u8>>> {
+        load_data(5, defunct_page_tags(db_name, page_number, tags))
+    }
+    fn defunct_page_tags(
+        db_name: &str,
+        page_number: u32,
+        tags: &[(usize, Option<u16>)],
+    ) -> Vec<u8> {
+        edit_page(db_name, page_number, |page| {
+            let page_size = page.len();
+            for (i, offset) in tags {
+                // tags are stored from the end of the page, the offset (with the flags) after the size
+                let pos = page_size - 4 * i - 2;
+                let mut value = u16::from_le_bytes([page[pos], page[pos + 1]]);
+                if let Some(offset) = offset {
+                    value = value & 0xe000 | offset;
+                }
+                value |= (jet::PageTagFlags::FLAG_IS_DEFUNCT.bits() as u16) << 13;
+                page[pos..pos + 2].copy_from_slice(&value.to_le_bytes());
+            }
+        })
+    }
+
     #[test]
     fn test_edb_table_default() {
         let jdb = init_tests(5, None); // None means default db is used: test.db
@@ -169,6 +255,1996 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_system_identity_indexes() {
+        let jdb = init_tests(5, Some("SystemIdentity.mdb"));
+
+        let indexes = jdb.get_indexes("CHAINED_DATABASES").unwrap();
+        assert_eq!(indexes.len(), 1);
+        assert_eq!(indexes[0].name, "Year_index");
+        assert_eq!(indexes[0].columns.len(), 1);
+        assert_eq!(indexes[0].columns[0].id, 1);
+        assert!(indexes[0].columns[0].descending);
+        assert!(!indexes[0].unique);
+        assert!(!indexes[0].primary);
+
+        let indexes = jdb.get_indexes("ROLE_IDS").unwrap();
+        assert_eq!(indexes.len(), 1);
+        assert_eq!(indexes[0].name, "RoleGuid_index");
+        assert!(indexes[0].unique);
+        assert!(!indexes[0].columns[0].descending);
+
+        let indexes = jdb.get_indexes("MSysObjects").unwrap();
+        let names: Vec<&str> = indexes.iter().map(|i| i.name.as_str()).collect();
+        assert_eq!(names, vec!["Id", "Name", "RootObjects"]);
+        assert!(indexes[0].primary);
+        assert_eq!(indexes[0].fdp, jet::FixedPageNumber::Catalog as u32);
+        let key: Vec<u32> = indexes[1].columns.iter().map(|c| c.id).collect();
+        assert_eq!(key, vec![1, 2, 128]);
+    }
+
+    #[test]
+    fn test_seek_primary_key() {
+        for db_name in ["SystemIdentity.mdb", "test.edb"] {
+            let jdb = init_tests(5, Some(db_name));
+            let table_id = jdb.open_table("MSysObjects").unwrap();
+
+            // ObjidTable, Type, Id and Name of every row, in primary key order
+            let mut rows: Vec<[Vec<u8>; 4]> = vec![];
+            loop {
+                let col = |id: u32| jdb.get_column(table_id, id).unwrap().unwrap();
+                rows.push([col(1), col(2), col(3), col(128)]);
+                if !jdb.move_row(table_id, ESE_MoveNext).unwrap() {
+                    break;
+                }
+            }
+            assert!(rows.len() > 1);
+
+            for [objid_table, typ, id, name] in &rows {
+                let key: [&[u8]; 3] = [objid_table, typ, id];
+                for mode in [SeekMode::Eq, SeekMode::Ge, SeekMode::Le] {
+                    assert!(jdb.seek(table_id, &key, mode).unwrap());
+                    assert_eq!(&jdb.get_column(table_id, 128).unwrap().unwrap(), name);
+                }
+            }
+
+            // partial key: first row of the table's own objects
+            let [objid_table, _, _, name] = &rows[0];
+            assert!(jdb
+                .seek(table_id, &[objid_table.as_slice()], SeekMode::Eq)
+                .unwrap());
+            assert_eq!(&jdb.get_column(table_id, 128).unwrap().unwrap(), name);
+
+            // cursor keeps working after seek
+            let [objid_table, typ, id, _] = &rows[rows.len() - 2];
+            assert!(jdb
+                .seek(table_id, &[objid_table, typ, id], SeekMode::Eq)
+                .unwrap());
+            assert!(jdb.move_row(table_id, ESE_MoveNext).unwrap());
+            assert_eq!(
+                jdb.get_column(table_id, 128).unwrap().unwrap(),
+                rows[rows.len() - 1][3]
+            );
+            assert!(!jdb.move_row(table_id, ESE_MoveNext).unwrap());
+
+            // keys out of range
+            let max = i32::MAX.to_le_bytes();
+            let min = i32::MIN.to_le_bytes();
+            assert!(!jdb.seek(table_id, &[&max], SeekMode::Eq).unwrap());
+            assert!(!jdb.seek(table_id, &[&max], SeekMode::Ge).unwrap());
+            assert!(jdb.seek(table_id, &[&max], SeekMode::Le).unwrap());
+            assert_eq!(
+                jdb.get_column(table_id, 128).unwrap().unwrap(),
+                rows[rows.len() - 1][3]
+            );
+            assert!(!jdb.seek(table_id, &[&min], SeekMode::Le).unwrap());
+            assert!(jdb.seek(table_id, &[&min], SeekMode::Ge).unwrap());
+            assert_eq!(jdb.get_column(table_id, 128).unwrap().unwrap(), rows[0][3]);
+        }
+    }
+
+    #[test]
+    fn test_secondary_index_scan() {
+        let jdb = init_tests(5, Some("Current.mdb"));
+        let table_id = jdb.open_table("MSysObjects").unwrap();
+        let col = |id: u32| jdb.get_column(table_id, id).unwrap().unwrap();
+        let i32_col = |id: u32| i32::from_le_bytes(col(id).try_into().unwrap());
+
+        // Name index: ObjidTable, Type, Name
+        assert!(jdb.set_current_index(table_id, Some("Name")).unwrap());
+        let mut rows: Vec<(i32, i16, Vec<u8>)> = vec![];
+        loop {
+            let typ = i16::from_le_bytes(col(2).try_into().unwrap());
+            rows.push((i32_col(1), typ, col(128)));
+            if !jdb.move_row(table_id, ESE_MoveNext).unwrap() {
+                break;
+            }
+        }
+        assert_eq!(rows.len(), 467);
+        for w in rows.windows(2) {
+            assert!((w[0].0, w[0].1) <= (w[1].0, w[1].1));
+            if (w[0].0, w[0].1) == (w[1].0, w[1].1) {
+                assert!(w[0].2.to_ascii_uppercase() <= w[1].2.to_ascii_uppercase());
+            }
+        }
+
+        // same rows backward
+        assert!(jdb.move_row(table_id, ESE_MoveLast).unwrap());
+        for row in rows.iter().rev() {
+            assert_eq!(col(128), row.2);
+            jdb.move_row(table_id, ESE_MovePrevious).unwrap();
+        }
+
+        // rows of a single table by partial key range
+        let objid = rows[rows.len() / 2].0;
+        let objid_bytes = objid.to_le_bytes();
+        let bound: [&[u8]; 1] = [&objid_bytes];
+        assert!(jdb
+            .set_index_range(table_id, Some(&bound), Some(&bound))
+            .unwrap());
+        let mut count = 1;
+        assert_eq!(i32_col(1), objid);
+        while jdb.move_row(table_id, ESE_MoveNext).unwrap() {
+            assert_eq!(i32_col(1), objid);
+            count += 1;
+        }
+        assert_eq!(count, rows.iter().filter(|r| r.0 == objid).count());
+        assert!(jdb.move_row(table_id, ESE_MoveLast).unwrap());
+        assert_eq!(i32_col(1), objid);
+
+        // seek on the current index after moving through its pages
+        let first = &rows[0];
+        let typ = first.1.to_le_bytes();
+        assert!(jdb.set_index_range(table_id, None, None).unwrap());
+        assert!(jdb.move_row(table_id, ESE_MoveLast).unwrap());
+        assert!(jdb
+            .seek(table_id, &[&first.0.to_le_bytes(), &typ], SeekMode::Eq)
+            .unwrap());
+        assert_eq!(col(128), first.2);
+
+        // back to the primary index
+        assert!(jdb.set_current_index(table_id, None).unwrap());
+        assert_eq!(i32_col(1), rows[0].0);
+        assert!(jdb.set_current_index(table_id, Some("Unknown")).is_err());
+
+        // DateTime index
+        let jdb = init_tests(5, Some("SystemIdentity.mdb"));
+        let table_id = jdb.open_table("SYSTEM_IDENTITY").unwrap();
+        assert!(jdb
+            .set_current_index(table_id, Some("CreationTime_index"))
+            .unwrap());
+        let mut times: Vec<Vec<u8>> = vec![];
+        loop {
+            times.push(jdb.get_column(table_id, 1).unwrap().unwrap());
+            if !jdb.move_row(table_id, ESE_MoveNext).unwrap() {
+                break;
+            }
+        }
+        assert_eq!(times.len(), 6);
+        // descending index: newest first
+        let indexes = jdb.get_indexes("SYSTEM_IDENTITY").unwrap();
+        assert!(indexes[0].columns[0].descending);
+        let as_u64 = |v: &Vec<u8>| u64::from_le_bytes(v.as_slice().try_into().unwrap());
+        for w in times.windows(2) {
+            assert!(as_u64(&w[0]) >= as_u64(&w[1]));
+        }
+        let start: [&[u8]; 1] = [&times[2]];
+        let end: [&[u8]; 1] = [&times[3]];
+        assert!(jdb
+            .set_index_range(table_id, Some(&start), Some(&end))
+            .unwrap());
+        assert_eq!(jdb.get_column(table_id, 1).unwrap().unwrap(), times[2]);
+        assert!(jdb.move_row(table_id, ESE_MoveNext).unwrap());
+        assert_eq!(jdb.get_column(table_id, 1).unwrap().unwrap(), times[3]);
+        assert!(!jdb.move_row(table_id, ESE_MoveNext).unwrap());
+        assert!(jdb.seek(table_id, &[&times[3]], SeekMode::Eq).unwrap());
+        assert!(!jdb.seek(table_id, &[&times[5]], SeekMode::Eq).unwrap());
+    }
+
+    #[test]
+    fn test_seek_text_index() {
+        let jdb = init_tests(5, Some("Current.mdb"));
+        let table_id = jdb.open_table("CLIENTS").unwrap();
+        let username = || utils::from_utf16(&jdb.get_column(table_id, 256).unwrap().unwrap());
+        let text = |s: &str| {
+            s.encode_utf16()
+                .flat_map(|c| c.to_le_bytes())
+                .collect::<Vec<u8>>()
+        };
+
+        // Username, RoleGuid, TenantId (codepage 1200, case insensitive)
+        assert!(jdb
+            .set_current_index(table_id, Some("Username_RoleGuid_TenantId_index"))
+            .unwrap());
+        let jane = text("LAB\\Jane\0");
+        assert!(jdb.seek(table_id, &[&jane], SeekMode::Eq).unwrap());
+        assert_eq!(username().unwrap(), "lab\\jane\0");
+        let dc = text("lab\\dc\0");
+        assert!(!jdb.seek(table_id, &[&dc], SeekMode::Eq).unwrap());
+        assert!(jdb.seek(table_id, &[&dc], SeekMode::Ge).unwrap());
+        assert_eq!(username().unwrap(), "lab\\dc-1$\0");
+
+        let dc1 = text("lab\\dc-1$\0");
+        let bound: [&[u8]; 1] = [&dc1];
+        assert!(jdb
+            .set_index_range(table_id, Some(&bound), Some(&bound))
+            .unwrap());
+        let mut count = 1;
+        while jdb.move_row(table_id, ESE_MoveNext).unwrap() {
+            assert_eq!(username().unwrap(), "lab\\dc-1$\0");
+            count += 1;
+        }
+        assert_eq!(count, 10);
+
+        // characters without a known sort weight nor one learned from the text indexes of
+        // the database can't make a key
+        let unknown = text("lab\\dc 1$\0");
+        assert!(jdb.seek(table_id, &[&unknown], SeekMode::Eq).is_err());
+        assert!(jdb.seek(table_id, &[&dc1], SeekMode::Eq).unwrap());
+    }
+
+    #[test]
+    fn test_learned_sort_weights() {
+        // the sort keys stored in the text indexes, with some characters of the weight table
+        // replaced by ones without weights: their weights are learned back from the keys
+        let hidden = [('\\', 0xe000), ('.', 0xe001), ('a', 0xe002), ('-', 0xe003)];
+        let mut samples: Vec<key::SortKeySample> = vec![];
+        for db_name in ["Current.mdb", "{03A01CC5-91BB-4936-B685-63697785D39E}.mdb"] {
+            let jdb = init_tests(5, Some(db_name));
+            let reader = jdb.get_reader().unwrap();
+            for tbl_def in reader.load_catalog().unwrap() {
+                let table = &tbl_def.table_catalog_definition.as_ref().unwrap().name;
+                for index in tbl_def.index_definition_array.iter() {
+                    if !key::has_sort_key_text(&tbl_def, index) {
+                        continue;
+                    }
+                    let table_id = jdb.open_table(table).unwrap();
+                    let mut found = jdb.set_current_index(table_id, Some(&index.name)).unwrap();
+                    while found {
+                        let bookmark = jdb.get_bookmark(table_id).unwrap();
+                        let values: Vec<Option<Vec<u8>>> = index
+                            .key_columns
+                            .iter()
+                            .map(|s| jdb.get_column(table_id, s.column_identifier).unwrap())
+                            .collect();
+                        let values: Vec<Option<&[u8]>> =
+                            values.iter().map(|v| v.as_deref()).collect();
+                        let stored_key = bookmark.index_key.unwrap_or(bookmark.key);
+                        samples.extend(
+                            key::sort_key_samples(&tbl_def, index, &stored_key, &values).unwrap(),
+                        );
+                        found = jdb.move_row(table_id, ESE_MoveNext).unwrap();
+                    }
+                    jdb.close_table(table_id);
+                }
+            }
+        }
+        assert!(samples.len() > 100);
+        for sample in samples.iter_mut() {
+            for c in sample.text.iter_mut() {
+                if let Some((_, h)) = hidden.iter().find(|(ch, _)| *ch as u16 == *c) {
+                    *c = *h;
+                }
+            }
+        }
+
+        let weights = key::SortWeights::learn(&samples);
+        assert_eq!(weights.primary_weight(0xe000), Some(&[0x07, 0x41][..]));
+        assert_eq!(weights.primary_weight(0xe001), Some(&[0x07, 0x33][..]));
+        assert_eq!(weights.primary_weight(0xe002), Some(&[0x0e, 0x02][..]));
+        assert_eq!(weights.special_weight(0xe003), Some([0x82, 0x12]));
+        for sample in samples.iter() {
+            let value: Vec<u8> = sample.text.iter().flat_map(|c| c.to_le_bytes()).collect();
+            let key = key::normalize_value_with_weights(
+                ESE_coltypText,
+                1200,
+                sample.lcmap_flags,
+                Some(&value),
+                &weights,
+            );
+            assert_eq!(key.unwrap()[1..], sample.sort_key);
+        }
+    }
+
+    #[test]
+    fn test_index_keys() {
+        // keys made from the column values must match the ones stored in the index pages
+        for db_name in [
+            "Current.mdb",
+            "{03A01CC5-91BB-4936-B685-63697785D39E}.mdb",
+            "SystemIdentity.mdb",
+            "test.edb",
+            "decompress_test.edb",
+            "decompress_test2.edb",
+        ] {
+            let jdb = init_tests(5, Some(db_name));
+            let reader = jdb.get_reader().unwrap();
+            for tbl_def in reader.load_catalog().unwrap() {
+                let table = &tbl_def.table_catalog_definition.as_ref().unwrap().name;
+                for index in tbl_def.index_definition_array.iter() {
+                    if index.is_primary() {
+                        continue;
+                    }
+                    let table_id = jdb.open_table(table).unwrap();
+                    assert!(jdb.set_current_index(table_id, Some(&index.name)).is_ok());
+                    let first_page = reader
+                        .find_first_leaf_page(index.father_data_page_number)
+                        .unwrap();
+                    let mut page = jet::DbPage::new(reader, first_page).unwrap();
+                    let mut i = 1;
+                    loop {
+                        while i < page.tags() {
+                            let page_tag = page.tag(i).unwrap();
+                            i += 1;
+                            if page_tag
+                                .flags()
+                                .intersects(jet::PageTagFlags::FLAG_IS_DEFUNCT)
+                            {
+                                continue;
+                            }
+                            let (stored_key, _) = reader.load_index_entry(&page, page_tag).unwrap();
+                            let values: Vec<Option<Vec<u8>>> = index
+                                .key_columns
+                                .iter()
+                                .map(|s| jdb.get_column(table_id, s.column_identifier).unwrap())
+                                .collect();
+                            let values: Vec<Option<&[u8]>> =
+                                values.iter().map(|v| v.as_deref()).collect();
+                            let key = key::make_key_with_nulls(&tbl_def, index, &values).unwrap();
+                            assert_eq!(key, stored_key, "{} {}", table, index.name);
+
+                            // and decode back into the same values, text up to the case
+                            let decoded = key::decode_key(&tbl_def, index, &stored_key).unwrap();
+                            assert_eq!(decoded.len(), values.len());
+                            for (d, v) in decoded.iter().zip(values.iter()) {
+                                if d.exact {
+                                    assert_eq!(d.value.as_deref(), *v);
+                                } else {
+                                    let unicode = tbl_def
+                                        .column_catalog_definition_array
+                                        .iter()
+                                        .any(|c| c.identifier == d.column_id && c.codepage == 1200);
+                                    let text = |v: &[u8]| {
+                                        let s = if unicode {
+                                            utils::from_utf16(v).unwrap()
+                                        } else {
+                                            String::from_utf8_lossy(v).to_string()
+                                        };
+                                        s.trim_end_matches('\0').to_lowercase()
+                                    };
+                                    assert_eq!(text(d.value.as_ref().unwrap()), text(v.unwrap()));
+                                }
+                            }
+                            jdb.move_row(table_id, ESE_MoveNext).unwrap();
+                        }
+                        if page.next_page() == 0 {
+                            break;
+                        }
+                        page = jet::DbPage::new(reader, page.next_page()).unwrap();
+                        i = 1;
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_record_key() {
+        let jdb = init_tests(5, Some("test.edb"));
+        let reader = jdb.get_reader().unwrap();
+        let catalog = reader.load_catalog().unwrap();
+
+        // MSysObjects: the key is made of ObjidTable, Type and Id
+        let tbl_def = &catalog[0];
+        let table_id = jdb.open_table("MSysObjects").unwrap();
+        let mut keys: Vec<Vec<u8>> = vec![];
+        loop {
+            let (record_key, bookmark) = jdb.get_record_key(table_id).unwrap();
+            let col = |id: u32| jdb.get_column(table_id, id).unwrap().unwrap();
+            let values = [col(1), col(2), col(3)];
+            let values: Vec<&[u8]> = values.iter().map(|v| v.as_slice()).collect();
+            assert_eq!(record_key, key::make_primary_key(tbl_def, &values).unwrap());
+            assert_eq!(bookmark.key, record_key);
+            assert!(bookmark.page_number > 0 && bookmark.page_tag_index > 0);
+            keys.push(record_key);
+            if !jdb.move_row(table_id, ESE_MoveNext).unwrap() {
+                break;
+            }
+        }
+        for w in keys.windows(2) {
+            assert!(w[0] < w[1]);
+        }
+
+        // keys follow the cursor
+        let (last_key, _) = jdb.get_record_key(table_id).unwrap();
+        assert!(jdb.move_row(table_id, ESE_MoveFirst).unwrap());
+        let (first_key, _) = jdb.get_record_key(table_id).unwrap();
+        assert_eq!(first_key, keys[0]);
+        assert_eq!(&last_key, keys.last().unwrap());
+
+        // on a secondary index it is still the primary key of the record
+        assert!(jdb.set_current_index(table_id, Some("Name")).unwrap());
+        let (record_key, _) = jdb.get_record_key(table_id).unwrap();
+        assert!(keys.contains(&record_key));
+    }
+
+    #[test]
+    fn test_bookmarks() {
+        let jdb = init_tests(5, Some("Current.mdb"));
+        let table_id = jdb.open_table("MSysObjects").unwrap();
+        let name = |jdb: &ese_parser::EseParser<BufReader<File>>| {
+            jdb.get_column(table_id, 128).unwrap().unwrap()
+        };
+
+        let mut rows: Vec<(Bookmark, Vec<u8>)> = vec![];
+        loop {
+            rows.push((jdb.get_bookmark(table_id).unwrap(), name(&jdb)));
+            if !jdb.move_row(table_id, ESE_MoveNext).unwrap() {
+                break;
+            }
+        }
+        for (i, (bookmark, row_name)) in rows.iter().enumerate().rev().step_by(7) {
+            assert!(jdb.goto_bookmark(table_id, bookmark).unwrap());
+            assert_eq!(&name(&jdb), row_name);
+            if i + 1 < rows.len() {
+                assert!(jdb.move_row(table_id, ESE_MoveNext).unwrap());
+                assert_eq!(name(&jdb), rows[i + 1].1);
+            }
+        }
+
+        // the page is only a hint
+        let mut moved = rows[100].0.clone();
+        moved.page_number = rows[0].0.page_number;
+        moved.page_tag_index = 1;
+        assert!(jdb.goto_bookmark(table_id, &moved).unwrap());
+        assert_eq!(name(&jdb), rows[100].1);
+
+        // unknown records keep the position
+        let mut gone = rows[100].0.clone();
+        gone.key.push(0);
+        assert!(!jdb.goto_bookmark(table_id, &gone).unwrap());
+        assert_eq!(name(&jdb), rows[100].1);
+
+        // secondary index bookmarks return to the same index entry
+        assert!(jdb.set_current_index(table_id, Some("Name")).unwrap());
+        assert!(jdb.goto_bookmark(table_id, &rows[3].0).is_err());
+        let mut entries: Vec<(Bookmark, Vec<u8>)> = vec![];
+        loop {
+            entries.push((jdb.get_bookmark(table_id).unwrap(), name(&jdb)));
+            if !jdb.move_row(table_id, ESE_MoveNext).unwrap() {
+                break;
+            }
+        }
+        assert_eq!(entries.len(), rows.len());
+        for (i, (bookmark, entry_name)) in entries.iter().enumerate().step_by(5) {
+            assert!(bookmark.index_key.is_some());
+            assert!(jdb.goto_bookmark(table_id, bookmark).unwrap());
+            assert_eq!(&name(&jdb), entry_name);
+            assert_eq!(jdb.get_bookmark(table_id).unwrap(), *bookmark);
+            if i > 0 {
+                assert!(jdb.move_row(table_id, ESE_MovePrevious).unwrap());
+                assert_eq!(name(&jdb), entries[i - 1].1);
+            }
+        }
+    }
+
+    #[test]
+    fn test_multiple_cursors() {
+        let jdb = init_tests(5, Some("Current.mdb"));
+        let outer = jdb.open_table("MSysObjects").unwrap();
+        let inner = jdb.open_table("MSysObjects").unwrap();
+        assert_ne!(outer, inner);
+
+        // look up the table row of every column row, while iterating the same table
+        let mut columns = 0;
+        loop {
+            let typ = jdb.get_fixed_column::<i16>(outer, 2).unwrap().unwrap();
+            if typ == 2 {
+                let objid = jdb.get_column(outer, 1).unwrap().unwrap();
+                let table_type = 1i16.to_le_bytes();
+                let key: [&[u8]; 3] = [&objid, &table_type, &objid];
+                assert!(jdb.seek(inner, &key, SeekMode::Eq).unwrap());
+                assert_eq!(jdb.get_column(inner, 3).unwrap().unwrap(), objid);
+                columns += 1;
+            }
+            if !jdb.move_row(outer, ESE_MoveNext).unwrap() {
+                break;
+            }
+        }
+        assert!(columns > 0);
+
+        // cursors on different indexes
+        assert!(jdb.set_current_index(inner, Some("Name")).unwrap());
+        assert!(jdb.move_row(outer, ESE_MoveFirst).unwrap());
+        let first = jdb.get_bookmark(outer).unwrap();
+        assert!(jdb.move_row(inner, ESE_MoveNext).unwrap());
+        assert_eq!(jdb.get_bookmark(outer).unwrap(), first);
+
+        // closed tables can't be used, not even once another table took their cursor slot
+        assert!(jdb.close_table(inner));
+        assert!(!jdb.close_table(inner));
+        assert!(jdb.move_row(inner, ESE_MoveNext).is_err());
+        let other = jdb.open_table("ROLE_ACCESS").unwrap();
+        assert_ne!(other, inner);
+        assert!(jdb.move_row(inner, ESE_MoveFirst).is_err());
+        assert!(jdb.get_bookmark(inner).is_err());
+        assert!(!jdb.close_table(inner));
+        assert!(jdb.move_row(other, ESE_MoveNext).unwrap());
+        assert_eq!(jdb.get_bookmark(outer).unwrap(), first);
+        assert!(jdb.close_table(other));
+        assert!(jdb.close_table(outer));
+    }
+
+    #[test]
+    fn test_skip_rows_and_positions() {
+        let jdb = init_tests(5, Some("Current.mdb"));
+        let table_id = jdb.open_table("MSysObjects").unwrap();
+        let mut rows: Vec<Bookmark> = vec![];
+        loop {
+            rows.push(jdb.get_bookmark(table_id).unwrap());
+            if !jdb.move_row(table_id, ESE_MoveNext).unwrap() {
+                break;
+            }
+        }
+        let pages: std::collections::BTreeSet<u32> = rows.iter().map(|b| b.page_number).collect();
+        assert!(pages.len() > 1);
+        let n = rows.len();
+
+        assert!(jdb.move_row(table_id, ESE_MoveLast).unwrap());
+        assert_eq!(jdb.get_bookmark(table_id).unwrap(), rows[n - 1]);
+
+        // large skips in both directions
+        for crow in [2, 17, 100, 250] {
+            assert!(jdb.move_row(table_id, ESE_MoveFirst).unwrap());
+            assert!(jdb.move_row(table_id, crow).unwrap());
+            assert_eq!(jdb.get_bookmark(table_id).unwrap(), rows[crow as usize]);
+            assert!(jdb.move_row(table_id, -crow + 1).unwrap());
+            assert_eq!(jdb.get_bookmark(table_id).unwrap(), rows[1]);
+            assert!(jdb.move_row(table_id, ESE_MoveLast).unwrap());
+            assert!(jdb.move_row(table_id, -crow).unwrap());
+            assert_eq!(
+                jdb.get_bookmark(table_id).unwrap(),
+                rows[n - 1 - crow as usize]
+            );
+        }
+        // not enough rows: the cursor stays on its row
+        assert!(jdb.move_row(table_id, ESE_MoveFirst).unwrap());
+        assert!(!jdb.move_row(table_id, n as i32).unwrap());
+        assert_eq!(jdb.get_bookmark(table_id).unwrap(), rows[0]);
+        assert!(jdb.move_row(table_id, ESE_MoveNext).unwrap());
+        assert_eq!(jdb.get_bookmark(table_id).unwrap(), rows[1]);
+        assert!(jdb.move_row(table_id, ESE_MoveLast).unwrap());
+        assert!(!jdb.move_row(table_id, -(n as i32)).unwrap());
+        assert_eq!(jdb.get_bookmark(table_id).unwrap(), rows[n - 1]);
+        assert!(jdb.move_row(table_id, ESE_MovePrevious).unwrap());
+        assert_eq!(jdb.get_bookmark(table_id).unwrap(), rows[n - 2]);
+
+        // no move
+        assert!(jdb.move_row(table_id, 0).unwrap());
+        assert_eq!(jdb.get_bookmark(table_id).unwrap(), rows[n - 2]);
+
+        // skip past the end of the deleted records
+        let jdb = delete_page_tags("Current.mdb", 13, &[(1, None)]);
+        let table_id = jdb.open_deleted("MSysObjects").unwrap();
+        assert!(jdb.move_row(table_id, ESE_MoveFirst).unwrap());
+        let deleted = jdb.get_bookmark(table_id).unwrap();
+        assert!(!jdb.move_row(table_id, 5).unwrap());
+        assert_eq!(jdb.get_bookmark(table_id).unwrap(), deleted);
+        assert!(jdb.get_recovery_status(table_id).is_ok());
+        assert!(!jdb.move_row(table_id, ESE_MovePrevious).unwrap());
+        assert!(!jdb.move_row(table_id, -5).unwrap());
+        assert!(jdb.move_row(table_id, 0).unwrap());
+        assert_eq!(jdb.get_bookmark(table_id).unwrap(), deleted);
+        let jdb = init_tests(5, Some("Current.mdb"));
+        let table_id = jdb.open_table("MSysObjects").unwrap();
+
+        // fractional positions
+        assert!(jdb.goto_position(table_id, 0.0).unwrap());
+        assert_eq!(jdb.get_bookmark(table_id).unwrap(), rows[0]);
+        assert!(jdb.goto_position(table_id, 1.0).unwrap());
+        assert_eq!(jdb.get_bookmark(table_id).unwrap(), rows[n - 1]);
+        let mut last = 0;
+        for i in 1..10 {
+            assert!(jdb.goto_position(table_id, i as f64 / 10.0).unwrap());
+            let bookmark = jdb.get_bookmark(table_id).unwrap();
+            let row = rows.iter().position(|b| *b == bookmark).unwrap();
+            assert!(row >= last);
+            last = row;
+        }
+        assert!(jdb.goto_position(table_id, 0.5).unwrap());
+        assert!(jdb.move_row(table_id, ESE_MoveNext).unwrap());
+        assert!(jdb.goto_position(table_id, 1.5).is_err());
+        assert!(jdb.goto_position(table_id, f64::NAN).is_err());
+
+        // on a secondary index
+        assert!(jdb.set_current_index(table_id, Some("Name")).unwrap());
+        let first = jdb.get_bookmark(table_id).unwrap();
+        assert!(jdb.move_row(table_id, ESE_MoveLast).unwrap());
+        let last = jdb.get_bookmark(table_id).unwrap();
+        assert!(jdb.goto_position(table_id, 0.0).unwrap());
+        assert_eq!(jdb.get_bookmark(table_id).unwrap(), first);
+        assert!(jdb.goto_position(table_id, 1.0).unwrap());
+        assert_eq!(jdb.get_bookmark(table_id).unwrap(), last);
+        assert!(jdb.move_row(table_id, -2).unwrap());
+        assert!(jdb.move_row(table_id, 2).unwrap());
+        assert_eq!(jdb.get_bookmark(table_id).unwrap(), last);
+    }
+
+    #[test]
+    fn test_count_rows() {
+        for db_name in [
+            "Current.mdb",
+            "SystemIdentity.mdb",
+            "test.edb",
+            "decompress_test.edb",
+        ] {
+            let jdb = init_tests(5, Some(db_name));
+            for table in jdb.get_tables().unwrap() {
+                let table_id = jdb.open_table(&table).unwrap();
+                let mut rows = 0;
+                if jdb.move_row(table_id, ESE_MoveFirst).unwrap() {
+                    rows += 1;
+                    while jdb.move_row(table_id, ESE_MoveNext).unwrap() {
+                        rows += 1;
+                    }
+                }
+                assert_eq!(
+                    jdb.count_rows(&table).unwrap(),
+                    rows,
+                    "{} {}",
+                    db_name,
+                    table
+                );
+
+                let estimate = jdb.estimate_rows(&table).unwrap();
+                assert!(
+                    estimate * 2 >= rows && estimate <= rows * 2,
+                    "{} {}: {} rows, estimated {}",
+                    db_name,
+                    table,
+                    rows,
+                    estimate
+                );
+            }
+        }
+        let jdb = init_tests(5, Some("Current.mdb"));
+        assert!(jdb.count_rows("Unknown").is_err());
+    }
+
+    #[test]
+    fn test_space() {
+        use parser::space::*;
+        let extent = |first_page: u32, page_count: u32| Extent {
+            first_page,
+            page_count,
+        };
+
+        let jdb = init_tests(5, Some("Current.mdb"));
+        let spaces = load_space(jdb.get_reader().unwrap()).unwrap();
+        let find = |table: &str, name: &str| {
+            spaces
+                .iter()
+                .find(|s| s.table == table && s.name == name)
+                .unwrap()
+        };
+
+        let database = &spaces[0];
+        assert_eq!(database.object_type, SpaceObjectType::Database);
+        assert_eq!(database.space_tree_page_numbers, Some((2, 3)));
+        assert_eq!(database.owned, vec![extent(1, 254)]);
+        assert_eq!(database.available, vec![extent(75, 180)]);
+
+        let objects = find("MSysObjects", "MSysObjects");
+        assert_eq!(objects.object_type, SpaceObjectType::Table);
+        assert_eq!(objects.parent_father_data_page_number, 1);
+        assert_eq!(objects.owned, vec![extent(4, 20), extent(42, 20)]);
+        // the only available extent is defunct
+        assert!(objects.available.is_empty());
+
+        let name = find("MSysObjects", "Name");
+        assert_eq!(name.object_type, SpaceObjectType::Index);
+        assert_eq!(
+            name.owned,
+            vec![extent(7, 3), extent(15, 4), extent(23, 1), extent(44, 18)]
+        );
+        assert_eq!(name.available, vec![extent(45, 17)]);
+        assert!(name.owns(61) && name.is_available(61) && !name.is_available(44));
+
+        // single extent objects
+        let role_access = find("ROLE_ACCESS", "ROLE_ACCESS");
+        assert_eq!(role_access.space_tree_page_numbers, None);
+        assert_eq!(role_access.owned, vec![extent(31, 2)]);
+        assert!(role_access.available.is_empty());
+        let role_index = find("ROLE_ACCESS", "RoleGuid_index");
+        assert_eq!(role_index.owned, vec![extent(32, 1)]);
+        assert_eq!(role_index.parent_father_data_page_number, 31);
+
+        // every object gets its space from its parent
+        for space in spaces.iter().skip(1) {
+            let parent = spaces
+                .iter()
+                .find(|s| s.father_data_page_number == space.parent_father_data_page_number)
+                .unwrap();
+            for e in space.owned.iter() {
+                assert!(parent.owns(e.first_page) && parent.owns(e.last_page()));
+            }
+        }
+    }
+
+    #[test]
+    fn test_page_owners() {
+        use jet::PageFlags;
+        use parser::space::*;
+
+        let jdb = init_tests(5, Some("Current.mdb"));
+        let reader = jdb.get_reader().unwrap();
+        let owners = load_page_owners(reader).unwrap();
+        assert_eq!(owners.len() as u32, reader.page_count().unwrap());
+        assert!(owners.values().all(|o| o.error.is_none()));
+
+        let owner = |page_number: u32| {
+            let o = &owners[&page_number];
+            (o.object_type, o.table.as_str(), o.name.as_str())
+        };
+        assert_eq!(owner(1), (Some(SpaceObjectType::Database), "", ""));
+        assert!(owners[&2].flags.contains(PageFlags::IS_SPACE_TREE));
+        assert_eq!(
+            owner(4),
+            (Some(SpaceObjectType::Table), "MSysObjects", "MSysObjects")
+        );
+        assert!(owners[&4]
+            .flags
+            .contains(PageFlags::IS_ROOT | PageFlags::IS_PARENT));
+        assert_eq!(
+            owner(7),
+            (Some(SpaceObjectType::Index), "MSysObjects", "Name")
+        );
+        assert!(owners[&15]
+            .flags
+            .contains(PageFlags::IS_INDEX | PageFlags::IS_LEAF));
+        assert_eq!(owner(31).2, "ROLE_ACCESS");
+        assert_eq!(
+            owner(32),
+            (
+                Some(SpaceObjectType::Index),
+                "ROLE_ACCESS",
+                "RoleGuid_index"
+            )
+        );
+        // never used page
+        assert_eq!(owners[&45].object_identifier, 0);
+        assert_eq!(owner(45), (None, "", ""));
+
+        // every page owned by a catalog object lies in its owned space
+        let spaces = load_space(reader).unwrap();
+        for o in owners.values().filter(|o| o.object_type.is_some()) {
+            let space = spaces
+                .iter()
+                .find(|s| s.object_identifier == o.object_identifier)
+                .unwrap();
+            assert!(space.owns(o.page_number), "page {}", o.page_number);
+        }
+    }
+
+    #[test]
+    fn test_deleted_records() {
+        let table = "ROLE_ACCESS";
+        let jdb = init_tests(5, Some("Current.mdb"));
+        let columns = jdb.get_columns(table).unwrap();
+        let rows = load_table_rows(&jdb, table).unwrap();
+        assert!(rows.len() > 2);
+
+        // no deleted records in the test databases
+        let table_id = jdb.open_deleted(table).unwrap();
+        assert!(!jdb.move_row(table_id, ESE_MoveFirst).unwrap());
+        assert!(jdb.get_recovery_status(table_id).is_err());
+        let table_id = jdb.open_table(table).unwrap();
+        assert!(jdb.get_recovery_status(table_id).is_err());
+
+        // the record of the first tag of the leaf page is deleted
+        let father_data_page_number = 31;
+        let jdb = delete_page_tags("Current.mdb", father_data_page_number, &[(1, None)]);
+        let live = load_table_rows(&jdb, table).unwrap();
+        assert_eq!(live[..], rows[1..]);
+        let table_id = jdb.open_deleted(table).unwrap();
+        assert_eq!(load_rows(&jdb, table_id, &columns).unwrap(), rows[..1]);
+        assert!(jdb.move_row(table_id, ESE_MoveFirst).unwrap());
+        assert_eq!(
+            jdb.get_recovery_status(table_id).unwrap(),
+            ese_parser::RecoveryStatus::Intact
+        );
+        assert!(jdb.seek(table_id, &[], SeekMode::Eq).is_err());
+        assert!(jdb.set_current_index(table_id, None).is_err());
+        assert!(jdb.goto_position(table_id, 0.5).is_err());
+
+        let reader = jdb.get_reader().unwrap();
+        let page = jet::DbPage::new(reader, father_data_page_number).unwrap();
+        let last = page.tags() - 1;
+        let second_offset = page.tag(2).unwrap().offset;
+        // the last record is deleted too, its data was reused by the second tag
+        let jdb = delete_page_tags(
+            "Current.mdb",
+            father_data_page_number,
+            &[(1, None), (last, Some(second_offset))],
+        );
+        let table_id = jdb.open_deleted(table).unwrap();
+        assert!(jdb.move_row(table_id, ESE_MoveLast).unwrap());
+        assert_eq!(
+            jdb.get_recovery_status(table_id).unwrap(),
+            ese_parser::RecoveryStatus::Overwritten
+        );
+        assert!(jdb.move_row(table_id, ESE_MovePrevious).unwrap());
+        assert!(!jdb.move_row(table_id, ESE_MovePrevious).unwrap());
+        assert_eq!(jdb.count_rows(table).unwrap() as usize, rows.len() - 2);
+    }
+
+    #[test]
+    fn test_orphaned_long_values() {
+        let (db_name, table) = ("decompress_test2.edb", "test_table");
+        let jdb = init_tests(5, Some(db_name));
+        assert!(jdb.load_orphaned_long_values(table).unwrap().is_empty());
+        assert!(jdb
+            .load_orphaned_long_values("MSysObjects")
+            .unwrap()
+            .is_empty());
+
+        // the long values of the deleted first record are left in the long value tree
+        let table_id = jdb.open_table(table).unwrap();
+        let (_, bookmark) = jdb.get_record_key(table_id).unwrap();
+        let mut values: Vec<Vec<u8>> = jdb
+            .get_columns(table)
+            .unwrap()
+            .iter()
+            .map(|col| jdb.get_column(table_id, col.id).unwrap().unwrap())
+            .collect();
+        let jdb = delete_page_tags(
+            db_name,
+            bookmark.page_number,
+            &[(bookmark.page_tag_index, None)],
+        );
+        let orphans = jdb.load_orphaned_long_values(table).unwrap();
+        assert_eq!(
+            orphans
+                .iter()
+                .map(|lv| lv.long_value_key)
+                .collect::<Vec<u64>>(),
+            vec![1, 2, 3, 4]
+        );
+        // sizes from the roots, the chunks of the compressed values are shorter
+        assert!(orphans.iter().all(|lv| lv.size == lv.data.len() as u64));
+        let reader = jdb.get_reader().unwrap();
+        let lv_page = reader
+            .load_catalog()
+            .unwrap()
+            .iter()
+            .find(|t| t.table_catalog_definition.as_ref().unwrap().name == table)
+            .and_then(|t| t.long_value_catalog_definition.as_ref())
+            .unwrap()
+            .father_data_page_number;
+        let lv_tags = reader.load_lv_metadata(lv_page).unwrap();
+        assert!(orphans.iter().any(|lv| {
+            lv_tags[&lv.long_value_key]
+                .values()
+                .map(|tag| tag.size as u64)
+                .sum::<u64>()
+                < lv.size
+        }));
+        let mut data: Vec<Vec<u8>> = orphans.into_iter().map(|lv| lv.data).collect();
+        values.sort();
+        data.sort();
+        assert_eq!(data, values);
+    }
+
+    #[test]
+    fn test_dropped_tables() {
+        let table = "ROLE_ACCESS";
+        let jdb = init_tests(5, Some("Current.mdb"));
+        assert!(jdb.get_dropped_tables().is_empty());
+        let columns = jdb.get_columns(table).unwrap();
+        let rows = load_table_rows(&jdb, table).unwrap();
+
+        // the table, column and index entries of ROLE_ACCESS in MSysObjects are deleted
+        let catalog_page = 14;
+        let tags: Vec<(usize, Option<u16>)> = (14..=18).map(|i| (i, None)).collect();
+        let data = defunct_page_tags("Current.mdb", catalog_page, &tags);
+        let jdb = load_data(5, data.clone());
+        assert!(!jdb.get_tables().unwrap().contains(&table.to_string()));
+        assert!(jdb.open_table(table).is_err());
+        // the defunct catalog entries are only loaded on demand
+        assert!(jdb.get_dropped_tables().is_empty());
+        assert!(jdb.open_dropped_table(8).is_err());
+        let options = parser::reader::ReaderOptions {
+            include_dropped: true,
+            ..parser::reader::ReaderOptions::new(5)
+        };
+        let jdb =
+            ese_parser::EseParser::load_with_options(std::io::Cursor::new(data), options).unwrap();
+        assert!(!jdb.get_tables().unwrap().contains(&table.to_string()));
+
+        let catalog = jdb.get_reader().unwrap().load_catalog_with(true).unwrap();
+        let dropped: Vec<&jet::TableDefinition> = catalog.iter().filter(|t| t.dropped).collect();
+        assert_eq!(dropped.len(), 1);
+        assert_eq!(
+            dropped[0]
+                .index_definition_array
+                .iter()
+                .map(|i| i.name.as_str())
+                .collect::<Vec<&str>>(),
+            vec!["RoleGuid_index"]
+        );
+
+        let dropped_tables = jdb.get_dropped_tables();
+        assert_eq!(dropped_tables.len(), 1);
+        let dropped_table = &dropped_tables[0];
+        assert_eq!(
+            (dropped_table.object_identifier, dropped_table.name.as_str()),
+            (8, table)
+        );
+        assert_eq!(dropped_table.father_data_page_number, 31);
+        assert!(dropped_table.intact);
+        assert_eq!(
+            dropped_table
+                .columns
+                .iter()
+                .map(|c| (c.id, c.name.as_str()))
+                .collect::<Vec<(u32, &str)>>(),
+            columns
+                .iter()
+                .map(|c| (c.id, c.name.as_str()))
+                .collect::<Vec<(u32, &str)>>()
+        );
+        let table_id = jdb.open_dropped_table(8).unwrap();
+        assert_eq!(load_rows(&jdb, table_id, &columns).unwrap(), rows);
+        assert!(jdb.open_dropped_table(10).is_err());
+    }
+
+    #[test]
+    fn test_shadow_catalog() {
+        let db_name = "Current.mdb";
+        let jdb = init_tests(5, Some(db_name));
+        assert_eq!(jdb.get_catalog(), jet::FixedPageNumber::Catalog);
+        let tables = jdb.get_tables().unwrap();
+        assert!(jdb
+            .get_reader()
+            .unwrap()
+            .compare_catalogs()
+            .unwrap()
+            .is_empty());
+
+        // the catalog root page fails its checksum
+        let mut data = read_test_data(db_name);
+        let page_size = jdb.get_reader().unwrap().page_size() as usize;
+        page_mut(&mut data, page_size, jet::FixedPageNumber::Catalog as u32)[page_size / 2] ^= 0xff;
+        assert!(ese_parser::EseParser::load_with_catalog(
+            5,
+            std::io::Cursor::new(data.clone()),
+            jet::FixedPageNumber::Catalog
+        )
+        .is_err());
+        let jdb = load_data(5, data);
+        assert_eq!(jdb.get_catalog(), jet::FixedPageNumber::CatalogBackup);
+        assert_eq!(jdb.get_tables().unwrap(), tables);
+        let table_id = jdb.open_table("ROLE_ACCESS").unwrap();
+        assert!(jdb.move_row(table_id, ESE_MoveFirst).unwrap());
+        assert!(jdb.get_reader().unwrap().compare_catalogs().is_err());
+
+        // the entries of ROLE_ACCESS are deleted from the catalog, not from its shadow copy
+        let tags: Vec<(usize, Option<u16>)> = (14..=18).map(|i| (i, None)).collect();
+        let jdb = delete_page_tags(db_name, 14, &tags);
+        assert_eq!(jdb.get_catalog(), jet::FixedPageNumber::Catalog);
+        let differences = jdb.get_reader().unwrap().compare_catalogs().unwrap();
+        assert_eq!(differences.len(), 5);
+        assert!(differences.iter().all(|d| d.table_object_identifier == 8
+            && d.catalog.is_none()
+            && d.shadow.is_some()
+            && d.fields.is_empty()));
+        assert_eq!(differences[0].shadow.as_ref().unwrap().name, "ROLE_ACCESS");
+        let jdb = ese_parser::EseParser::load_with_catalog(
+            5,
+            std::io::Cursor::new(read_test_data(db_name)),
+            jet::FixedPageNumber::CatalogBackup,
+        )
+        .unwrap();
+        assert_eq!(jdb.get_tables().unwrap(), tables);
+    }
+
+    #[test]
+    fn test_checksum_policy() {
+        use parser::reader::*;
+
+        let db_name = "Current.mdb";
+        let table = "ROLE_ACCESS";
+        let jdb = init_tests(5, Some(db_name));
+        let rows = load_table_rows(&jdb, table).unwrap();
+        assert!(jdb.get_corruption_report().is_empty());
+
+        // a byte of the free space of the table leaf page is changed
+        let mut data = read_test_data(db_name);
+        let page_size = jdb.get_reader().unwrap().page_size() as usize;
+        page_mut(&mut data, page_size, 31)[1000] ^= 0xff;
+
+        let load = |checksum_policy| {
+            let options = ReaderOptions {
+                checksum_policy,
+                ..ReaderOptions::new(5)
+            };
+            ese_parser::EseParser::load_with_options(std::io::Cursor::new(data.clone()), options)
+                .unwrap()
+        };
+        let jdb = load(ChecksumPolicy::Strict);
+        assert!(load_table_rows(&jdb, table).is_err());
+        assert!(jdb.get_corruption_report().is_empty());
+
+        let jdb = load(ChecksumPolicy::Warn);
+        assert_eq!(load_table_rows(&jdb, table).unwrap(), rows);
+        // zero filled pages aren't reported
+        parser::space::load_page_owners(jdb.get_reader().unwrap()).unwrap();
+        let report = jdb.get_corruption_report();
+        assert_eq!(report.pages.len(), 1);
+        assert_eq!(report.pages[0].page_number, 31);
+        assert!(report.pages[0].error.contains("checksum"));
+
+        let jdb = load(ChecksumPolicy::Ignore);
+        assert_eq!(load_table_rows(&jdb, table).unwrap(), rows);
+        assert!(jdb.get_corruption_report().is_empty());
+    }
+
+    #[test]
+    fn test_ecc_correction() {
+        let db_name = "Current.mdb";
+        let table = "ROLE_ACCESS";
+        let jdb = init_tests(5, Some(db_name));
+        let rows = load_table_rows(&jdb, table).unwrap();
+        let page_size = jdb.get_reader().unwrap().page_size() as usize;
+
+        // a bit of the first record of the table leaf page is flipped
+        let mut data = read_test_data(db_name);
+        let bit = (40 + 16 + 10) * 8 + 3;
+        let mut corrupted = data.clone();
+        page_mut(&mut corrupted, page_size, 31)[bit / 8] ^= 1 << (bit % 8);
+        // cache of a single page, the corrected page is served after its eviction
+        let jdb = load_data(1, corrupted.clone());
+        assert_eq!(load_table_rows(&jdb, table).unwrap(), rows);
+        assert_eq!(load_table_rows(&jdb, table).unwrap(), rows);
+        let report = jdb.get_corruption_report();
+        assert!(report.pages.is_empty());
+        assert_eq!(report.corrected.len(), 1);
+        assert_eq!(
+            (
+                report.corrected[0].page_number,
+                report.corrected[0].bits.clone()
+            ),
+            (31, vec![bit])
+        );
+        let reader = jdb.get_reader().unwrap();
+        assert!(reader.verify_page_checksum(31).is_ok());
+        assert_eq!(
+            reader.read_bytes(32 * page_size as u64, page_size).unwrap(),
+            page_mut(&mut data, page_size, 31)
+        );
+
+        // two flipped bits aren't correctable
+        page_mut(&mut corrupted, page_size, 31)[bit / 8 + 1] ^= 1;
+        let jdb = load_data(5, corrupted);
+        assert!(jdb
+            .open_table(table)
+            .and_then(|table_id| jdb.move_row(table_id, ESE_MoveFirst))
+            .is_err());
+        assert!(jdb.get_corruption_report().is_empty());
+    }
+
+    #[test]
+    fn test_verify() {
+        use parser::verify::*;
+
+        let errors = |jdb: &ese_parser::EseParser<std::io::Cursor<Vec<u8>>>| {
+            let report = jdb.verify().unwrap();
+            report
+                .errors
+                .iter()
+                .map(|e| (e.kind, e.page_number, e.table.clone(), e.name.clone()))
+                .collect::<Vec<_>>()
+        };
+
+        for db_name in ["Current.mdb", "decompress_test2.edb", "test.edb"] {
+            let report = init_tests(5, Some(db_name)).verify().unwrap();
+            assert!(report.is_consistent(), "{}: {:?}", db_name, report.errors);
+            assert!(report.corrected.is_empty());
+        }
+        let report = init_tests(5, Some("Current.mdb")).verify().unwrap();
+        assert_eq!(report.tree_count, 29);
+
+        // CLIENTS has the leaf pages 73 and 74, under its root page 33
+        let clients = "CLIENTS".to_string();
+        let jdb = load_data(
+            5,
+            edit_page("Current.mdb", 74, |page| {
+                // previous page of the header
+                page[16..20].copy_from_slice(&0u32.to_le_bytes());
+            }),
+        );
+        assert_eq!(
+            errors(&jdb),
+            vec![(
+                IntegrityErrorKind::SiblingLink,
+                74,
+                clients.clone(),
+                clients.clone()
+            )]
+        );
+
+        let jdb = load_data(
+            5,
+            edit_page("Current.mdb", 73, |page| {
+                // the tags 2 and 3 are swapped
+                let page_size = page.len();
+                let (tag3, tag2) = page[page_size - 16..page_size - 8].split_at_mut(4);
+                tag3.swap_with_slice(tag2);
+            }),
+        );
+        assert!(errors(&jdb).iter().all(|e| *e
+            == (
+                IntegrityErrorKind::KeyOrder,
+                73,
+                clients.clone(),
+                clients.clone()
+            )));
+        assert!(!errors(&jdb).is_empty());
+
+        let mut data = read_test_data("Current.mdb");
+        page_mut(&mut data, 4096, 73)[1000] ^= 0x11;
+        assert_eq!(
+            errors(&load_data(5, data)),
+            vec![(
+                IntegrityErrorKind::Checksum,
+                73,
+                String::new(),
+                String::new()
+            )]
+        );
+
+        // a chunk of a long value of test_table is missing
+        let jdb = delete_page_tags("decompress_test2.edb", 40, &[(5, None)]);
+        let lv_errors = errors(&jdb);
+        assert_eq!(lv_errors.len(), 1);
+        assert_eq!(lv_errors[0].0, IntegrityErrorKind::LongValue);
+        assert_eq!(lv_errors[0].2, "test_table");
+    }
+
+    // a log file of the database, with a checksum record starting each range of records
+    fn build_log(db_name: &str, generation: u32, ranges: &[Vec<u8>]) -> Vec<u8> {
+        let db = read_test_data(db_name);
+        let mut data = vec![0u8; parser::log::LOG_FILE_HEADER_SIZE];
+        data[4..8].copy_from_slice(&generation.to_le_bytes());
+        // sector size, header and file sizes in sectors
+        data[8..10].copy_from_slice(&512u16.to_le_bytes());
+        data[10..12].copy_from_slice(&8u16.to_le_bytes());
+        data[12..14].copy_from_slice(&16u16.to_le_bytes());
+        // created on 2024-01-01 12:00:00 UTC
+        data[16..24].copy_from_slice(&[0, 0, 12, 1, 1, 124, 1, 0]);
+        // log signature of the database header
+        data[44..72].copy_from_slice(&db[108..136]);
+        let checksum = utils::calc_crc32(&data);
+        data[..4].copy_from_slice(&checksum.to_le_bytes());
+
+        for (i, records) in ranges.iter().enumerate() {
+            let cb_next = if i + 1 < ranges.len() {
+                22 + records.len() as u32
+            } else {
+                0
+            };
+            data.push(parser::log::LRTYP_CHECKSUM);
+            data.extend_from_slice(&0u32.to_le_bytes());
+            data.extend_from_slice(&(records.len() as u32).to_le_bytes());
+            data.extend_from_slice(&cb_next.to_le_bytes());
+            data.extend_from_slice(&[0u8; 9]);
+            data.extend_from_slice(records);
+        }
+        data
+    }
+
+    fn transaction_record(lrtyp: u8, procid: u16, level: u8, trx: Option<u32>) -> Vec<u8> {
+        let mut record = vec![lrtyp];
+        record.extend_from_slice(&procid.to_le_bytes());
+        record.push(level);
+        if let Some(trx) = trx {
+            record.extend_from_slice(&trx.to_le_bytes());
+        }
+        record
+    }
+
+    // sizes: the key prefix size and key suffix of inserts, the old data size of replaces
+    fn node_record(
+        lrtyp: u8,
+        object_identifier: u32,
+        page_number: u32,
+        iline: u16,
+        dbtime: u64,
+        sizes: Option<(u16, &[u8])>,
+        data: &[u8],
+    ) -> Vec<u8> {
+        let mut record = vec![lrtyp];
+        record.extend_from_slice(&1u16.to_le_bytes());
+        record.push(1);
+        record.extend_from_slice(&page_number.to_le_bytes());
+        record.extend_from_slice(&object_identifier.to_le_bytes());
+        record.extend_from_slice(&(dbtime - 1).to_le_bytes());
+        record.extend_from_slice(&dbtime.to_le_bytes());
+        record.extend_from_slice(&0u32.to_le_bytes());
+        record.push(1);
+        record.extend_from_slice(&iline.to_le_bytes());
+        if let Some((size, key)) = sizes {
+            record.extend_from_slice(&size.to_le_bytes());
+            record.extend_from_slice(&(key.len() as u16).to_le_bytes());
+            record.extend_from_slice(&(data.len() as u16).to_le_bytes());
+            record.extend_from_slice(key);
+            record.extend_from_slice(data);
+        }
+        record
+    }
+
+    // the page record starting the split and merge records, of the session 1
+    fn page_record(lrtyp: u8, object_identifier: u32, page_number: u32, dbtime: u64) -> Vec<u8> {
+        let mut record = node_record(lrtyp, object_identifier, page_number, 0, dbtime, None, b"");
+        record.truncate(record.len() - 2);
+        record
+    }
+
+    // split and merge records of the ROLE_ACCESS tree (object 8) of Current.mdb
+    // pages: the new, parent and right pages; flags: of the split and new pages;
+    // keys: the parent key, the key prefixes of the split and new pages
+    fn split_record(
+        page_number: u32,
+        dbtime: u64,
+        split_type: u8,
+        pages: [u32; 3],
+        iline_split: u16,
+        flags: [u32; 2],
+        keys: [&[u8]; 3],
+    ) -> Vec<u8> {
+        let mut record = page_record(parser::log::LRTYP_SPLIT, 8, page_number, dbtime);
+        for page in pages {
+            record.extend_from_slice(&page.to_le_bytes());
+        }
+        record.extend_from_slice(&[0u8; 16]);
+        record.extend_from_slice(&[split_type, 0]);
+        record.extend_from_slice(&iline_split.to_le_bytes());
+        record.extend_from_slice(&[0u8; 4]);
+        for flags in flags {
+            record.extend_from_slice(&flags.to_le_bytes());
+        }
+        for key in keys {
+            record.extend_from_slice(&(key.len() as u16).to_le_bytes());
+        }
+        record.extend_from_slice(&keys.concat());
+        record
+    }
+
+    // pages: the right, left and parent pages
+    fn merge_record(
+        page_number: u32,
+        dbtime: u64,
+        merge_type: u8,
+        pages: [u32; 3],
+        iline_merge: u16,
+        iline_parent: u16,
+        parent_key: &[u8],
+    ) -> Vec<u8> {
+        let mut record = page_record(parser::log::LRTYP_MERGE, 8, page_number, dbtime);
+        for page in pages {
+            record.extend_from_slice(&page.to_le_bytes());
+        }
+        record.extend_from_slice(&[0u8; 24]);
+        record.push(merge_type);
+        record.extend_from_slice(&iline_merge.to_le_bytes());
+        record.extend_from_slice(&iline_parent.to_le_bytes());
+        record.extend_from_slice(&(parent_key.len() as u16).to_le_bytes());
+        record.extend_from_slice(parent_key);
+        record
+    }
+
+    fn write_log(name: &str, data: &[u8]) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("ese_parser_{}_{}", std::process::id(), name));
+        std::fs::write(&path, data).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_log() {
+        use nom_derive::Parse;
+        use parser::log::*;
+
+        let jdb = init_tests(5, Some("Current.mdb"));
+        let db = read_test_data("Current.mdb");
+        let (_, log_signature) = jet::Signature::parse_le(&db[108..]).unwrap();
+        assert_eq!(jdb.get_reader().unwrap().log_signature(), log_signature);
+
+        let ranges = vec![
+            [
+                transaction_record(LRTYP_BEGIN0, 1, 1, Some(0x10)),
+                node_record(LRTYP_INSERT, 10, 73, 2, 100, Some((3, b"key")), b"data"),
+                node_record(LRTYP_REPLACE, 8, 31, 1, 101, Some((4, b"")), b"new"),
+                transaction_record(LRTYP_COMMIT0, 1, 0, Some(0x11)),
+                // decoded by its size only
+                vec![LRTYP_TRACE, 1, 0, 3, 0, b'a', b'b', b'c'],
+            ]
+            .concat(),
+            // without a known size the rest of the range is skipped
+            vec![200, 1, 2, 3, 4],
+            [
+                node_record(LRTYP_FLAG_DELETE, 9999, 80, 3, 102, None, b""),
+                transaction_record(LRTYP_ROLLBACK, 1, 0, None),
+            ]
+            .concat(),
+        ];
+        let path = write_log("edb00007.log", &build_log("Current.mdb", 7, &ranges));
+        let log = jdb.load_log(&path).unwrap();
+        assert_eq!(log.header.generation, 7);
+        // the unknown record and the rest of its range are reported
+        assert!(!log.is_complete());
+        assert_eq!(log.truncated.len(), 1);
+        let truncated = &log.truncated[0];
+        assert_eq!(truncated.size, 5);
+        assert_eq!({ truncated.position.l_generation }, 7);
+        assert!(truncated.error.contains("record type 200"));
+        assert_eq!(
+            log.records.iter().map(|r| r.lrtyp).collect::<Vec<_>>(),
+            vec![
+                LRTYP_BEGIN0,
+                LRTYP_INSERT,
+                LRTYP_REPLACE,
+                LRTYP_COMMIT0,
+                LRTYP_TRACE,
+                LRTYP_FLAG_DELETE,
+                LRTYP_ROLLBACK
+            ]
+        );
+        // the first record follows the checksum record at the start of the sector 8
+        assert_eq!(
+            log.records[0].position,
+            jet::LgPos {
+                ib: 22,
+                isec: 8,
+                l_generation: 7
+            }
+        );
+        assert!(log
+            .records
+            .windows(2)
+            .all(|r| r[0].position < r[1].position));
+        assert_eq!(
+            log.records[0].operation,
+            LogOperation::BeginTransaction {
+                level: 1,
+                trx: Some(0x10)
+            }
+        );
+
+        let insert = log.records[1].operation.node().unwrap();
+        assert_eq!(
+            (insert.page_number, insert.iline, insert.dbtime_after),
+            (73, 2, 100)
+        );
+        assert_eq!((insert.key_prefix_size, &insert.key[..]), (3, &b"key"[..]));
+        assert_eq!(insert.data, b"data");
+        assert_eq!(
+            insert.object_type,
+            Some(parser::space::SpaceObjectType::Table)
+        );
+        assert_eq!(insert.table, "CLIENTS");
+
+        let replace = log.records[2].operation.node().unwrap();
+        assert!(replace.key.is_empty());
+        assert_eq!(replace.data, b"new");
+        assert_eq!(replace.table, "ROLE_ACCESS");
+
+        assert_eq!(
+            (log.records[4].procid, &log.records[4].operation),
+            (1, &LogOperation::Other { page_number: None })
+        );
+        let delete = log.records[5].operation.node().unwrap();
+        assert_eq!((delete.object_type, delete.table.as_str()), (None, ""));
+        std::fs::remove_file(&path).unwrap();
+
+        // the log of another database
+        let path = write_log("edb00008.log", &build_log("test.edb", 8, &ranges));
+        assert!(jdb.load_log(&path).is_err());
+        assert_eq!(load_log_file(&path).unwrap().records.len(), 7);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_replay_logs() {
+        use parser::log::*;
+
+        let rows = |jdb: &dyn EseDb| load_table_rows(jdb, "ROLE_ACCESS").unwrap();
+
+        // the database is dirty, its header requires the log generation 124
+        let db = read_test_data("Current.mdb");
+        let before = rows(&load_data(5, db.clone()));
+        assert_eq!(before.len(), 3);
+
+        // ROLE_ACCESS leaf page 31, its rows are the tags 1 to 3
+        let page = &db[32 * 4096..33 * 4096];
+        let dbtime = u64::from_le_bytes(page[8..16].try_into().unwrap());
+        let tag_3 = &page[40 + 102..40 + 102 + 43];
+        let key_size = 2 + u16::from_le_bytes([tag_3[0], tag_3[1]]) as usize;
+        // node records of the session 1 moved to another session
+        let session = |procid: u16, mut record: Vec<u8>| {
+            record[1..3].copy_from_slice(&procid.to_le_bytes());
+            record
+        };
+        let replace = node_record(
+            LRTYP_REPLACE,
+            8,
+            31,
+            0,
+            dbtime + 1,
+            Some((43, b"")),
+            &tag_3[key_size..],
+        );
+        let records = [
+            transaction_record(LRTYP_BEGIN0, 1, 1, Some(1)),
+            replace,
+            transaction_record(LRTYP_COMMIT0, 1, 0, Some(2)),
+            // rolled back
+            transaction_record(LRTYP_BEGIN0, 2, 1, Some(3)),
+            session(
+                2,
+                node_record(LRTYP_FLAG_DELETE, 8, 31, 1, dbtime + 2, None, b""),
+            ),
+            transaction_record(LRTYP_ROLLBACK, 2, 0, None),
+            // not committed
+            transaction_record(LRTYP_BEGIN0, 3, 1, Some(4)),
+            session(
+                3,
+                node_record(LRTYP_DELETE, 8, 31, 2, dbtime + 3, None, b""),
+            ),
+            // already on the page
+            transaction_record(LRTYP_BEGIN0, 1, 1, Some(5)),
+            node_record(LRTYP_FLAG_DELETE, 8, 31, 1, dbtime, None, b""),
+            transaction_record(LRTYP_COMMIT0, 1, 0, Some(6)),
+            transaction_record(LRTYP_BEGIN0, 1, 1, Some(7)),
+            node_record(LRTYP_FLAG_DELETE, 8, 31, 2, dbtime + 4, None, b""),
+            transaction_record(LRTYP_COMMIT0, 1, 0, Some(8)),
+        ]
+        .concat();
+
+        let log_dir = std::env::temp_dir().join(format!("ese_parser_{}_logs", std::process::id()));
+        std::fs::create_dir_all(&log_dir).unwrap();
+        std::fs::write(
+            log_dir.join("edb0007C.log"),
+            build_log("Current.mdb", 124, &[records]),
+        )
+        .unwrap();
+        // the log of another database is ignored
+        std::fs::write(
+            log_dir.join("edb00001.log"),
+            build_log("test.edb", 1, &[vec![]]),
+        )
+        .unwrap();
+
+        let jdb =
+            ese_parser::EseParser::load_with_logs(5, std::io::Cursor::new(db.clone()), &log_dir)
+                .unwrap();
+        let report = jdb.get_replay_report().unwrap();
+        assert_eq!(report.generations, vec![124]);
+        assert_eq!(
+            (report.applied, report.skipped, report.discarded),
+            (2, 1, 2)
+        );
+        assert_eq!(report.pages, vec![31]);
+        assert!(report.is_complete());
+        assert_eq!(report.errors.len(), 1);
+        assert!(report.errors[0].contains("edb00001.log"));
+        // the first row has the columns of the third one, the third one is deleted
+        assert_eq!(rows(&jdb), vec![before[2].clone(), before[1].clone()]);
+        assert_eq!(jdb.count_rows("ROLE_ACCESS").unwrap(), 2);
+        let report = jdb.verify().unwrap();
+        assert!(report.is_consistent(), "{:?}", report.errors);
+
+        // the data of the first row replaced by the difference with the data of the third one
+        let data = |tag: usize| {
+            let entry = &page[4096 - 4 * tag - 4..4096 - 4 * tag];
+            let size = u16::from_le_bytes([entry[0], entry[1]]) as usize & 0x1fff;
+            let offset = u16::from_le_bytes([entry[2], entry[3]]) as usize & 0x1fff;
+            let node = &page[40 + offset..40 + offset + size];
+            node[2 + u16::from_le_bytes([node[0], node[1]]) as usize..].to_vec()
+        };
+        let (old, new) = (data(1), data(3));
+        let same = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+        let mut diffs = (same as u16 | 0x8000).to_le_bytes().to_vec();
+        diffs.extend_from_slice(&((old.len() - same) as u16).to_le_bytes());
+        diffs.extend_from_slice(&((new.len() - same) as u16).to_le_bytes());
+        diffs.extend_from_slice(&new[same..]);
+        let replace_diff = |old_size: usize| {
+            [
+                transaction_record(LRTYP_BEGIN0, 1, 1, Some(1)),
+                node_record(
+                    LRTYP_REPLACE_D,
+                    8,
+                    31,
+                    0,
+                    dbtime + 1,
+                    Some((old_size as u16, b"")),
+                    &diffs,
+                ),
+                // not replayed, the pages may be out of date
+                page_record(LRTYP_SET_EXTERNAL_HEADER, 8, 31, dbtime + 2),
+                vec![0, 0],
+                transaction_record(LRTYP_COMMIT0, 1, 0, Some(2)),
+            ]
+            .concat()
+        };
+        std::fs::write(
+            log_dir.join("edb0007C.log"),
+            build_log("Current.mdb", 124, &[replace_diff(old.len())]),
+        )
+        .unwrap();
+        let jdb =
+            ese_parser::EseParser::load_with_logs(5, std::io::Cursor::new(db.clone()), &log_dir)
+                .unwrap();
+        let report = jdb.get_replay_report().unwrap();
+        assert_eq!(report.applied, 1);
+        assert!(!report.is_complete());
+        assert_eq!(report.unapplied.len(), 1);
+        assert_eq!(report.unapplied[0].1, 31);
+        assert_eq!(
+            rows(&jdb),
+            vec![before[2].clone(), before[1].clone(), before[2].clone()]
+        );
+        let report = jdb.verify().unwrap();
+        assert!(report.is_consistent(), "{:?}", report.errors);
+
+        // a committed operation which doesn't match the page fails the load
+        std::fs::write(
+            log_dir.join("edb0007C.log"),
+            build_log("Current.mdb", 124, &[replace_diff(old.len() + 1)]),
+        )
+        .unwrap();
+        let result =
+            ese_parser::EseParser::load_with_logs(5, std::io::Cursor::new(db.clone()), &log_dir);
+        assert!(result.is_err_and(|e| e.as_str().contains("page 31")));
+
+        // a record which can't be decoded, the replay would miss its operation
+        std::fs::write(
+            log_dir.join("edb0007C.log"),
+            build_log("Current.mdb", 124, &[vec![200, 1, 2, 3]]),
+        )
+        .unwrap();
+        let result =
+            ese_parser::EseParser::load_with_logs(5, std::io::Cursor::new(db.clone()), &log_dir);
+        assert!(result.is_err_and(|e| e.as_str().contains("not completely decoded")));
+
+        // the required generation is missing
+        std::fs::remove_file(log_dir.join("edb0007C.log")).unwrap();
+        std::fs::write(
+            log_dir.join("edb00007.log"),
+            build_log("Current.mdb", 7, &[vec![]]),
+        )
+        .unwrap();
+        assert!(
+            ese_parser::EseParser::load_with_logs(5, std::io::Cursor::new(db), &log_dir).is_err()
+        );
+        std::fs::remove_dir_all(&log_dir).unwrap();
+    }
+
+    #[test]
+    fn test_replay_split_merge() {
+        use parser::log::*;
+        use parser::verify::*;
+
+        let rows = |jdb: &dyn EseDb| load_table_rows(jdb, "ROLE_ACCESS").unwrap();
+        let db = read_test_data("Current.mdb");
+        let before = rows(&load_data(5, db.clone()));
+
+        // ROLE_ACCESS root leaf page 31, split into the pages 75 and 76 of the available space
+        // of the database, then merged back into 76
+        let page = &db[32 * 4096..33 * 4096];
+        let dbtime = u64::from_le_bytes(page[8..16].try_into().unwrap());
+        let flags = u32::from_le_bytes(page[36..40].try_into().unwrap());
+        let (root, leaf, parent) = (
+            jet::PageFlags::IS_ROOT.bits(),
+            jet::PageFlags::IS_LEAF.bits(),
+            jet::PageFlags::IS_PARENT.bits(),
+        );
+        let key = |iline: usize| {
+            let tag = 4096 - 4 * (iline + 1) - 4;
+            let size = u16::from_le_bytes([page[tag], page[tag + 1]]) as usize & 0x1fff;
+            let offset = u16::from_le_bytes([page[tag + 2], page[tag + 3]]) as usize & 0x1fff;
+            let data = &page[40 + offset..40 + offset + size];
+            data[2..2 + u16::from_le_bytes([data[0], data[1]]) as usize].to_vec()
+        };
+        let (key_1, key_2) = (key(1), key(2));
+        let prefix = &key_1[..key_1.iter().zip(&key_2).take_while(|(a, b)| a == b).count()];
+        assert!(!prefix.is_empty());
+        let operations = [
+            split_record(
+                31,
+                dbtime + 1,
+                SPLIT_TYPE_VERTICAL,
+                [75, 0, 0],
+                0,
+                [flags & !leaf | parent, flags & !root],
+                [b"", b"", b""],
+            ),
+            split_record(
+                75,
+                dbtime + 2,
+                SPLIT_TYPE_RIGHT,
+                [76, 31, 0],
+                1,
+                [flags & !root, flags & !root],
+                [&key_1, b"", prefix],
+            ),
+            merge_record(
+                75,
+                dbtime + 3,
+                MERGE_TYPE_FULL_RIGHT,
+                [76, 0, 31],
+                0,
+                0,
+                b"",
+            ),
+        ];
+
+        let log_dir =
+            std::env::temp_dir().join(format!("ese_parser_{}_split_merge", std::process::id()));
+        std::fs::create_dir_all(&log_dir).unwrap();
+        for (count, pages, tree_pages) in [
+            (1, vec![31, 75], vec![75]),
+            (2, vec![31, 75, 76], vec![75, 76]),
+            (3, vec![31, 75, 76], vec![76]),
+        ] {
+            let records = [
+                vec![transaction_record(LRTYP_BEGIN0, 1, 1, Some(1))],
+                operations[..count].to_vec(),
+                vec![transaction_record(LRTYP_COMMIT0, 1, 0, Some(2))],
+            ]
+            .concat()
+            .concat();
+            std::fs::write(
+                log_dir.join("edb0007C.log"),
+                build_log("Current.mdb", 124, &[records]),
+            )
+            .unwrap();
+            let jdb = ese_parser::EseParser::load_with_logs(
+                5,
+                std::io::Cursor::new(db.clone()),
+                &log_dir,
+            )
+            .unwrap();
+            let report = jdb.get_replay_report().unwrap();
+            assert_eq!((report.applied, report.pages.clone()), (count, pages));
+            assert_eq!(rows(&jdb), before);
+            // the space tree records aren't in the log, the new pages aren't owned by the table
+            let report = jdb.verify().unwrap();
+            assert!(!report.errors.is_empty());
+            assert!(
+                report
+                    .errors
+                    .iter()
+                    .all(|e| e.kind == IntegrityErrorKind::Space
+                        && tree_pages.contains(&e.page_number)),
+                "{:?}",
+                report.errors
+            );
+        }
+        std::fs::remove_dir_all(&log_dir).unwrap();
+    }
+
+    #[test]
+    fn test_timeline() {
+        use parser::log::*;
+        use parser::timeline::*;
+
+        let jdb = init_tests(5, Some("Current.mdb"));
+        let columns = jdb.get_columns("ROLE_ACCESS").unwrap();
+        let table_id = jdb.open_table("ROLE_ACCESS").unwrap();
+        jdb.move_row(table_id, ESE_MoveFirst).unwrap();
+        let first_key = jdb.get_record_key(table_id).unwrap().0;
+        jdb.move_row(table_id, ESE_MoveLast).unwrap();
+        let last_row = columns
+            .iter()
+            .map(|c| (c.name.clone(), jdb.get_column(table_id, c.id).unwrap()))
+            .collect::<Vec<_>>();
+
+        // ROLE_ACCESS leaf page 31, the record of its last tag inserted again with another key
+        let db = read_test_data("Current.mdb");
+        let page = &db[32 * 4096..33 * 4096];
+        let tag_3 = &page[40 + 102..40 + 102 + 43];
+        let key_size = 2 + u16::from_le_bytes([tag_3[0], tag_3[1]]) as usize;
+        let record = &tag_3[key_size..];
+        let records = [
+            transaction_record(LRTYP_BEGIN0, 1, 1, Some(1)),
+            node_record(LRTYP_INSERT, 8, 31, 3, 1, Some((0, b"new key")), record),
+            // the first record overwritten
+            node_record(LRTYP_REPLACE, 8, 31, 0, 2, Some((43, b"")), record),
+            // not a table b-tree
+            node_record(LRTYP_FLAG_DELETE, 9999, 80, 0, 3, None, b""),
+            // not changes of records, the records of the other types are decoded by their size
+            vec![LRTYP_TRACE, 1, 0, 3, 0, b'a', b'b', b'c'],
+            page_record(LRTYP_SET_EXTERNAL_HEADER, 8, 31, 3),
+            vec![0, 0],
+            split_record(
+                31,
+                3,
+                SPLIT_TYPE_VERTICAL,
+                [75, 0, 0],
+                0,
+                [0, 0],
+                [b"", b"", b""],
+            ),
+            transaction_record(LRTYP_COMMIT0, 1, 0, Some(2)),
+            transaction_record(LRTYP_BEGIN0, 1, 1, Some(3)),
+            node_record(LRTYP_FLAG_DELETE, 8, 31, 0, 4, None, b""),
+            transaction_record(LRTYP_ROLLBACK, 1, 0, None),
+        ]
+        .concat();
+        let log_dir =
+            std::env::temp_dir().join(format!("ese_parser_{}_timeline", std::process::id()));
+        std::fs::create_dir_all(&log_dir).unwrap();
+        // followed by a range with a record which can't be decoded
+        std::fs::write(
+            log_dir.join("edb00005.log"),
+            build_log("Current.mdb", 5, &[records, vec![200, 1, 2, 3]]),
+        )
+        .unwrap();
+
+        let timeline = jdb.get_timeline(&log_dir).unwrap();
+        std::fs::remove_dir_all(&log_dir).unwrap();
+        assert!(!timeline.is_complete());
+        assert_eq!(timeline.truncated.len(), 1);
+        assert_eq!(
+            (
+                { timeline.truncated[0].position.l_generation },
+                timeline.truncated[0].size
+            ),
+            (5, 4)
+        );
+        assert!(timeline.errors.is_empty());
+        let timeline = timeline.changes;
+        assert_eq!(
+            timeline
+                .iter()
+                .map(|c| (c.table.as_str(), c.operation, c.committed))
+                .collect::<Vec<_>>(),
+            vec![
+                ("ROLE_ACCESS", ChangeOperation::Insert, true),
+                ("ROLE_ACCESS", ChangeOperation::Update, true),
+                ("ROLE_ACCESS", ChangeOperation::Delete, false)
+            ]
+        );
+        assert_eq!(timeline[0].key, b"new key");
+        assert_eq!(timeline[1].key, first_key);
+        assert_eq!(timeline[2].key, first_key);
+        assert!(timeline[2].columns.is_empty());
+        for change in &timeline[..2] {
+            assert_eq!(
+                change
+                    .columns
+                    .iter()
+                    .map(|c| (c.name.clone(), c.value.clone()))
+                    .collect::<Vec<_>>(),
+                last_row
+            );
+        }
+        assert_eq!((timeline[0].time.year, timeline[0].time.hours), (124, 12));
+        assert_eq!({ timeline[0].position.l_generation }, 5);
+    }
+
+    // a checkpoint of the log stream of the database, with the database attached
+    fn build_checkpoint(db_name: &str, generation: u32) -> Vec<u8> {
+        let db = read_test_data(db_name);
+        let mut data = vec![0u8; parser::checkpoint::CHECKPOINT_FILE_SIZE];
+        data[16..20].copy_from_slice(&generation.to_le_bytes());
+        data[20..48].copy_from_slice(&db[108..136]);
+        let path = "C:\\db\\"
+            .encode_utf16()
+            .chain(db_name.encode_utf16())
+            .flat_map(|c| c.to_le_bytes())
+            .collect::<Vec<u8>>();
+        let attach = parser::checkpoint::CHECKPOINT_ATTACH_INFO_OFFSET;
+        data[attach] = 1;
+        data[attach + 2..attach + 4].copy_from_slice(&(path.len() as u16).to_le_bytes());
+        data[attach + 20..attach + 48].copy_from_slice(&db[24..52]);
+        data[attach + 48..attach + 48 + path.len()].copy_from_slice(&path);
+        let checksum = utils::calc_crc32(&data);
+        data[..4].copy_from_slice(&checksum.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn test_checkpoint() {
+        use parser::checkpoint::*;
+
+        let checkpoint = parse_checkpoint(&build_checkpoint("Current.mdb", 5)).unwrap();
+        assert_eq!(checkpoint.generation(), 5);
+        assert_eq!(checkpoint.attachments.len(), 1);
+        assert_eq!(checkpoint.attachments[0].path, "C:\\db\\Current.mdb");
+        let jdb = init_tests(5, Some("Current.mdb"));
+        let reader = jdb.get_reader().unwrap();
+        assert!(checkpoint
+            .attachment(&reader.database_signature())
+            .is_some());
+        assert_eq!(checkpoint.header.log_signature, reader.log_signature());
+
+        let dir =
+            std::env::temp_dir().join(format!("ese_parser_{}_checkpoint", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let write = |name: &str, data: Vec<u8>| std::fs::write(dir.join(name), data).unwrap();
+        // the database is dirty, its header requires the log generation 124
+        write("edb.chk", build_checkpoint("Current.mdb", 123));
+        write("edb0007B.log", build_log("Current.mdb", 123, &[vec![]]));
+        write("edb0007C.log", build_log("Current.mdb", 124, &[vec![]]));
+        let report = jdb.check_file_set(Some(dir.join("edb.chk")), &dir).unwrap();
+        assert!(report.is_consistent(), "{:?}", report);
+        assert_eq!(
+            (report.first_generation, report.last_generation),
+            (123, 124)
+        );
+
+        write("edb0007E.log", build_log("Current.mdb", 126, &[vec![]]));
+        write("other.log", build_log("test.edb", 125, &[vec![]]));
+        let report = jdb.check_file_set(Some(dir.join("edb.chk")), &dir).unwrap();
+        assert_eq!(report.missing_generations, vec![125]);
+        assert_eq!(report.mismatches.len(), 1);
+
+        // another database attached to the log stream, with the path of the database
+        let mut data = build_checkpoint("Current.mdb", 123);
+        let attach = CHECKPOINT_ATTACH_INFO_OFFSET;
+        data[attach + 20..attach + 48].copy_from_slice(&read_test_data("test.edb")[24..52]);
+        let checksum = utils::calc_crc32(&data);
+        data[..4].copy_from_slice(&checksum.to_le_bytes());
+        write("edb.chk", data);
+        let report = jdb.check_file_set(Some(dir.join("edb.chk")), &dir).unwrap();
+        assert_eq!(report.mismatches.len(), 2);
+        assert!(report
+            .mismatches
+            .iter()
+            .any(|m| m.contains("not attached") && m.contains("C:\\db\\Current.mdb")));
+
+        write("edb.chk", build_checkpoint("test.edb", 123));
+        let report = jdb.check_file_set(Some(dir.join("edb.chk")), &dir).unwrap();
+        assert_eq!(report.mismatches.len(), 2);
+        assert_eq!(report.first_generation, 124);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let reader = jdb.get_reader().unwrap();
+        let report = check_file_set(reader, None, &[]);
+        assert_eq!(report.missing_generations, vec![124]);
+
+        // a clean copy of the database doesn't require its last generations
+        let mut db = read_test_data("Current.mdb");
+        db[52..56].copy_from_slice(&(jet::DbState::CleanShutdown as u32).to_le_bytes());
+        let checksum = utils::calc_crc32(&db[..4096]);
+        db[..4].copy_from_slice(&checksum.to_le_bytes());
+        let reader = parser::reader::Reader::load_db(std::io::Cursor::new(db), 5).unwrap();
+        assert!(check_file_set(&reader, None, &[]).is_consistent());
+        let logs = [5, 7]
+            .iter()
+            .map(|g| parser::log::parse_log(&build_log("Current.mdb", *g, &[vec![]])).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            check_file_set(&reader, None, &logs).missing_generations,
+            vec![6]
+        );
+        // nor the logs from its checkpoint
+        let checkpoint = parse_checkpoint(&build_checkpoint("Current.mdb", 3)).unwrap();
+        let report = check_file_set(&reader, Some(&checkpoint), &logs[..1]);
+        assert!(report.is_consistent(), "{:?}", report);
+        assert_eq!((report.first_generation, report.last_generation), (5, 5));
+    }
+
+    #[test]
+    fn test_carve_records() {
+        use parser::carve::*;
+
+        let row_of = |jdb: &dyn EseDb, record: &CarvedRecord| {
+            let table_id = jdb.open_table(&record.table).unwrap();
+            let mut ok = jdb.move_row(table_id, ESE_MoveFirst).unwrap();
+            while ok {
+                if record
+                    .columns
+                    .iter()
+                    .all(|(id, value)| &jdb.get_column(table_id, *id).unwrap() == value)
+                {
+                    return true;
+                }
+                ok = jdb.move_row(table_id, ESE_MoveNext).unwrap();
+            }
+            false
+        };
+
+        // stale copies of CLIENTS records left by page splits
+        let jdb = init_tests(5, Some("Current.mdb"));
+        let records = carve_records(jdb.get_reader().unwrap()).unwrap();
+        assert_eq!(records.len(), 19);
+        assert!(records.iter().all(|r| r.table == "CLIENTS"
+            && r.region == CarvedRegion::Unallocated
+            && (r.page_number == 73 || r.page_number == 74)));
+        let record = &records[0];
+        assert_eq!(
+            (record.page_number, record.offset, record.size),
+            (73, 3697, 63)
+        );
+        assert_eq!(
+            record
+                .columns
+                .iter()
+                .map(|(id, _)| *id)
+                .collect::<Vec<u32>>(),
+            vec![1, 2, 3, 4, 5, 128]
+        );
+        assert!(row_of(&jdb, record));
+
+        // fixed size columns are placed by their identifiers, with gaps for deleted columns
+        let reader = jdb.get_reader().unwrap();
+        let catalog = reader.load_catalog().unwrap();
+        let clients = catalog
+            .iter()
+            .find(|t| t.table_catalog_definition.as_ref().unwrap().name == "CLIENTS")
+            .unwrap();
+        let page_size = reader.page_size() as u64;
+        let mut data = reader.read_bytes((73 + 1) * page_size + 3697, 63).unwrap();
+        assert_eq!(decode_record(clients, &data, 0).unwrap().1, record.columns);
+        let without = |ids: &[u32]| {
+            let mut tbl_def = clients.clone();
+            tbl_def
+                .column_catalog_definition_array
+                .retain(|col| !ids.contains(&col.identifier));
+            tbl_def
+        };
+        let values_without = |ids: &[u32]| -> ColumnValues {
+            record
+                .columns
+                .iter()
+                .filter(|(id, _)| !ids.contains(id))
+                .cloned()
+                .collect()
+        };
+        let gap = without(&[2]);
+        assert_eq!(
+            decode_record(&gap, &data, 0).unwrap().1,
+            values_without(&[2])
+        );
+        // NULL bit of column 4, the mask ends at the variable size column offsets
+        let variable_offset = u16::from_le_bytes([data[2], data[3]]) as usize;
+        data[variable_offset - 1] |= 1 << 3;
+        let mut expected = values_without(&[2]);
+        expected.iter_mut().find(|(id, _)| *id == 4).unwrap().1 = None;
+        assert_eq!(decode_record(&gap, &data, 0).unwrap().1, expected);
+        // columns past two gaps of unknown sizes can't be placed
+        let ids: Vec<u32> = decode_record(&without(&[2, 3]), &data, 0)
+            .unwrap()
+            .1
+            .iter()
+            .map(|(id, _)| *id)
+            .collect();
+        assert_eq!(ids, vec![1, 128]);
+
+        // the ROLE_ACCESS leaf page freed into the available space of the database
+        let mut data = read_test_data("Current.mdb");
+        let page_size = jdb.get_reader().unwrap().page_size() as usize;
+        let (leaf_page, free_page) = (31, 75);
+        let page = page_mut(&mut data, page_size, leaf_page).to_vec();
+        let free = page_mut(&mut data, page_size, free_page);
+        free.copy_from_slice(&page);
+        update_page_checksum(free, free_page);
+        let jdb = load_data(5, data);
+        let records = carve_records(jdb.get_reader().unwrap()).unwrap();
+        let freed: Vec<&CarvedRecord> = records
+            .iter()
+            .filter(|r| r.page_number == free_page)
+            .collect();
+        assert_eq!(freed.len() as u64, jdb.count_rows("ROLE_ACCESS").unwrap());
+        for record in freed {
+            assert_eq!(record.region, CarvedRegion::AvailablePage);
+            assert_eq!(record.table, "ROLE_ACCESS");
+            assert!(row_of(&jdb, record));
+        }
+    }
+
     fn get_str_value(db_name: &str, table_name: &str, column_name: &str) -> String {
         let jdb = init_tests(5, Some(db_name));
         let columns = jdb.get_columns(table_name).unwrap();
@@ -0,0 +1,299 @@
+//carve.rs
+use simple_error::SimpleError;
+use std::collections::BTreeSet;
+use std::mem;
+
+use crate::parser::ese_db::DataDefinitionHeader;
+use crate::parser::jet;
+use crate::parser::reader::{ReadSeek, Reader};
+use crate::parser::space;
+
+// Last identifiers of the fixed and variable size columns, tagged columns follow
+const LAST_FIXED_SIZE_COLUMN: u32 = 127;
+const LAST_VARIABLE_SIZE_COLUMN: u32 = 255;
+
+// Variable size column offsets with this bit set are NULL
+const VARIABLE_SIZE_NULL: u16 = 0x8000;
+
+// (column identifier, value) pairs, None for NULL values
+pub type ColumnValues = Vec<(u32, Option<Vec<u8>>)>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CarvedRegion {
+    // space of a table leaf page not covered by its tags
+    Unallocated,
+    // page marked as available by a space tree
+    AvailablePage,
+}
+
+// Record found in free space, only its fixed and variable size columns are decoded:
+// the end of the tagged columns is unknown
+#[derive(Clone, Debug)]
+pub struct CarvedRecord {
+    pub page_number: u32,
+    // offset of the data definition header from the start of the page
+    pub offset: u32,
+    pub size: u32,
+    pub region: CarvedRegion,
+    pub table: String,
+    // the columns up to the last fixed and variable size column of the record
+    pub columns: ColumnValues,
+}
+
+// Fixed size column values follow the data definition header, followed by the bit mask
+// of their NULL values, the array of the variable size column end offsets
+// and the variable size column values
+pub(crate) fn decode_record(
+    tbl_def: &jet::TableDefinition,
+    data: &[u8],
+    offset: usize,
+) -> Option<(usize, ColumnValues)> {
+    let ddh_size = mem::size_of::<DataDefinitionHeader>();
+    let ddh = data.get(offset..offset + ddh_size)?;
+    let last_fixed_size_data_type = ddh[0] as u32;
+    let last_variable_size_data_type = ddh[1] as u32;
+    let variable_size_data_types_offset = u16::from_le_bytes([ddh[2], ddh[3]]) as usize;
+    if last_fixed_size_data_type > LAST_FIXED_SIZE_COLUMN
+        || last_variable_size_data_type < LAST_FIXED_SIZE_COLUMN
+        // nothing but tagged columns, the header can't be told from random data
+        || (last_fixed_size_data_type == 0 && last_variable_size_data_type == LAST_FIXED_SIZE_COLUMN)
+    {
+        return None;
+    }
+
+    let columns = &tbl_def.column_catalog_definition_array;
+    let is_column = |id: u32| columns.iter().any(|col| col.identifier == id);
+    if (last_fixed_size_data_type > 0 && !is_column(last_fixed_size_data_type))
+        || (last_variable_size_data_type > LAST_FIXED_SIZE_COLUMN
+            && !is_column(last_variable_size_data_type))
+    {
+        return None;
+    }
+
+    // the fixed size columns of the table define the offset of the variable size data;
+    // every column identifier up to the last one has its space in the record, including
+    // the ones missing from the catalog (deleted columns), whose size is only known
+    // if there is a single one
+    let fixed_data_bits_mask_size = (last_fixed_size_data_type as usize).div_ceil(8);
+    let fixed_sizes: Vec<Option<usize>> = (1..=last_fixed_size_data_type)
+        .map(|id| {
+            columns
+                .iter()
+                .find(|col| col.identifier == id)
+                .map(|col| col.size as usize)
+        })
+        .collect();
+    let known_size: usize = fixed_sizes.iter().flatten().sum();
+    let missing = fixed_sizes.iter().filter(|size| size.is_none()).count();
+    let fixed_size =
+        variable_size_data_types_offset.checked_sub(ddh_size + fixed_data_bits_mask_size)?;
+    if (missing == 0 && fixed_size != known_size) || fixed_size < known_size + missing {
+        return None;
+    }
+    let gap_size = if missing == 1 {
+        Some(fixed_size - known_size)
+    } else {
+        None
+    };
+    // offsets of the fixed size columns by identifier, None past an unknown gap
+    let mut fixed_offsets: Vec<Option<usize>> = vec![];
+    let mut fixed_offset = Some(offset + ddh_size);
+    for size in fixed_sizes.iter() {
+        fixed_offsets.push(fixed_offset);
+        fixed_offset = fixed_offset
+            .zip(size.or(gap_size))
+            .map(|(o, size)| o + size);
+    }
+    let number_of_variable_size_data_types =
+        last_variable_size_data_type.saturating_sub(LAST_FIXED_SIZE_COLUMN) as usize;
+    let variable_sizes_offset = offset + variable_size_data_types_offset;
+    let variable_values_offset = variable_sizes_offset + number_of_variable_size_data_types * 2;
+    let variable_sizes = data.get(variable_sizes_offset..variable_values_offset)?;
+    let variable_ends: Vec<u16> = variable_sizes
+        .chunks_exact(2)
+        .map(|c| u16::from_le_bytes([c[0], c[1]]))
+        .collect();
+    let mut previous_end = 0;
+    for end in variable_ends.iter() {
+        let end = end & !VARIABLE_SIZE_NULL;
+        if end < previous_end {
+            return None;
+        }
+        previous_end = end;
+    }
+    let record_end = variable_values_offset + previous_end as usize;
+    if record_end > data.len() {
+        return None;
+    }
+
+    let fixed_data_bits_mask = &data[variable_sizes_offset - fixed_data_bits_mask_size..];
+    let mut values = vec![];
+    for col in columns.iter() {
+        if col.identifier <= LAST_FIXED_SIZE_COLUMN {
+            if col.identifier == 0 || col.identifier > last_fixed_size_data_type {
+                continue;
+            }
+            let i = (col.identifier - 1) as usize;
+            let Some(fixed_offset) = fixed_offsets[i] else {
+                continue;
+            };
+            let value = if fixed_data_bits_mask[i / 8] & (1 << (i % 8)) > 0 {
+                None
+            } else {
+                Some(data[fixed_offset..fixed_offset + col.size as usize].to_vec())
+            };
+            values.push((col.identifier, value));
+        } else if col.identifier <= last_variable_size_data_type
+            && col.identifier <= LAST_VARIABLE_SIZE_COLUMN
+        {
+            let k = (col.identifier - LAST_FIXED_SIZE_COLUMN - 1) as usize;
+            let end = variable_ends[k];
+            let value = if end & VARIABLE_SIZE_NULL != 0 {
+                None
+            } else {
+                let start = if k > 0 {
+                    variable_ends[k - 1] & !VARIABLE_SIZE_NULL
+                } else {
+                    0
+                };
+                Some(
+                    data[variable_values_offset + start as usize
+                        ..variable_values_offset + end as usize]
+                        .to_vec(),
+                )
+            };
+            values.push((col.identifier, value));
+        }
+    }
+    Some((record_end - offset, values))
+}
+
+// Scans the page data from start to end (offsets from the start of the page)
+// for records of the tables
+fn carve_region(
+    tables: &[&jet::TableDefinition],
+    data: &[u8],
+    page_number: u32,
+    region: CarvedRegion,
+    (start, end): (usize, usize),
+    records: &mut Vec<CarvedRecord>,
+) {
+    let region_data = &data[..end];
+    let mut offset = start;
+    while offset + mem::size_of::<DataDefinitionHeader>() <= end {
+        let found = tables.iter().find_map(|tbl_def| {
+            decode_record(tbl_def, region_data, offset)
+                .map(|(size, columns)| (tbl_def, size, columns))
+        });
+        match found {
+            Some((tbl_def, size, columns)) => {
+                records.push(CarvedRecord {
+                    page_number,
+                    offset: offset as u32,
+                    size: size as u32,
+                    region,
+                    table: tbl_def
+                        .table_catalog_definition
+                        .as_ref()
+                        .map(|t| t.name.clone())
+                        .unwrap_or_default(),
+                    columns,
+                });
+                offset += size;
+            }
+            None => offset += 1,
+        }
+    }
+}
+
+// Carves records of the catalog tables from the unallocated space of their leaf pages
+// (between the tag data, and before the tag array) and from the pages marked as available
+// by the space trees. Pages keep the object identifier of their last owner in the header,
+// the records of other tables are only looked for on pages without a known owner.
+pub fn carve_records<T: ReadSeek>(reader: &Reader<T>) -> Result<Vec<CarvedRecord>, SimpleError> {
+    let catalog = reader.load_catalog()?;
+    let tables: Vec<&jet::TableDefinition> = catalog
+        .iter()
+        .filter(|tbl_def| tbl_def.table_catalog_definition.is_some())
+        .collect();
+    let table_of = |object_identifier: u32| {
+        tables.iter().find(|tbl_def| {
+            tbl_def
+                .table_catalog_definition
+                .as_ref()
+                .is_some_and(|t| t.identifier == object_identifier)
+        })
+    };
+
+    let mut available_pages: BTreeSet<u32> = BTreeSet::new();
+    for object_space in space::load_space(reader)? {
+        for extent in object_space.available.iter() {
+            available_pages.extend(extent.first_page..=extent.last_page());
+        }
+    }
+
+    let page_size = reader.page_size() as usize;
+    let mut records = vec![];
+    for owner in space::load_page_owners(reader)?.values() {
+        if owner.error.is_some() || owner.object_identifier == 0 {
+            // zero filled or unreadable page
+            continue;
+        }
+        let db_page = jet::DbPage::new(reader, owner.page_number)?;
+        let data = reader.read_bytes(db_page.offset(), page_size)?;
+        let owner_table = table_of(owner.object_identifier);
+        if available_pages.contains(&owner.page_number) {
+            let candidates = match owner_table {
+                Some(tbl_def) => vec![*tbl_def],
+                None => tables.clone(),
+            };
+            carve_region(
+                &candidates,
+                &data,
+                owner.page_number,
+                CarvedRegion::AvailablePage,
+                (db_page.size(), page_size),
+                &mut records,
+            );
+            continue;
+        }
+
+        let tbl_def = match owner_table {
+            Some(tbl_def)
+                if owner.flags.contains(jet::PageFlags::IS_LEAF)
+                    && !owner.flags.intersects(
+                        jet::PageFlags::IS_SPACE_TREE
+                            | jet::PageFlags::IS_INDEX
+                            | jet::PageFlags::IS_LONG_VALUE,
+                    ) =>
+            {
+                *tbl_def
+            }
+            _ => continue,
+        };
+        // unallocated space: the gaps between the tags of the data area
+        let mut tag_ranges = vec![];
+        for i in 0..db_page.tags() {
+            let tag = db_page.tag(i)?;
+            let start = db_page.size() + tag.offset as usize;
+            tag_ranges.push((start, start + tag.size as usize));
+        }
+        tag_ranges.sort();
+        let data_end = page_size.saturating_sub(db_page.tags() * 4);
+        let mut start = db_page.size();
+        for (tag_start, tag_end) in tag_ranges.into_iter().chain([(data_end, data_end)]) {
+            if tag_start > start {
+                carve_region(
+                    &[tbl_def],
+                    &data,
+                    owner.page_number,
+                    CarvedRegion::Unallocated,
+                    (start, tag_start.min(data_end)),
+                    &mut records,
+                );
+            }
+            start = start.max(tag_end);
+        }
+    }
+    Ok(records)
+}
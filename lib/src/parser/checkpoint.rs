@@ -0,0 +1,214 @@
+//checkpoint.rs
+use nom_derive::*;
+use simple_error::SimpleError;
+use std::mem;
+use std::path::Path;
+
+use crate::parser::jet;
+use crate::parser::jet::uint32_t;
+use crate::parser::log::{parse_struct, LogFile};
+use crate::parser::reader::{ReadSeek, Reader};
+
+pub const CHECKPOINT_FILE_SIZE: usize = 4096;
+// the attach info entries follow the fixed part of the checkpoint
+pub const CHECKPOINT_ATTACH_INFO_OFFSET: usize = 1024;
+
+#[derive(Copy, Clone, Default, Debug, Nom)]
+#[repr(C)]
+pub struct CheckpointHeader {
+    pub checksum: uint32_t,
+    #[nom(Parse = "{ jet::LgPos::parse_le }")]
+    pub last_full_backup_checkpoint: jet::LgPos,
+    // the recovery of the databases starts at this log position
+    #[nom(Parse = "{ jet::LgPos::parse_le }")]
+    pub checkpoint: jet::LgPos,
+    #[nom(Parse = "{ jet::Signature::parse_le }")]
+    pub log_signature: jet::Signature,
+}
+
+// Fixed part of an attach info entry, followed by cb_names bytes of the UTF-16 database path
+#[derive(Copy, Clone, Default, Debug, Nom)]
+#[repr(C, packed)]
+pub struct AttachInfoHeader {
+    // 0 ends the attach info entries
+    pub dbid: u8,
+    pub flags: u8,
+    pub cb_names: u16,
+    #[nom(Parse = "{ jet::LgPos::parse_le }")]
+    pub attach_position: jet::LgPos,
+    #[nom(Parse = "{ jet::LgPos::parse_le }")]
+    pub consistent_position: jet::LgPos,
+    #[nom(Parse = "{ jet::Signature::parse_le }")]
+    pub database_signature: jet::Signature,
+}
+
+// Database attached to the log stream when the checkpoint was written
+#[derive(Clone, Debug)]
+pub struct AttachInfo {
+    pub header: AttachInfoHeader,
+    pub path: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct CheckpointFile {
+    pub header: CheckpointHeader,
+    pub attachments: Vec<AttachInfo>,
+}
+
+impl CheckpointFile {
+    // first log generation needed to recover the databases
+    pub fn generation(&self) -> u32 {
+        self.header.checkpoint.l_generation
+    }
+
+    pub fn attachment(&self, database_signature: &jet::Signature) -> Option<&AttachInfo> {
+        self.attachments
+            .iter()
+            .find(|a| { a.header.database_signature } == *database_signature)
+    }
+}
+
+pub fn parse_checkpoint(data: &[u8]) -> Result<CheckpointFile, SimpleError> {
+    if data.len() < CHECKPOINT_FILE_SIZE {
+        return Err(SimpleError::new(format!(
+            "checkpoint file of {} bytes is shorter than {}",
+            data.len(),
+            CHECKPOINT_FILE_SIZE
+        )));
+    }
+    let data = &data[..CHECKPOINT_FILE_SIZE];
+    let header: CheckpointHeader = parse_struct(data, 0)?;
+    let checksum = crate::utils::calc_crc32(data);
+    if header.checksum != checksum {
+        return Err(SimpleError::new(format!(
+            "wrong checkpoint checksum: {}, calculated {}",
+            { header.checksum },
+            checksum
+        )));
+    }
+
+    let mut attachments = vec![];
+    let mut offset = CHECKPOINT_ATTACH_INFO_OFFSET;
+    while offset + mem::size_of::<AttachInfoHeader>() <= data.len() {
+        let attach: AttachInfoHeader = parse_struct(data, offset)?;
+        if attach.dbid == 0 {
+            break;
+        }
+        offset += mem::size_of::<AttachInfoHeader>();
+        let names = data
+            .get(offset..offset + attach.cb_names as usize)
+            .ok_or_else(|| {
+                SimpleError::new(format!(
+                    "database {}: path of {} bytes is out of the checkpoint",
+                    attach.dbid,
+                    { attach.cb_names }
+                ))
+            })?;
+        let path = crate::utils::from_utf16(names)
+            .map_err(|e| SimpleError::new(format!("database {} path: {}", attach.dbid, e)))?;
+        attachments.push(AttachInfo {
+            header: attach,
+            path: path.trim_end_matches('\0').to_string(),
+        });
+        offset += attach.cb_names as usize;
+    }
+    Ok(CheckpointFile {
+        header,
+        attachments,
+    })
+}
+
+pub fn load_checkpoint_file(path: impl AsRef<Path>) -> Result<CheckpointFile, SimpleError> {
+    let path = path.as_ref();
+    let data = std::fs::read(path)
+        .map_err(|e| SimpleError::new(format!("can't read {}: {}", path.to_string_lossy(), e)))?;
+    parse_checkpoint(&data)
+}
+
+// Whether a database, its checkpoint and its logs belong together
+#[derive(Clone, Debug, Default)]
+pub struct FileSetReport {
+    // generations needed to recover the database: from the checkpoint (or the lowest required
+    // generation) of a dirty database, or from the first log of a clean one,
+    // to the highest required generation of a dirty database or the last log
+    pub first_generation: u32,
+    pub last_generation: u32,
+    pub missing_generations: Vec<u32>,
+    // files of another database or log stream, or which can't be loaded
+    pub mismatches: Vec<String>,
+}
+
+impl FileSetReport {
+    pub fn is_consistent(&self) -> bool {
+        self.missing_generations.is_empty() && self.mismatches.is_empty()
+    }
+}
+
+// Checks the signatures of the checkpoint and of the logs against the database header,
+// and lists the needed log generations which are missing
+pub fn check_file_set<T: ReadSeek>(
+    reader: &Reader<T>,
+    checkpoint: Option<&CheckpointFile>,
+    logs: &[LogFile],
+) -> FileSetReport {
+    let mut report = FileSetReport::default();
+    let log_signature = reader.log_signature();
+    let (min_required, max_required) = if reader.is_dirty() {
+        reader.required_log_generations()
+    } else {
+        (0, 0)
+    };
+
+    let mut generations = vec![];
+    for log in logs {
+        if log.header.log_signature != log_signature {
+            report.mismatches.push(format!(
+                "log generation {}: log signature doesn't match the database",
+                log.header.generation
+            ));
+        } else {
+            generations.push(log.header.generation);
+        }
+    }
+    generations.sort_unstable();
+
+    report.first_generation = min_required;
+    if let Some(checkpoint) = checkpoint {
+        if checkpoint.header.log_signature != log_signature {
+            report
+                .mismatches
+                .push("checkpoint: log signature doesn't match the database".to_string());
+        } else if reader.is_dirty() {
+            if checkpoint
+                .attachment(&reader.database_signature())
+                .is_none()
+            {
+                // the attachments are matched by the database signature, not by their path
+                report.mismatches.push(format!(
+                    "checkpoint: the database is not attached (attached: {})",
+                    checkpoint
+                        .attachments
+                        .iter()
+                        .map(|a| a.path.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ));
+            }
+            // the logs before the checkpoint are already in the database,
+            // a clean database doesn't need any
+            report.first_generation = checkpoint.generation();
+        }
+    }
+    if report.first_generation == 0 {
+        report.first_generation = generations.first().cloned().unwrap_or_default();
+    }
+    report.last_generation = max_required
+        .max(generations.last().cloned().unwrap_or_default())
+        .max(report.first_generation);
+    if report.first_generation > 0 {
+        report.missing_generations = (report.first_generation..=report.last_generation)
+            .filter(|generation| generations.binary_search(generation).is_err())
+            .collect();
+    }
+    report
+}
@@ -261,9 +261,9 @@ impl PagesOrLocale {
     pub fn codepage(&self) -> uint32_t {
         self.nop_or_cp_or_li
     }
-    // pub fn locale_identifier(&self) -> uint32_t {
-    // self.nop_or_cp_or_li
-    // }
+    pub fn locale_identifier(&self) -> uint32_t {
+        self.nop_or_cp_or_li
+    }
 }
 
 #[repr(packed)]
@@ -48,7 +48,7 @@ impl<'a> Parse<&'a [u8]> for PageFlags {
     }
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum FixedPageNumber {
     Database = 1,
     Catalog = 4,
@@ -201,7 +201,7 @@ pub struct DateTime {
 }
 impl_read_struct!(DateTime);
 
-#[derive(Copy, Clone, Default, Debug, Nom)]
+#[derive(Copy, Clone, Default, Debug, PartialEq, Eq, Nom)]
 #[repr(C)]
 pub struct Signature {
     pub random: uint32_t,
@@ -219,6 +219,33 @@ pub struct LgPos {
 }
 impl_read_struct!(LgPos);
 
+impl LgPos {
+    // log positions are ordered by generation, sector and byte offset
+    fn sort_key(&self) -> (u32, u16, u16) {
+        (self.l_generation, self.isec, self.ib)
+    }
+}
+
+impl PartialEq for LgPos {
+    fn eq(&self, other: &Self) -> bool {
+        self.sort_key() == other.sort_key()
+    }
+}
+
+impl Eq for LgPos {}
+
+impl PartialOrd for LgPos {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for LgPos {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.sort_key().cmp(&other.sort_key())
+    }
+}
+
 #[repr(C, packed)]
 #[derive(Debug, Copy, Default, Clone, Nom)]
 pub struct BackupInfo {
@@ -376,6 +403,41 @@ impl PageTag {
     }
 }
 
+bitflags! {
+    // IndexDefinition::flags (IDB flags persisted in the catalog)
+    #[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+    pub struct IndexFlags : u32 {
+        const Unique                = 0x00001;
+        const AllowAllNulls         = 0x00002;
+        const AllowFirstNull        = 0x00004;
+        const AllowSomeNulls        = 0x00008;
+        const NoNullSeg             = 0x00010;
+        const Primary               = 0x00020;
+        const LocaleSet             = 0x00040;
+        const Multivalued           = 0x00080;
+        const TemplateIndex         = 0x00100;
+        const DerivedIndex          = 0x00200;
+        const LocalizedText         = 0x00400;
+        const SortNullsHigh         = 0x00800;
+        const UnicodeFixupOn        = 0x01000;
+        const CrossProduct          = 0x02000;
+        const DisallowTruncation    = 0x04000;
+        const NestedTable           = 0x08000;
+        const DotNetGuid            = 0x10000;
+        const ImmutableStructure    = 0x20000;
+    }
+}
+
+bitflags! {
+    // IndexSegment::flags (IDXSEG flags)
+    #[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+    pub struct IndexSegmentFlags : u8 {
+        const MustBeNull     = 0x20;
+        const Descending     = 0x40;
+        const TemplateColumn = 0x80;
+    }
+}
+
 #[derive(Clone, Debug, Default)]
 #[repr(C)]
 pub struct CatalogDefinition {
@@ -388,13 +450,155 @@ pub struct CatalogDefinition {
 
     pub size: uint32_t,
     pub codepage: uint32_t,
+    pub locale_identifier: uint32_t,
     pub lcmap_flags: uint32_t,
     pub flags: uint32_t,
+    pub key_most: uint16_t,
 
     pub name: String,
 
     pub template_name: Vec<u8>,
     pub default_value: Vec<u8>,
+    pub key_field_ids: Vec<u8>,
+    pub conditional_columns: Vec<u8>,
+}
+
+impl CatalogDefinition {
+    // names of the fields that differ
+    pub fn differences(&self, other: &CatalogDefinition) -> Vec<&'static str> {
+        let mut fields = vec![];
+        macro_rules! compare {
+            ($($field: ident),*) => {
+                $(
+                    if self.$field != other.$field {
+                        fields.push(stringify!($field));
+                    }
+                )*
+            };
+        }
+        compare!(
+            father_data_page_object_identifier,
+            cat_type,
+            identifier,
+            column_type,
+            father_data_page_number,
+            size,
+            codepage,
+            locale_identifier,
+            lcmap_flags,
+            flags,
+            key_most,
+            name,
+            template_name,
+            default_value,
+            key_field_ids,
+            conditional_columns
+        );
+        fields
+    }
+}
+
+// Entry of the catalog that differs from its shadow copy
+#[derive(Clone, Debug)]
+pub struct CatalogDifference {
+    // the key of the entry
+    pub table_object_identifier: uint32_t,
+    pub cat_type: uint16_t,
+    pub identifier: uint32_t,
+    // None if the entry is missing
+    pub catalog: Option<CatalogDefinition>,
+    pub shadow: Option<CatalogDefinition>,
+    // fields that differ if the entry is in both catalogs
+    pub fields: Vec<&'static str>,
+}
+
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct IndexSegment {
+    pub column_identifier: uint32_t,
+    pub flags: IndexSegmentFlags,
+}
+
+impl IndexSegment {
+    pub fn is_descending(&self) -> bool {
+        self.flags.contains(IndexSegmentFlags::Descending)
+    }
+}
+
+// 4-byte IDXSEG (bFlags, bReserved, fid) was introduced in format revision 7,
+// before that a segment is a signed 2-byte fid, negative for descending order
+const ESEDB_FORMAT_REVISION_4_BYTE_IDXSEG: FormatRevision = 0x07;
+
+fn parse_index_segments(data: &[u8], format_revision: FormatRevision) -> Vec<IndexSegment> {
+    if format_revision >= ESEDB_FORMAT_REVISION_4_BYTE_IDXSEG {
+        data.chunks_exact(4)
+            .map(|seg| IndexSegment {
+                column_identifier: u16::from_le_bytes([seg[2], seg[3]]) as u32,
+                flags: IndexSegmentFlags::from_bits_truncate(seg[0]),
+            })
+            .collect()
+    } else {
+        data.chunks_exact(2)
+            .map(|seg| {
+                let fid = i16::from_le_bytes([seg[0], seg[1]]);
+                let flags = if fid < 0 {
+                    IndexSegmentFlags::Descending
+                } else {
+                    IndexSegmentFlags::empty()
+                };
+                IndexSegment {
+                    column_identifier: fid.unsigned_abs() as u32,
+                    flags,
+                }
+            })
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct IndexDefinition {
+    pub name: String,
+    // object identifier of the index B-tree (equal to the table one for the primary index)
+    pub identifier: uint32_t,
+    pub father_data_page_object_identifier: uint32_t,
+    pub father_data_page_number: uint32_t,
+    pub flags: IndexFlags,
+    pub locale_identifier: uint32_t,
+    pub lcmap_flags: uint32_t,
+    pub key_most: uint16_t,
+    pub key_columns: Vec<IndexSegment>,
+    pub conditional_columns: Vec<IndexSegment>,
+}
+
+impl IndexDefinition {
+    pub fn new(cat_def: &CatalogDefinition, format_revision: FormatRevision) -> IndexDefinition {
+        IndexDefinition {
+            name: cat_def.name.clone(),
+            identifier: cat_def.identifier,
+            father_data_page_object_identifier: cat_def.father_data_page_object_identifier,
+            father_data_page_number: cat_def.father_data_page_number,
+            flags: IndexFlags::from_bits_retain(cat_def.flags),
+            locale_identifier: cat_def.locale_identifier,
+            lcmap_flags: cat_def.lcmap_flags,
+            key_most: cat_def.key_most,
+            key_columns: parse_index_segments(&cat_def.key_field_ids, format_revision),
+            conditional_columns: parse_index_segments(
+                &cat_def.conditional_columns,
+                format_revision,
+            ),
+        }
+    }
+
+    pub fn is_unique(&self) -> bool {
+        self.flags.contains(IndexFlags::Unique)
+    }
+
+    pub fn is_primary(&self) -> bool {
+        self.flags.contains(IndexFlags::Primary)
+    }
+
+    pub fn is_conditional(&self) -> bool {
+        !self.conditional_columns.is_empty()
+    }
 }
 
 #[derive(Clone)]
@@ -402,7 +606,16 @@ pub struct CatalogDefinition {
 pub struct TableDefinition {
     pub table_catalog_definition: Option<CatalogDefinition>,
     pub column_catalog_definition_array: Vec<CatalogDefinition>,
+    pub index_definition_array: Vec<IndexDefinition>,
     pub long_value_catalog_definition: Option<CatalogDefinition>,
+    // rebuilt from the defunct catalog entries of a dropped table
+    pub dropped: bool,
+}
+
+impl TableDefinition {
+    pub fn primary_index(&self) -> Option<&IndexDefinition> {
+        self.index_definition_array.iter().find(|i| i.is_primary())
+    }
 }
 
 pub struct PageTree {
@@ -467,4 +680,21 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn test_parse_index_segments() {
+        // LE_IDXSEG: bFlags, bReserved, fid
+        let segs = parse_index_segments(&[0x00, 0, 1, 0, 0x40, 0, 0x80, 0], 0x11);
+        assert_eq!(segs.len(), 2);
+        assert_eq!(segs[0].column_identifier, 1);
+        assert!(!segs[0].is_descending());
+        assert_eq!(segs[1].column_identifier, 128);
+        assert!(segs[1].is_descending());
+
+        // IDXSEG_OLD: signed fid, negative for descending
+        let segs = parse_index_segments(&(-256i16).to_le_bytes(), 0x06);
+        assert_eq!(segs.len(), 1);
+        assert_eq!(segs[0].column_identifier, 256);
+        assert!(segs[0].is_descending());
+    }
 }
@@ -0,0 +1,1356 @@
+//key.rs
+use crate::ese_trait::*;
+use crate::parser::jet;
+use simple_error::SimpleError;
+use std::collections::{HashMap, HashSet};
+
+// The prefix byte of every normalized key segment
+pub const KEY_PREFIX_NULL: u8 = 0x00;
+pub const KEY_PREFIX_ZERO_LENGTH: u8 = 0x40;
+pub const KEY_PREFIX_NULL_HIGH: u8 = 0xc0;
+pub const KEY_PREFIX_DATA: u8 = 0x7f;
+
+// The maximum length of a normalized key (JET_cbKeyMostMost)
+pub const KEY_MOST_MOST: usize = 2000;
+
+// The key length limit of indexes without explicit one (JET_cbKeyMost_OLD)
+pub const KEY_MOST_DEFAULT: usize = 255;
+
+// Binary data is normalized in chunks of 8 bytes, every chunk is followed by
+// the number of significant bytes in it (or 9 if more chunks follow)
+const BINARY_CHUNK_SIZE: usize = 8;
+
+// LCMapString flags of the index text normalization
+pub const NORM_IGNORECASE: u32 = 0x00000001;
+pub const NORM_IGNORENONSPACE: u32 = 0x00000002;
+pub const LCMAP_UPPERCASE: u32 = 0x00000200;
+pub const LCMAP_SORTKEY: u32 = 0x00000400;
+
+// Sort key scripts and section separators (as produced by LCMapString)
+const SORTKEY_SCRIPT_PUNCTUATION: u8 = 0x07;
+const SORTKEY_SCRIPT_DIGIT: u8 = 0x0d;
+const SORTKEY_SCRIPT_LATIN: u8 = 0x0e;
+const SORTKEY_SEPARATOR: u8 = 0x01;
+const SORTKEY_DIACRITIC_NONE: u8 = 0x02;
+const SORTKEY_CASE_LOWER: u8 = 0x02;
+const SORTKEY_CASE_UPPER: u8 = 0x12;
+
+// Latin letters a..z primary weights
+const LATIN_WEIGHTS: [u8; 26] = [
+    0x02, 0x09, 0x0a, 0x1a, 0x21, 0x23, 0x25, 0x2c, 0x32, 0x35, 0x36, 0x48, 0x51, 0x70, 0x7c, 0x7e,
+    0x89, 0x8a, 0x91, 0x99, 0x9f, 0xa2, 0xa4, 0xa7, 0xa8, 0xa9,
+];
+
+fn flip_sign_bit(v: &[u8]) -> Vec<u8> {
+    let mut key: Vec<u8> = v.iter().rev().copied().collect();
+    key[0] ^= 0x80;
+    key
+}
+
+fn big_endian(v: &[u8]) -> Vec<u8> {
+    v.iter().rev().copied().collect()
+}
+
+fn ieee_float(v: &[u8]) -> Vec<u8> {
+    let mut key = big_endian(v);
+    if key[0] & 0x80 != 0 {
+        // negative: reverse the whole order
+        key.iter_mut().for_each(|b| *b = !*b);
+    } else {
+        key[0] ^= 0x80;
+    }
+    key
+}
+
+// GUID bytes are compared starting from the node part
+fn guid(v: &[u8]) -> Vec<u8> {
+    [&v[10..16], &v[8..10], &v[6..8], &v[4..6], &v[0..4]].concat()
+}
+
+fn binary_chunks(v: &[u8]) -> Vec<u8> {
+    let mut key = Vec::with_capacity((v.len() / BINARY_CHUNK_SIZE + 1) * (BINARY_CHUNK_SIZE + 1));
+    let mut chunks = v.chunks(BINARY_CHUNK_SIZE).peekable();
+    while let Some(chunk) = chunks.next() {
+        key.extend_from_slice(chunk);
+        key.resize(key.len() + BINARY_CHUNK_SIZE - chunk.len(), 0);
+        if chunks.peek().is_some() {
+            key.push(BINARY_CHUNK_SIZE as u8 + 1);
+        } else {
+            key.push(chunk.len() as u8);
+        }
+    }
+    key
+}
+
+// Upper case of a windows-1252 character, the code page of the non-Unicode text columns
+fn cp1252_uppercase(b: u8) -> u8 {
+    match b {
+        0xe0..=0xfe if b != 0xf7 => b - 0x20,
+        0x9a | 0x9c | 0x9e => b - 0x10, // s, oe, z with caron
+        0xff => 0x9f,                   // y with diaeresis
+        _ => b.to_ascii_uppercase(),
+    }
+}
+
+fn ascii_text(v: &[u8], codepage: u32) -> Vec<u8> {
+    // the default code page of the text columns is windows-1252
+    let upper: fn(u8) -> u8 = if codepage == ESE_CP::ASCII as u32 || codepage == ESE_CP::None as u32
+    {
+        cp1252_uppercase
+    } else {
+        |b| b.to_ascii_uppercase()
+    };
+    let mut key: Vec<u8> = v
+        .iter()
+        .take_while(|&&b| b != 0)
+        .map(|&b| upper(b))
+        .collect();
+    key.push(0);
+    key
+}
+
+// primary sort weight of a character, None for the characters without a known weight
+fn sort_weight(c: u16) -> Option<[u8; 2]> {
+    let weight = match c {
+        0x30 => [SORTKEY_SCRIPT_DIGIT, 0x03],
+        0x31..=0x39 => [SORTKEY_SCRIPT_DIGIT, 0x1a + 2 * (c - 0x31) as u8],
+        0x41..=0x5a => [SORTKEY_SCRIPT_LATIN, LATIN_WEIGHTS[(c - 0x41) as usize]],
+        0x61..=0x7a => [SORTKEY_SCRIPT_LATIN, LATIN_WEIGHTS[(c - 0x61) as usize]],
+        0x24 => [SORTKEY_SCRIPT_PUNCTUATION, 0x21], // '$'
+        0x2e => [SORTKEY_SCRIPT_PUNCTUATION, 0x33], // '.'
+        0x5c => [SORTKEY_SCRIPT_PUNCTUATION, 0x41], // '\'
+        _ => return None,
+    };
+    Some(weight)
+}
+
+// Splits a sort key into its primary, diacritic, case, extra and special weights, with its
+// length up to the terminating zero; None if it is cut before
+fn sort_key_sections(key: &[u8]) -> Option<([&[u8]; 5], usize)> {
+    let mut sections = [&key[..0]; 5];
+    let mut pos = 0;
+    for section in sections.iter_mut().take(4) {
+        let len = key[pos..].iter().position(|&b| b == SORTKEY_SEPARATOR)?;
+        *section = &key[pos..pos + len];
+        pos += len + 1;
+    }
+    // special weights: 2 bytes of position and 2 bytes of weight
+    let mut len = 0;
+    while *key.get(pos + len)? != 0 {
+        len += 4;
+    }
+    sections[4] = key.get(pos..pos + len)?;
+    Some((sections, pos + len + 1))
+}
+
+fn special_position(pos: usize) -> [u8; 2] {
+    (0xffff - pos as u16).to_be_bytes()
+}
+
+// A text value and the sort key an index stores for it (without the segment prefix, ascending)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SortKeySample {
+    pub text: Vec<u16>,
+    pub sort_key: Vec<u8>,
+    pub lcmap_flags: u32,
+}
+
+// Sort weights of the characters out of the weight table, learned from the sort keys the text
+// indexes of a database store for the values of its records (see learn)
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SortWeights {
+    primary: HashMap<u16, Vec<u8>>,
+    diacritic: HashMap<u16, u8>,
+    case: HashMap<u16, u8>,
+    // characters ignored like the hyphen: only their position is kept, with this weight
+    special: HashMap<u16, [u8; 2]>,
+}
+
+// Weights of one character proposed by a sample
+enum Proposal {
+    Primary(u16, Vec<u8>),
+    Diacritic(u16, u8),
+    Case(u16, u8),
+    Special(u16, [u8; 2]),
+}
+
+impl SortWeights {
+    pub fn primary_weight(&self, c: u16) -> Option<&[u8]> {
+        self.primary.get(&c).map(|w| w.as_slice())
+    }
+
+    pub fn special_weight(&self, c: u16) -> Option<[u8; 2]> {
+        self.special.get(&c).copied()
+    }
+
+    // Learns the weights from the samples: the characters of a sample without a weight get the
+    // part of its sort key left by the known ones. A sample with several such characters is only
+    // used when nothing else can be learned, assuming 2-byte primary weights. A character given
+    // different weights by two samples gets none.
+    pub fn learn(samples: &[SortKeySample]) -> Self {
+        let mut weights = SortWeights::default();
+        let mut conflicting: HashSet<u16> = HashSet::new();
+        let mut guess_widths = false;
+        loop {
+            let mut changed = false;
+            for sample in samples.iter() {
+                for proposal in weights.propose(sample, guess_widths, &conflicting) {
+                    changed |= weights.accept(proposal, &mut conflicting);
+                }
+            }
+            if changed {
+                guess_widths = false;
+            } else if guess_widths {
+                return weights;
+            } else {
+                guess_widths = true;
+            }
+        }
+    }
+
+    fn accept(&mut self, proposal: Proposal, conflicting: &mut HashSet<u16>) -> bool {
+        fn insert<T: PartialEq>(map: &mut HashMap<u16, T>, c: u16, w: T) -> Option<bool> {
+            match map.get(&c) {
+                Some(known) if *known == w => Some(false),
+                Some(_) => None,
+                None => {
+                    map.insert(c, w);
+                    Some(true)
+                }
+            }
+        }
+        let (c, accepted) = match proposal {
+            Proposal::Primary(c, w) if !self.special.contains_key(&c) => {
+                (c, insert(&mut self.primary, c, w))
+            }
+            Proposal::Special(c, w) if !self.primary.contains_key(&c) => {
+                (c, insert(&mut self.special, c, w))
+            }
+            Proposal::Primary(c, _) | Proposal::Special(c, _) => (c, None),
+            Proposal::Diacritic(c, w) => (c, insert(&mut self.diacritic, c, w)),
+            Proposal::Case(c, w) => (c, insert(&mut self.case, c, w)),
+        };
+        accepted.unwrap_or_else(|| {
+            self.primary.remove(&c);
+            self.diacritic.remove(&c);
+            self.case.remove(&c);
+            self.special.remove(&c);
+            conflicting.insert(c)
+        })
+    }
+
+    // the weights of the sample characters out of the weight table, nothing if the sample
+    // doesn't match the table
+    fn propose(
+        &self,
+        sample: &SortKeySample,
+        guess_widths: bool,
+        conflicting: &HashSet<u16>,
+    ) -> Vec<Proposal> {
+        let mut proposals: Vec<Proposal> = vec![];
+        let Some(([primary, diacritic, case, _, special], _)) = sort_key_sections(&sample.sort_key)
+        else {
+            return proposals;
+        };
+        let specials: HashMap<[u8; 2], [u8; 2]> = special
+            .chunks_exact(4)
+            .map(|w| ([w[0], w[1]], [w[2], w[3]]))
+            .collect();
+
+        // characters with a primary weight, with the weight when it is known
+        let mut chars: Vec<(u16, Option<Vec<u8>>)> = vec![];
+        let mut special_count = 0;
+        for (pos, &c) in sample.text.iter().enumerate() {
+            if c == 0 {
+                continue;
+            }
+            if conflicting.contains(&c) {
+                return vec![];
+            }
+            let stored = specials.get(&special_position(pos));
+            special_count += stored.is_some() as usize;
+            let known = match c {
+                0x2d => Some([0x82, 0x12]),
+                _ => self.special.get(&c).copied(),
+            };
+            match (known, stored) {
+                (Some(w), Some(stored)) if w == *stored => continue,
+                (Some(_), Some(stored)) if c != 0x2d => {
+                    proposals.push(Proposal::Special(c, *stored));
+                    continue;
+                }
+                (Some(_), _) => return vec![],
+                (None, Some(stored)) => {
+                    if sort_weight(c).is_some() || self.primary.contains_key(&c) {
+                        return vec![];
+                    }
+                    proposals.push(Proposal::Special(c, *stored));
+                    continue;
+                }
+                (None, None) => {}
+            }
+            let weight = sort_weight(c).map(|w| w.to_vec());
+            chars.push((c, weight.or_else(|| self.primary.get(&c).cloned())));
+        }
+        if special_count != specials.len() {
+            return vec![];
+        }
+
+        // the unknown primary weights share the bytes left by the known ones
+        let known: usize = chars
+            .iter()
+            .filter_map(|(_, w)| w.as_ref())
+            .map(|w| w.len())
+            .sum();
+        let unknown: Vec<u16> = chars
+            .iter()
+            .filter(|(_, w)| w.is_none())
+            .map(|(c, _)| *c)
+            .collect();
+        let Some(rest) = primary.len().checked_sub(known) else {
+            return vec![];
+        };
+        let width = match unknown.first() {
+            None if rest == 0 => 0,
+            Some(&u)
+                if unknown.iter().all(|&c| c == u) && rest > 0 && rest % unknown.len() == 0 =>
+            {
+                rest / unknown.len()
+            }
+            Some(_) if guess_widths && rest == 2 * unknown.len() => 2,
+            _ => return proposals,
+        };
+        let mut learned: HashMap<u16, &[u8]> = HashMap::new();
+        let mut offset = 0;
+        for (c, weight) in chars.iter() {
+            let len = weight.as_ref().map_or(width, |w| w.len());
+            let bytes = &primary[offset..offset + len];
+            offset += len;
+            match weight {
+                // a learned weight the sample disagrees with is a conflict
+                Some(w) if w != bytes && sort_weight(*c).is_some() => return vec![],
+                Some(w) if w != bytes => proposals.push(Proposal::Primary(*c, bytes.to_vec())),
+                Some(_) => {}
+                None => {
+                    if learned.insert(*c, bytes).is_some_and(|w| w != bytes) {
+                        return vec![];
+                    }
+                }
+            }
+        }
+        let mut learned: Vec<(u16, &[u8])> = learned.into_iter().collect();
+        learned.sort_unstable();
+        proposals.extend(
+            learned
+                .into_iter()
+                .map(|(c, w)| Proposal::Primary(c, w.to_vec())),
+        );
+
+        // one diacritic and case weight by character, the trailing default ones are left out
+        if sample.lcmap_flags & NORM_IGNORENONSPACE == 0 {
+            let expected = |_| SORTKEY_DIACRITIC_NONE;
+            let learned = section_weights(&chars, diacritic, expected, &self.diacritic);
+            proposals.extend(learned.into_iter().map(|(c, w)| Proposal::Diacritic(c, w)));
+        }
+        if sample.lcmap_flags & NORM_IGNORECASE == 0 {
+            let learned = section_weights(&chars, case, static_case_weight, &self.case);
+            proposals.extend(learned.into_iter().map(|(c, w)| Proposal::Case(c, w)));
+        }
+        proposals
+    }
+}
+
+// The diacritic or case weights of a sample section for the characters out of the weight
+// table, nothing if the section doesn't match the table
+fn section_weights(
+    chars: &[(u16, Option<Vec<u8>>)],
+    section: &[u8],
+    expected: fn(u16) -> u8,
+    known: &HashMap<u16, u8>,
+) -> Vec<(u16, u8)> {
+    if section.len() > chars.len() {
+        return vec![];
+    }
+    let mut learned = vec![];
+    for (i, (c, _)) in chars.iter().enumerate() {
+        // the default weights of both sections are 2
+        let w = section.get(i).copied().unwrap_or(SORTKEY_DIACRITIC_NONE);
+        match sort_weight(*c) {
+            Some(_) if expected(*c) != w => return vec![],
+            Some(_) => {}
+            None if known.get(c) != Some(&w) => learned.push((*c, w)),
+            None => {}
+        }
+    }
+    learned.sort_unstable();
+    learned.dedup();
+    learned
+}
+
+fn static_case_weight(c: u16) -> u8 {
+    if (0x41..=0x5a).contains(&c) {
+        SORTKEY_CASE_UPPER
+    } else {
+        SORTKEY_CASE_LOWER
+    }
+}
+
+// Reproduction of LCMapString(LCMAP_SORTKEY) for the default (word) sort, limited to
+// the characters of the weight table and the learned weights; other characters can't
+// be normalized
+fn unicode_sort_key(
+    text: &[u16],
+    lcmap_flags: u32,
+    weights: &SortWeights,
+) -> Result<Vec<u8>, SimpleError> {
+    let mut primary: Vec<u8> = vec![];
+    let mut diacritic: Vec<u8> = vec![];
+    let mut case: Vec<u8> = vec![];
+    let mut special: Vec<u8> = vec![];
+    let no_weight = |kind: &str, c: u16, pos: usize| {
+        SimpleError::new(format!(
+            "no {}weight for character U+{:04X} at {}",
+            kind, c, pos
+        ))
+    };
+    for (pos, &c) in text.iter().enumerate() {
+        // hyphen is ignored in word sort, only its position is kept
+        let ignored = match c {
+            0 => continue,
+            0x2d => Some([0x82, 0x12]),
+            _ => weights.special.get(&c).copied(),
+        };
+        if let Some(weight) = ignored {
+            special.extend_from_slice(&special_position(pos));
+            special.extend_from_slice(&weight);
+            continue;
+        }
+        match sort_weight(c) {
+            Some(weight) => {
+                primary.extend_from_slice(&weight);
+                diacritic.push(SORTKEY_DIACRITIC_NONE);
+                case.push(static_case_weight(c));
+            }
+            None => {
+                let weight = weights
+                    .primary
+                    .get(&c)
+                    .ok_or_else(|| no_weight("sort ", c, pos))?;
+                primary.extend_from_slice(weight);
+                diacritic.push(*weights.diacritic.get(&c).unwrap_or(&SORTKEY_DIACRITIC_NONE));
+                match weights.case.get(&c) {
+                    Some(&w) => case.push(w),
+                    None if lcmap_flags & NORM_IGNORECASE != 0 => {}
+                    None => return Err(no_weight("case ", c, pos)),
+                }
+            }
+        }
+    }
+    if lcmap_flags & NORM_IGNORENONSPACE != 0 {
+        diacritic.clear();
+    }
+    if lcmap_flags & NORM_IGNORECASE != 0 {
+        case.clear();
+    }
+    while diacritic.last() == Some(&SORTKEY_DIACRITIC_NONE) {
+        diacritic.pop();
+    }
+    while case.last() == Some(&SORTKEY_CASE_LOWER) {
+        case.pop();
+    }
+
+    let mut key = primary;
+    key.push(SORTKEY_SEPARATOR);
+    key.append(&mut diacritic);
+    key.push(SORTKEY_SEPARATOR);
+    key.append(&mut case);
+    key.push(SORTKEY_SEPARATOR);
+    key.push(SORTKEY_SEPARATOR);
+    key.append(&mut special);
+    key.push(0);
+    Ok(key)
+}
+
+fn unicode_text(v: &[u8], lcmap_flags: u32, weights: &SortWeights) -> Result<Vec<u8>, SimpleError> {
+    let text: Vec<u16> = v
+        .chunks_exact(2)
+        .map(|c| u16::from_le_bytes([c[0], c[1]]))
+        .collect();
+    if lcmap_flags == LCMAP_UPPERCASE {
+        let mut key: Vec<u8> = vec![];
+        for c in char::decode_utf16(text.iter().copied()) {
+            let c = c.unwrap_or(char::REPLACEMENT_CHARACTER);
+            for u in c.to_uppercase() {
+                let mut buf = [0u16; 2];
+                for w in u.encode_utf16(&mut buf) {
+                    key.extend_from_slice(&w.to_be_bytes());
+                }
+            }
+        }
+        key.extend_from_slice(&[0, 0]);
+        return Ok(key);
+    }
+    unicode_sort_key(&text, lcmap_flags, weights)
+}
+
+fn expect_size(column_type: u32, value: &[u8], size: usize) -> Result<(), SimpleError> {
+    if value.len() != size {
+        return Err(SimpleError::new(format!(
+            "column type {}: expected {} bytes key value, got {}",
+            column_type,
+            size,
+            value.len()
+        )));
+    }
+    Ok(())
+}
+
+// Normalizes a column value (as stored in a record) into a byte-comparable
+// ascending key segment, None is NULL
+pub fn normalize_value(
+    column_type: u32,
+    codepage: u32,
+    lcmap_flags: u32,
+    value: Option<&[u8]>,
+) -> Result<Vec<u8>, SimpleError> {
+    normalize_value_with_weights(
+        column_type,
+        codepage,
+        lcmap_flags,
+        value,
+        &SortWeights::default(),
+    )
+}
+
+// normalize_value with the learned weights of the characters out of the sort weight table
+pub fn normalize_value_with_weights(
+    column_type: u32,
+    codepage: u32,
+    lcmap_flags: u32,
+    value: Option<&[u8]>,
+    weights: &SortWeights,
+) -> Result<Vec<u8>, SimpleError> {
+    let value = match value {
+        None => return Ok(vec![KEY_PREFIX_NULL]),
+        Some([]) => return Ok(vec![KEY_PREFIX_ZERO_LENGTH]),
+        Some(v) => v,
+    };
+    let data = match column_type {
+        ESE_coltypBit => {
+            expect_size(column_type, value, 1)?;
+            vec![if value[0] == 0 { 0x00 } else { 0xff }]
+        }
+        ESE_coltypUnsignedByte => {
+            expect_size(column_type, value, 1)?;
+            value.to_vec()
+        }
+        ESE_coltypShort => {
+            expect_size(column_type, value, 2)?;
+            flip_sign_bit(value)
+        }
+        ESE_coltypLong => {
+            expect_size(column_type, value, 4)?;
+            flip_sign_bit(value)
+        }
+        ESE_coltypCurrency | ESE_coltypLongLong => {
+            expect_size(column_type, value, 8)?;
+            flip_sign_bit(value)
+        }
+        ESE_coltypUnsignedShort => {
+            expect_size(column_type, value, 2)?;
+            big_endian(value)
+        }
+        ESE_coltypUnsignedLong => {
+            expect_size(column_type, value, 4)?;
+            big_endian(value)
+        }
+        ESE_coltypUnsignedLongLong => {
+            expect_size(column_type, value, 8)?;
+            big_endian(value)
+        }
+        ESE_coltypIEEESingle => {
+            expect_size(column_type, value, 4)?;
+            ieee_float(value)
+        }
+        ESE_coltypIEEEDouble | ESE_coltypDateTime => {
+            expect_size(column_type, value, 8)?;
+            ieee_float(value)
+        }
+        ESE_coltypGUID => {
+            expect_size(column_type, value, 16)?;
+            guid(value)
+        }
+        ESE_coltypBinary | ESE_coltypLongBinary => binary_chunks(value),
+        ESE_coltypText | ESE_coltypLongText => {
+            if codepage == ESE_CP::Unicode as u32 {
+                unicode_text(value, lcmap_flags, weights)?
+            } else {
+                ascii_text(value, codepage)
+            }
+        }
+        _ => {
+            return Err(SimpleError::new(format!(
+                "key normalization of column type {} is not supported",
+                column_type
+            )));
+        }
+    };
+
+    let mut key = Vec::with_capacity(data.len() + 1);
+    key.push(KEY_PREFIX_DATA);
+    key.extend(data);
+    Ok(key)
+}
+
+// Normalizes a column value into the key segment of the index
+pub fn normalize_segment(
+    col: &jet::CatalogDefinition,
+    segment: &jet::IndexSegment,
+    index: &jet::IndexDefinition,
+    value: Option<&[u8]>,
+    weights: &SortWeights,
+) -> Result<Vec<u8>, SimpleError> {
+    let mut key = normalize_value_with_weights(
+        col.column_type,
+        col.codepage,
+        index.lcmap_flags,
+        value,
+        weights,
+    )
+    .map_err(|e| SimpleError::new(format!("column {} ({}): {}", col.name, col.identifier, e)))?;
+    if value.is_none() && index.flags.contains(jet::IndexFlags::SortNullsHigh) {
+        key[0] = KEY_PREFIX_NULL_HIGH;
+    }
+    if segment.is_descending() {
+        key.iter_mut().for_each(|b| *b = !*b);
+    }
+    Ok(key)
+}
+
+// Builds the normalized key of the index from the values of its (leading) key columns,
+// None values are NULL
+pub fn make_key_with_nulls(
+    tbl_def: &jet::TableDefinition,
+    index: &jet::IndexDefinition,
+    values: &[Option<&[u8]>],
+) -> Result<Vec<u8>, SimpleError> {
+    make_key_with_weights(tbl_def, index, values, &SortWeights::default())
+}
+
+// make_key_with_nulls with the learned weights of the characters out of the sort weight table
+pub fn make_key_with_weights(
+    tbl_def: &jet::TableDefinition,
+    index: &jet::IndexDefinition,
+    values: &[Option<&[u8]>],
+    weights: &SortWeights,
+) -> Result<Vec<u8>, SimpleError> {
+    if values.len() > index.key_columns.len() {
+        return Err(SimpleError::new(format!(
+            "index {} has {} key columns, but {} values were given",
+            index.name,
+            index.key_columns.len(),
+            values.len()
+        )));
+    }
+
+    let mut key: Vec<u8> = vec![];
+    for (segment, value) in index.key_columns.iter().zip(values.iter()) {
+        let col = tbl_def
+            .column_catalog_definition_array
+            .iter()
+            .find(|c| c.identifier == segment.column_identifier)
+            .ok_or_else(|| {
+                SimpleError::new(format!(
+                    "index {}: key column {} not found",
+                    index.name, segment.column_identifier
+                ))
+            })?;
+        key.append(&mut normalize_segment(
+            col, segment, index, *value, weights,
+        )?);
+    }
+    if index.key_most > 0 {
+        key.truncate(index.key_most as usize);
+    } else {
+        key.truncate(KEY_MOST_DEFAULT);
+    }
+    Ok(key)
+}
+
+// Builds the normalized key of the index from the values of its (leading) key columns
+pub fn make_key(
+    tbl_def: &jet::TableDefinition,
+    index: &jet::IndexDefinition,
+    values: &[&[u8]],
+) -> Result<Vec<u8>, SimpleError> {
+    let values: Vec<Option<&[u8]>> = values.iter().map(|v| Some(*v)).collect();
+    make_key_with_nulls(tbl_def, index, &values)
+}
+
+// Builds the key of the clustered index, tables without primary index are
+// ordered by a sequential 4-byte big-endian key (DBK)
+pub fn make_primary_key(
+    tbl_def: &jet::TableDefinition,
+    values: &[&[u8]],
+) -> Result<Vec<u8>, SimpleError> {
+    match tbl_def.primary_index() {
+        Some(index) => make_key(tbl_def, index, values),
+        None => match values {
+            [dbk] if dbk.len() == 4 => Ok(big_endian(dbk)),
+            _ => Err(SimpleError::new(
+                "table without primary index expects a single 4-byte sequential key value",
+            )),
+        },
+    }
+}
+
+// Returns the inclusive upper bound of all keys starting with key
+pub fn prefix_upper_bound(mut key: Vec<u8>) -> Vec<u8> {
+    if key.len() < KEY_MOST_MOST {
+        key.resize(KEY_MOST_MOST, 0xff);
+    }
+    key
+}
+
+// A column value decoded from a normalized key, in the record (little-endian) format
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyColumnValue {
+    pub column_id: u32,
+    pub column_type: u32,
+    // None is NULL
+    pub value: Option<Vec<u8>>,
+    // false if the normalization lost information (text case, truncated key)
+    pub exact: bool,
+}
+
+// Reads the bytes of a key segment, inverting them for descending segments
+struct SegmentReader<'a> {
+    key: &'a [u8],
+    pos: usize,
+    mask: u8,
+}
+
+impl SegmentReader<'_> {
+    fn byte(&mut self) -> Option<u8> {
+        let b = *self.key.get(self.pos)?;
+        self.pos += 1;
+        Some(b ^ self.mask)
+    }
+
+    fn bytes(&mut self, n: usize) -> Option<Vec<u8>> {
+        let v = self.key.get(self.pos..self.pos + n)?;
+        self.pos += n;
+        Some(v.iter().map(|b| b ^ self.mask).collect())
+    }
+}
+
+fn char_of_weight(script: u8, weight: u8) -> Option<u16> {
+    let c = match (script, weight) {
+        (SORTKEY_SCRIPT_DIGIT, 0x03) => 0x30,
+        (SORTKEY_SCRIPT_DIGIT, 0x1a..=0x2a) if weight & 1 == 0 => 0x31 + (weight - 0x1a) as u16 / 2,
+        (SORTKEY_SCRIPT_LATIN, _) => 0x61 + LATIN_WEIGHTS.iter().position(|&w| w == weight)? as u16,
+        (SORTKEY_SCRIPT_PUNCTUATION, 0x21) => 0x24,
+        (SORTKEY_SCRIPT_PUNCTUATION, 0x33) => 0x2e,
+        (SORTKEY_SCRIPT_PUNCTUATION, 0x41) => 0x5c,
+        _ => return None,
+    };
+    Some(c)
+}
+
+fn decode_primary_weights(primary: &[u8], text: &mut Vec<u16>) -> bool {
+    let mut exact = true;
+    for w in primary.chunks(2) {
+        let c = match w {
+            [script, weight] => char_of_weight(*script, *weight),
+            _ => None,
+        };
+        text.push(c.unwrap_or_else(|| {
+            exact = false;
+            0xfffd
+        }));
+    }
+    exact
+}
+
+// Reverses unicode_sort_key as far as possible, returns UTF-16LE text and whether it is complete
+fn decode_unicode_sort_key(r: &mut SegmentReader) -> (Vec<u8>, bool) {
+    let key: Vec<u8> = r.key[r.pos..].iter().map(|b| b ^ r.mask).collect();
+    let mut text: Vec<u16> = vec![];
+    let Some(([primary, diacritic, case, _, special], len)) = sort_key_sections(&key) else {
+        // cut by the key length limit: the primary weights it holds
+        r.pos = r.key.len();
+        let primary = key
+            .split(|&b| b == SORTKEY_SEPARATOR)
+            .next()
+            .unwrap_or_default();
+        decode_primary_weights(primary, &mut text);
+        return (text.iter().flat_map(|c| c.to_le_bytes()).collect(), false);
+    };
+    r.pos += len;
+
+    let mut exact = decode_primary_weights(primary, &mut text) && diacritic.is_empty();
+    for (c, &w) in text.iter_mut().zip(case.iter()) {
+        if w == SORTKEY_CASE_UPPER && (0x61..=0x7a).contains(c) {
+            *c -= 0x20;
+        }
+    }
+
+    // special weights: positions of the ignored hyphens
+    let mut hyphens: Vec<usize> = vec![];
+    for w in special.chunks_exact(4) {
+        hyphens.push(0xffff - u16::from_be_bytes([w[0], w[1]]) as usize);
+        exact &= w[2..] == [0x82, 0x12];
+    }
+    hyphens.sort_unstable();
+    for pos in hyphens {
+        text.insert(pos.min(text.len()), 0x2d);
+    }
+
+    (text.iter().flat_map(|c| c.to_le_bytes()).collect(), exact)
+}
+
+// Decodes the data of a key segment, returns the value and whether it is exact,
+// None if the (truncated) key does not hold enough bytes for a fixed size value
+fn decode_data(
+    column_type: u32,
+    codepage: u32,
+    lcmap_flags: u32,
+    r: &mut SegmentReader,
+) -> Result<Option<(Vec<u8>, bool)>, SimpleError> {
+    let fixed = |r: &mut SegmentReader, size: usize, f: fn(&[u8]) -> Vec<u8>| {
+        r.bytes(size).map(|v| (f(&v), true))
+    };
+    let value = match column_type {
+        ESE_coltypBit => r
+            .byte()
+            .map(|b| (vec![if b == 0 { 0 } else { 0xff }], true)),
+        ESE_coltypUnsignedByte => r.byte().map(|b| (vec![b], true)),
+        ESE_coltypShort => fixed(r, 2, signed_value),
+        ESE_coltypLong => fixed(r, 4, signed_value),
+        ESE_coltypCurrency | ESE_coltypLongLong => fixed(r, 8, signed_value),
+        ESE_coltypUnsignedShort => fixed(r, 2, big_endian),
+        ESE_coltypUnsignedLong => fixed(r, 4, big_endian),
+        ESE_coltypUnsignedLongLong => fixed(r, 8, big_endian),
+        ESE_coltypIEEESingle => fixed(r, 4, ieee_float_value),
+        ESE_coltypIEEEDouble | ESE_coltypDateTime => fixed(r, 8, ieee_float_value),
+        ESE_coltypGUID => fixed(r, 16, |k| {
+            [&k[12..16], &k[10..12], &k[8..10], &k[6..8], &k[0..6]].concat()
+        }),
+        ESE_coltypBinary | ESE_coltypLongBinary => {
+            let mut value: Vec<u8> = vec![];
+            loop {
+                let Some(mut chunk) = r.bytes(BINARY_CHUNK_SIZE) else {
+                    value.extend(r.bytes(r.key.len() - r.pos).unwrap_or_default());
+                    break Some((value, false));
+                };
+                match r.byte() {
+                    Some(count) if count as usize == BINARY_CHUNK_SIZE + 1 => {
+                        value.append(&mut chunk)
+                    }
+                    Some(count) => {
+                        chunk.truncate(count as usize);
+                        value.append(&mut chunk);
+                        break Some((value, true));
+                    }
+                    None => {
+                        value.append(&mut chunk);
+                        break Some((value, false));
+                    }
+                }
+            }
+        }
+        ESE_coltypText | ESE_coltypLongText => {
+            if codepage != ESE_CP::Unicode as u32 {
+                // the original case is lost
+                let mut value: Vec<u8> = vec![];
+                loop {
+                    match r.byte() {
+                        Some(0) => break Some((value, false)),
+                        Some(b) => value.push(b),
+                        None => break Some((value, false)),
+                    }
+                }
+            } else if lcmap_flags == LCMAP_UPPERCASE {
+                let mut value: Vec<u8> = vec![];
+                loop {
+                    match r.bytes(2) {
+                        Some(c) if c == [0, 0] => break Some((value, false)),
+                        Some(c) => value.extend_from_slice(&[c[1], c[0]]),
+                        None => break Some((value, false)),
+                    }
+                }
+            } else {
+                let (value, exact) = decode_unicode_sort_key(r);
+                Some((value, exact && lcmap_flags & NORM_IGNORECASE == 0))
+            }
+        }
+        _ => {
+            return Err(SimpleError::new(format!(
+                "key decoding of column type {} is not supported",
+                column_type
+            )));
+        }
+    };
+    Ok(value)
+}
+
+fn signed_value(k: &[u8]) -> Vec<u8> {
+    let mut v = k.to_vec();
+    v[0] ^= 0x80;
+    big_endian(&v)
+}
+
+fn ieee_float_value(k: &[u8]) -> Vec<u8> {
+    let mut v = k.to_vec();
+    if v[0] & 0x80 != 0 {
+        v[0] ^= 0x80;
+    } else {
+        v.iter_mut().for_each(|b| *b = !*b);
+    }
+    big_endian(&v)
+}
+
+// Decodes a normalized key of the index back into the values of its key columns,
+// a truncated key yields only the columns (or the part of the column) it holds
+pub fn decode_key(
+    tbl_def: &jet::TableDefinition,
+    index: &jet::IndexDefinition,
+    key: &[u8],
+) -> Result<Vec<KeyColumnValue>, SimpleError> {
+    let segments = decode_segments(tbl_def, index, key)?;
+    Ok(segments.into_iter().map(|(value, _)| value).collect())
+}
+
+// decode_key with the bytes of the key holding every value
+fn decode_segments(
+    tbl_def: &jet::TableDefinition,
+    index: &jet::IndexDefinition,
+    key: &[u8],
+) -> Result<Vec<(KeyColumnValue, std::ops::Range<usize>)>, SimpleError> {
+    let mut values: Vec<(KeyColumnValue, std::ops::Range<usize>)> = vec![];
+    let mut pos = 0;
+    for segment in index.key_columns.iter() {
+        if pos >= key.len() {
+            break;
+        }
+        let col = tbl_def
+            .column_catalog_definition_array
+            .iter()
+            .find(|c| c.identifier == segment.column_identifier)
+            .ok_or_else(|| {
+                SimpleError::new(format!(
+                    "index {}: key column {} not found",
+                    index.name, segment.column_identifier
+                ))
+            })?;
+        let mut r = SegmentReader {
+            key,
+            pos,
+            mask: if segment.is_descending() { 0xff } else { 0 },
+        };
+        let value = match r.byte() {
+            Some(KEY_PREFIX_NULL) | Some(KEY_PREFIX_NULL_HIGH) => Some((None, true)),
+            Some(KEY_PREFIX_ZERO_LENGTH) => Some((Some(vec![]), true)),
+            Some(KEY_PREFIX_DATA) => {
+                decode_data(col.column_type, col.codepage, index.lcmap_flags, &mut r)?
+                    .map(|(v, exact)| (Some(v), exact))
+            }
+            Some(prefix) => {
+                return Err(SimpleError::new(format!(
+                    "index {}: unknown key segment prefix 0x{:x} at offset {}",
+                    index.name, prefix, pos
+                )));
+            }
+            None => None,
+        };
+        let Some((value, exact)) = value else {
+            break;
+        };
+        let value = KeyColumnValue {
+            column_id: col.identifier,
+            column_type: col.column_type,
+            value,
+            exact,
+        };
+        values.push((value, pos..r.pos));
+        pos = r.pos;
+    }
+    Ok(values)
+}
+
+fn is_sort_key_text(col: &jet::CatalogDefinition, index: &jet::IndexDefinition) -> bool {
+    (col.column_type == ESE_coltypText || col.column_type == ESE_coltypLongText)
+        && col.codepage == ESE_CP::Unicode as u32
+        && index.lcmap_flags != LCMAP_UPPERCASE
+}
+
+// Whether the index has Unicode sort key text columns the sort weights can be learned from,
+// the keys of multi-valued indexes can't be paired with a value of the record
+pub fn has_sort_key_text(tbl_def: &jet::TableDefinition, index: &jet::IndexDefinition) -> bool {
+    !index.flags.contains(jet::IndexFlags::Multivalued)
+        && index.key_columns.iter().any(|segment| {
+            tbl_def
+                .column_catalog_definition_array
+                .iter()
+                .any(|c| c.identifier == segment.column_identifier && is_sort_key_text(c, index))
+        })
+}
+
+// Whether the values have characters without a weight in the sort weight table
+pub fn needs_learned_weights(
+    tbl_def: &jet::TableDefinition,
+    index: &jet::IndexDefinition,
+    values: &[&[u8]],
+) -> bool {
+    index
+        .key_columns
+        .iter()
+        .zip(values.iter())
+        .any(|(segment, value)| {
+            let sort_key_text = tbl_def
+                .column_catalog_definition_array
+                .iter()
+                .any(|c| c.identifier == segment.column_identifier && is_sort_key_text(c, index));
+            sort_key_text
+                && value
+                    .chunks_exact(2)
+                    .map(|c| u16::from_le_bytes([c[0], c[1]]))
+                    .any(|c| c != 0 && c != 0x2d && sort_weight(c).is_none())
+        })
+}
+
+// Pairs the Unicode sort key segments of a key stored in the index with the text values of
+// the record it belongs to (the values of the index key columns), the segments cut by the
+// key length limit are left out
+pub fn sort_key_samples(
+    tbl_def: &jet::TableDefinition,
+    index: &jet::IndexDefinition,
+    key: &[u8],
+    values: &[Option<&[u8]>],
+) -> Result<Vec<SortKeySample>, SimpleError> {
+    let mut samples: Vec<SortKeySample> = vec![];
+    let segments = decode_segments(tbl_def, index, key)?;
+    for ((segment, value), (decoded, bytes)) in index
+        .key_columns
+        .iter()
+        .zip(values.iter())
+        .zip(segments.iter())
+    {
+        let (Some(value), Some(_)) = (value, &decoded.value) else {
+            continue;
+        };
+        let sort_key_text = tbl_def
+            .column_catalog_definition_array
+            .iter()
+            .any(|c| c.identifier == decoded.column_id && is_sort_key_text(c, index));
+        let mask = if segment.is_descending() { 0xff } else { 0 };
+        let sort_key: Vec<u8> = key[bytes.start + 1..bytes.end]
+            .iter()
+            .map(|b| b ^ mask)
+            .collect();
+        if !sort_key_text || sort_key_sections(&sort_key).is_none() {
+            continue;
+        }
+        samples.push(SortKeySample {
+            text: value
+                .chunks_exact(2)
+                .map(|c| u16::from_le_bytes([c[0], c[1]]))
+                .collect(),
+            sort_key,
+            lcmap_flags: index.lcmap_flags,
+        });
+    }
+    Ok(samples)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LCMAP_DEFAULT: u32 = 0x30401;
+
+    fn utf16(s: &str) -> Vec<u8> {
+        s.encode_utf16().flat_map(|c| c.to_le_bytes()).collect()
+    }
+
+    fn descending(mut key: Vec<u8>) -> Vec<u8> {
+        key.iter_mut().for_each(|b| *b = !*b);
+        key
+    }
+
+    // keys below are captured from the index pages of the databases in testdata
+    #[test]
+    fn test_normalize_fixed() {
+        let n = |typ: u32, v: &[u8]| normalize_value(typ, 0, LCMAP_DEFAULT, Some(v)).unwrap();
+
+        // MSysObjects Id: ObjidTable 2, Type 1, Id 2
+        assert_eq!(n(ESE_coltypLong, &[2, 0, 0, 0]), [0x7f, 0x80, 0, 0, 2]);
+        assert_eq!(n(ESE_coltypShort, &[1, 0]), [0x7f, 0x80, 0x01]);
+        assert_eq!(
+            n(ESE_coltypLong, &(-1i32).to_le_bytes()),
+            [0x7f, 0x7f, 0xff, 0xff, 0xff]
+        );
+        // MSysObjects RootObjects: RootFlag
+        assert_eq!(n(ESE_coltypBit, &[0xff]), [0x7f, 0xff]);
+        // CHAINED_DATABASES Year_index (descending): 2021
+        assert_eq!(
+            descending(n(ESE_coltypUnsignedShort, &2021u16.to_le_bytes())),
+            [0x80, 0xf8, 0x1a]
+        );
+        assert_eq!(n(ESE_coltypUnsignedLong, &[1, 0, 0, 0]), [0x7f, 0, 0, 0, 1]);
+        assert_eq!(
+            n(ESE_coltypLongLong, &(-2i64).to_le_bytes()),
+            [0x7f, 0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xfe]
+        );
+        // SYSTEM_IDENTITY CreationTime_index (descending)
+        assert_eq!(
+            descending(n(
+                ESE_coltypDateTime,
+                &[0xf0, 0x1d, 0x13, 0xb8, 0x5b, 0x60, 0xd7, 0x01]
+            )),
+            [0x80, 0x7e, 0x28, 0x9f, 0xa4, 0x47, 0xec, 0xe2, 0x0f]
+        );
+        // CLIENTS Username_RoleGuid_TenantId_index: RoleGuid
+        assert_eq!(
+            n(
+                ESE_coltypGUID,
+                &[
+                    0xc3, 0x5f, 0x49, 0xad, 0xaa, 0x0e, 0x3d, 0x41, 0xba, 0x7d, 0x8b, 0x13, 0xfa,
+                    0x7e, 0xc5, 0x98
+                ]
+            ),
+            [
+                0x7f, 0x8b, 0x13, 0xfa, 0x7e, 0xc5, 0x98, 0xba, 0x7d, 0x3d, 0x41, 0xaa, 0x0e, 0xc3,
+                0x5f, 0x49, 0xad
+            ]
+        );
+    }
+
+    #[test]
+    fn test_normalize_float_order() {
+        let values = [-1e10f64, -2.5, -0.0, 0.0, 1e-300, 2.5, 1e10];
+        let keys: Vec<Vec<u8>> = values
+            .iter()
+            .map(|v| normalize_value(ESE_coltypIEEEDouble, 0, 0, Some(&v.to_le_bytes())).unwrap())
+            .collect();
+        for w in keys.windows(2) {
+            assert!(w[0] <= w[1]);
+        }
+        let a = normalize_value(ESE_coltypIEEESingle, 0, 0, Some(&(-1.5f32).to_le_bytes()));
+        let b = normalize_value(ESE_coltypIEEESingle, 0, 0, Some(&1.5f32.to_le_bytes()));
+        assert!(a.unwrap() < b.unwrap());
+    }
+
+    #[test]
+    fn test_normalize_binary() {
+        let n = |v: &[u8]| normalize_value(ESE_coltypBinary, 0, LCMAP_DEFAULT, Some(v)).unwrap();
+
+        // CLIENTS Address_RoleGuid_TenantId_index: Address
+        assert_eq!(
+            n(&[0x0a, 0, 0, 0x02]),
+            [0x7f, 0x0a, 0, 0, 0x02, 0, 0, 0, 0, 0x04]
+        );
+        assert_eq!(
+            n(&[0xfe, 0x80, 0, 0, 0, 0, 0, 0, 0xc1, 0x4b, 0xef, 0xb2, 0x6e, 0xe0, 0xba, 0x13]),
+            [
+                0x7f, 0xfe, 0x80, 0, 0, 0, 0, 0, 0, 0x09, 0xc1, 0x4b, 0xef, 0xb2, 0x6e, 0xe0, 0xba,
+                0x13, 0x08
+            ]
+        );
+    }
+
+    #[test]
+    fn test_normalize_text() {
+        // MSysObjects Name (codepage 1252)
+        assert_eq!(
+            normalize_value(ESE_coltypText, 1252, LCMAP_DEFAULT, Some(b"MSysObjects")).unwrap(),
+            b"\x7fMSYSOBJECTS\x00"
+        );
+        // windows-1252 letters out of ASCII are upper cased too
+        assert_eq!(
+            normalize_value(
+                ESE_coltypText,
+                1252,
+                LCMAP_DEFAULT,
+                Some(b"caf\xe9 \x9c\xff\xf7")
+            )
+            .unwrap(),
+            b"\x7fCAF\xc9 \x8c\x9f\xf7\x00"
+        );
+
+        let n = |s: &str| {
+            normalize_value(ESE_coltypLongText, 1200, LCMAP_DEFAULT, Some(&utf16(s))).unwrap()
+        };
+        // DNS Address_index
+        assert_eq!(
+            n("10.0.0.10\0"),
+            [
+                0x7f, 0x0d, 0x1a, 0x0d, 0x03, 0x07, 0x33, 0x0d, 0x03, 0x07, 0x33, 0x0d, 0x03, 0x07,
+                0x33, 0x0d, 0x1a, 0x0d, 0x03, 0x01, 0x01, 0x01, 0x01, 0x00
+            ]
+        );
+        // CLIENTS Username_RoleGuid_TenantId_index
+        assert_eq!(
+            n("lab\\administrator\0"),
+            [
+                0x7f, 0x0e, 0x48, 0x0e, 0x02, 0x0e, 0x09, 0x07, 0x41, 0x0e, 0x02, 0x0e, 0x1a, 0x0e,
+                0x51, 0x0e, 0x32, 0x0e, 0x70, 0x0e, 0x32, 0x0e, 0x91, 0x0e, 0x99, 0x0e, 0x8a, 0x0e,
+                0x02, 0x0e, 0x99, 0x0e, 0x7c, 0x0e, 0x8a, 0x01, 0x01, 0x01, 0x01, 0x00
+            ]
+        );
+        assert_eq!(
+            n("lab\\dc-1$\0"),
+            [
+                0x7f, 0x0e, 0x48, 0x0e, 0x02, 0x0e, 0x09, 0x07, 0x41, 0x0e, 0x1a, 0x0e, 0x0a, 0x0d,
+                0x1a, 0x07, 0x21, 0x01, 0x01, 0x01, 0x01, 0xff, 0xf9, 0x82, 0x12, 0x00
+            ]
+        );
+        assert_eq!(n("LAB\\Administrator"), n("lab\\administrator"));
+        assert!(n("lab\\administrator") < n("lab\\dc-1$"));
+        assert!(n("10.0.0.111") < n("10.0.0.2"));
+
+        // case sensitive sort key and upper case normalization
+        let key = normalize_value(ESE_coltypText, 1200, LCMAP_SORTKEY, Some(&utf16("aB"))).unwrap();
+        assert_eq!(
+            key,
+            [0x7f, 0x0e, 0x02, 0x0e, 0x09, 0x01, 0x01, 0x02, 0x12, 0x01, 0x01, 0x00]
+        );
+        let key =
+            normalize_value(ESE_coltypText, 1200, LCMAP_UPPERCASE, Some(&utf16("aB"))).unwrap();
+        assert_eq!(key, [0x7f, 0x00, 0x41, 0x00, 0x42, 0x00, 0x00]);
+
+        // no made up weights for the characters out of the table, they need learned ones
+        let key = normalize_value(ESE_coltypText, 1200, LCMAP_DEFAULT, Some(&utf16("c:/")));
+        assert!(key.is_err());
+        let key = normalize_value(
+            ESE_coltypText,
+            1200,
+            LCMAP_UPPERCASE,
+            Some(&utf16("\u{416}")),
+        );
+        assert_eq!(key.unwrap(), [0x7f, 0x04, 0x16, 0x00, 0x00]);
+    }
+
+    #[test]
+    fn test_learn_sort_weights() {
+        // the keys of CLIENTS Username_RoleGuid_TenantId_index above, with the characters
+        // '\\' and '-' replaced by ones without weights
+        let sort_key = |s: &str| {
+            let key = normalize_value(ESE_coltypText, 1200, LCMAP_DEFAULT, Some(&utf16(s)));
+            key.unwrap()[1..].to_vec()
+        };
+        let sample = |s: &str, stored: &str| SortKeySample {
+            text: s.encode_utf16().collect(),
+            sort_key: sort_key(stored),
+            lcmap_flags: LCMAP_DEFAULT,
+        };
+        let samples = [
+            sample("lab\u{e000}administrator", "lab\\administrator"),
+            sample("lab\u{e000}dc\u{e001}1$", "lab\\dc-1$"),
+        ];
+        let weights = SortWeights::learn(&samples);
+        assert_eq!(weights.primary_weight(0xe000), Some(&[0x07, 0x41][..]));
+        assert_eq!(weights.special_weight(0xe001), Some([0x82, 0x12]));
+        let value = utf16("LAB\u{e000}dc\u{e001}1$");
+        let key = normalize_value_with_weights(
+            ESE_coltypText,
+            1200,
+            LCMAP_DEFAULT,
+            Some(&value),
+            &weights,
+        );
+        assert_eq!(key.unwrap()[1..], sort_key("lab\\dc-1$"));
+
+        // several characters without weights in a sample
+        let weights = SortWeights::learn(&[sample("\u{e000}\u{e001}", "ab")]);
+        assert_eq!(weights.primary_weight(0xe000), Some(&[0x0e, 0x02][..]));
+        assert_eq!(weights.primary_weight(0xe001), Some(&[0x0e, 0x09][..]));
+
+        // samples which don't agree leave the character without weight
+        let weights = SortWeights::learn(&[sample("x\u{e000}", "xa"), sample("\u{e000}", "b")]);
+        assert_eq!(weights.primary_weight(0xe000), None);
+        // nor is anything learned from a sample not matching the weight table
+        let weights = SortWeights::learn(&[sample("x\u{e000}", "ya")]);
+        assert_eq!(weights, SortWeights::default());
+    }
+
+    #[test]
+    fn test_normalize_null() {
+        assert_eq!(
+            normalize_value(ESE_coltypLong, 0, 0, None).unwrap(),
+            [KEY_PREFIX_NULL]
+        );
+        assert_eq!(
+            normalize_value(ESE_coltypBinary, 0, 0, Some(&[])).unwrap(),
+            [KEY_PREFIX_ZERO_LENGTH]
+        );
+        assert!(normalize_value(ESE_coltypLong, 0, 0, Some(&[1, 2])).is_err());
+        assert!(normalize_value(ESE_coltypSLV, 0, 0, Some(&[1])).is_err());
+
+        // NULL < zero-length < any data
+        let null = normalize_value(ESE_coltypText, 1252, 0, None).unwrap();
+        let empty = normalize_value(ESE_coltypText, 1252, 0, Some(&[])).unwrap();
+        let data = normalize_value(ESE_coltypText, 1252, 0, Some(b"\x01")).unwrap();
+        assert!(null < empty && empty < data);
+    }
+
+    fn decode(column_type: u32, codepage: u32, lcmap_flags: u32, key: &[u8]) -> (Vec<u8>, bool) {
+        let mut r = SegmentReader {
+            key,
+            pos: 1,
+            mask: 0,
+        };
+        decode_data(column_type, codepage, lcmap_flags, &mut r)
+            .unwrap()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_decode_fixed() {
+        let values: [(u32, Vec<u8>); 10] = [
+            (ESE_coltypBit, vec![0xff]),
+            (ESE_coltypShort, (-300i16).to_le_bytes().to_vec()),
+            (ESE_coltypLong, 2i32.to_le_bytes().to_vec()),
+            (ESE_coltypLongLong, (-2i64).to_le_bytes().to_vec()),
+            (ESE_coltypUnsignedShort, 2021u16.to_le_bytes().to_vec()),
+            (ESE_coltypUnsignedLong, 7u32.to_le_bytes().to_vec()),
+            (ESE_coltypIEEESingle, (-1.5f32).to_le_bytes().to_vec()),
+            (ESE_coltypIEEEDouble, 2.5f64.to_le_bytes().to_vec()),
+            (
+                ESE_coltypDateTime,
+                vec![0xf0, 0x1d, 0x13, 0xb8, 0x5b, 0x60, 0xd7, 0x01],
+            ),
+            (ESE_coltypGUID, (0..16).collect()),
+        ];
+        for (column_type, value) in values.iter() {
+            let key = normalize_value(*column_type, 0, 0, Some(value)).unwrap();
+            assert_eq!(decode(*column_type, 0, 0, &key), (value.clone(), true));
+        }
+        for value in [vec![0x0a, 0, 0, 2], (0..16).collect(), vec![0; 8]] {
+            let key = normalize_value(ESE_coltypBinary, 0, 0, Some(&value)).unwrap();
+            assert_eq!(decode(ESE_coltypBinary, 0, 0, &key), (value, true));
+        }
+    }
+
+    #[test]
+    fn test_decode_text() {
+        let key = normalize_value(ESE_coltypText, 1252, LCMAP_DEFAULT, Some(b"MSysObjects"));
+        assert_eq!(
+            decode(ESE_coltypText, 1252, LCMAP_DEFAULT, &key.unwrap()),
+            (b"MSYSOBJECTS".to_vec(), false)
+        );
+
+        for (text, lcmap_flags, expected, exact) in [
+            ("lab\\dc-1$\0", LCMAP_DEFAULT, "lab\\dc-1$", false),
+            ("10.0.0.10", LCMAP_DEFAULT, "10.0.0.10", false),
+            ("Lab-Dc", LCMAP_SORTKEY, "Lab-Dc", true),
+            ("Lab-Dc", LCMAP_UPPERCASE, "LAB-DC", false),
+        ] {
+            let key = normalize_value(ESE_coltypText, 1200, lcmap_flags, Some(&utf16(text)));
+            assert_eq!(
+                decode(ESE_coltypText, 1200, lcmap_flags, &key.unwrap()),
+                (utf16(expected), exact)
+            );
+        }
+    }
+}
@@ -0,0 +1,678 @@
+//log.rs
+#![allow(non_camel_case_types)]
+
+use nom_derive::*;
+use simple_error::SimpleError;
+use std::mem;
+use std::path::Path;
+
+use crate::parser::jet;
+use crate::parser::jet::{uint16_t, uint32_t, uint64_t, uint8_t};
+use crate::parser::space::SpaceObjectType;
+
+// The log file header is followed by the log records, in ranges covered by checksum records
+pub const LOG_FILE_HEADER_SIZE: usize = 4096;
+
+// Log record types (lrtyp)
+pub const LRTYP_NOP: u8 = 0;
+pub const LRTYP_INIT: u8 = 1;
+pub const LRTYP_TERM: u8 = 2;
+pub const LRTYP_MS: u8 = 3;
+pub const LRTYP_END: u8 = 4;
+pub const LRTYP_BEGIN: u8 = 5;
+pub const LRTYP_COMMIT: u8 = 6;
+pub const LRTYP_ROLLBACK: u8 = 7;
+pub const LRTYP_BEGIN0: u8 = 8;
+pub const LRTYP_COMMIT0: u8 = 9;
+pub const LRTYP_REFRESH: u8 = 10;
+pub const LRTYP_MACRO_BEGIN: u8 = 11;
+pub const LRTYP_MACRO_COMMIT: u8 = 12;
+pub const LRTYP_MACRO_ABORT: u8 = 13;
+pub const LRTYP_CREATE_DB: u8 = 14;
+pub const LRTYP_ATTACH_DB: u8 = 15;
+pub const LRTYP_DETACH_DB: u8 = 16;
+pub const LRTYP_RECOVERY_UNDO: u8 = 17;
+pub const LRTYP_RECOVERY_QUIT: u8 = 18;
+pub const LRTYP_FULL_BACKUP: u8 = 19;
+pub const LRTYP_INC_BACKUP: u8 = 20;
+pub const LRTYP_JET_OP: u8 = 21;
+pub const LRTYP_TRACE: u8 = 22;
+pub const LRTYP_SHUTDOWN_MARK: u8 = 23;
+pub const LRTYP_CREATE_MULTIPLE_EXTENT_FDP: u8 = 24;
+pub const LRTYP_CREATE_SINGLE_EXTENT_FDP: u8 = 25;
+pub const LRTYP_CONVERT_FDP: u8 = 26;
+pub const LRTYP_SPLIT: u8 = 27;
+pub const LRTYP_MERGE: u8 = 28;
+pub const LRTYP_INSERT: u8 = 29;
+pub const LRTYP_FLAG_INSERT: u8 = 30;
+pub const LRTYP_FLAG_INSERT_AND_REPLACE_DATA: u8 = 31;
+pub const LRTYP_FLAG_DELETE: u8 = 32;
+pub const LRTYP_REPLACE: u8 = 33;
+pub const LRTYP_REPLACE_D: u8 = 34;
+pub const LRTYP_DELETE: u8 = 35;
+pub const LRTYP_UNDO_INFO: u8 = 36;
+pub const LRTYP_DELTA: u8 = 37;
+pub const LRTYP_SET_EXTERNAL_HEADER: u8 = 38;
+pub const LRTYP_UNDO: u8 = 39;
+pub const LRTYP_CHECKSUM: u8 = 42;
+pub const LRTYP_PAGE_MOVE: u8 = 64;
+
+#[derive(Copy, Clone, Default, Debug, Nom)]
+#[repr(C)]
+pub struct LogFileHeader {
+    pub checksum: uint32_t,
+    pub generation: uint32_t,
+    pub sector_size: uint16_t,
+    // sizes in sectors
+    pub header_sectors: uint16_t,
+    pub file_sectors: uint16_t,
+    // 0 for 4096
+    pub page_size: uint16_t,
+    #[nom(Parse = "{ jet::DateTime::parse_le }")]
+    pub create_time: jet::DateTime,
+    #[nom(Parse = "{ jet::DateTime::parse_le }")]
+    pub previous_generation_create_time: jet::DateTime,
+    pub format_major_version: uint32_t,
+    pub format_minor_version: uint32_t,
+    pub format_update_version: uint32_t,
+    #[nom(Parse = "{ jet::Signature::parse_le }")]
+    pub log_signature: jet::Signature,
+}
+
+// Starts a range of records: the records of the next cb_forwards bytes,
+// the next checksum record follows cb_next bytes after this one (0 for the last range)
+#[derive(Copy, Clone, Default, Debug, Nom)]
+#[repr(C, packed)]
+pub struct LogChecksumRecord {
+    pub lrtyp: uint8_t,
+    pub cb_backwards: uint32_t,
+    pub cb_forwards: uint32_t,
+    pub cb_next: uint32_t,
+    pub checksum: uint32_t,
+    pub short_checksum: uint32_t,
+    pub use_short_checksum: uint8_t,
+}
+
+// Begin, commit and rollback of a transaction level of a session;
+// Begin0 and Commit0 records are followed by the transaction time stamp (trx)
+#[derive(Copy, Clone, Default, Debug, Nom)]
+#[repr(C, packed)]
+pub struct LogTransactionRecord {
+    pub lrtyp: uint8_t,
+    pub procid: uint16_t,
+    pub level: uint8_t,
+}
+
+// Operation on a page, the start of the node, split and merge records
+#[derive(Copy, Clone, Default, Debug, Nom)]
+#[repr(C, packed)]
+pub struct LogPageRecord {
+    pub lrtyp: uint8_t,
+    pub procid: uint16_t,
+    pub dbid: uint8_t,
+    pub page_number: uint32_t,
+    pub object_identifier: uint32_t,
+    pub dbtime_before: uint64_t,
+    pub dbtime_after: uint64_t,
+    pub trx_begin0: uint32_t,
+    pub level: uint8_t,
+}
+
+// Follows the page record of splits: the nodes of the page from iline_split move to the new
+// page, linked between the page and its right page (all of them for a vertical split of the
+// root, which then points to the new page), followed by the separator key of the page in
+// its parent, the old and the new key prefix (the data of the node 0 of the pages)
+#[derive(Copy, Clone, Default, Debug, Nom)]
+#[repr(C, packed)]
+pub struct LogSplitFields {
+    pub new_page_number: uint32_t,
+    pub parent_page_number: uint32_t,
+    pub right_page_number: uint32_t,
+    pub dbtime_right_before: uint64_t,
+    pub dbtime_parent_before: uint64_t,
+    pub split_type: uint8_t,
+    pub split_operation: uint8_t,
+    pub iline_split: uint16_t,
+    pub iline_operation: uint16_t,
+    pub lines: uint16_t,
+    pub split_page_flags: uint32_t,
+    pub new_page_flags: uint32_t,
+    pub cb_key_parent: uint16_t,
+    pub cb_prefix_split_old: uint16_t,
+    pub cb_prefix_split_new: uint16_t,
+}
+
+// Follows the page record of merges: the nodes of the page from iline_merge move to the
+// start of its right page; an empty or fully moved page is unlinked and its node of the
+// parent page (iline_parent) deleted, else the node gets the separator key which follows
+#[derive(Copy, Clone, Default, Debug, Nom)]
+#[repr(C, packed)]
+pub struct LogMergeFields {
+    pub right_page_number: uint32_t,
+    pub left_page_number: uint32_t,
+    pub parent_page_number: uint32_t,
+    pub dbtime_right_before: uint64_t,
+    pub dbtime_left_before: uint64_t,
+    pub dbtime_parent_before: uint64_t,
+    pub merge_type: uint8_t,
+    pub iline_merge: uint16_t,
+    pub iline_parent: uint16_t,
+    pub cb_key_parent_separator: uint16_t,
+}
+
+pub const SPLIT_TYPE_VERTICAL: u8 = 0;
+pub const SPLIT_TYPE_RIGHT: u8 = 1;
+pub const SPLIT_TYPE_APPEND: u8 = 2;
+
+pub const MERGE_TYPE_EMPTY_PAGE: u8 = 1;
+pub const MERGE_TYPE_FULL_RIGHT: u8 = 2;
+pub const MERGE_TYPE_PARTIAL_RIGHT: u8 = 3;
+
+// Operation on a node (page tag) of a page
+#[derive(Copy, Clone, Default, Debug, Nom)]
+#[repr(C, packed)]
+pub struct LogNodeRecord {
+    pub lrtyp: uint8_t,
+    pub procid: uint16_t,
+    pub dbid: uint8_t,
+    pub page_number: uint32_t,
+    pub object_identifier: uint32_t,
+    pub dbtime_before: uint64_t,
+    pub dbtime_after: uint64_t,
+    pub trx_begin0: uint32_t,
+    pub level: uint8_t,
+    pub iline: uint16_t,
+}
+
+// Sizes following the node record of insert and replace records; inserts carry the key
+// suffix (the prefix is shared with the page) and the data, replaces the old data size and
+// the new data (the differences with the old data for ReplaceD, see apply_diffs)
+#[derive(Copy, Clone, Default, Debug, Nom)]
+#[repr(C, packed)]
+pub struct LogNodeDataSizes {
+    pub cb_prefix_or_old: uint16_t,
+    pub cb_suffix_or_new: uint16_t,
+    pub cb_data: uint16_t,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct NodeOperation {
+    pub dbid: u8,
+    pub page_number: u32,
+    // father data page object identifier of the page
+    pub object_identifier: u32,
+    pub dbtime_before: u64,
+    pub dbtime_after: u64,
+    // node index of the page, the page tag iline + 1 (tag 0 holds the page key prefix)
+    pub iline: u16,
+    // the key suffix of inserts, the size of its prefix shared with the page
+    pub key_prefix_size: u16,
+    pub key: Vec<u8>,
+    pub data: Vec<u8>,
+    // the data size before replaces
+    pub old_data_size: u16,
+    // the tree of the object, resolved through the catalog (EseParser::load_log)
+    pub object_type: Option<SpaceObjectType>,
+    pub table: String,
+}
+
+impl NodeOperation {
+    pub fn is_long_value(&self) -> bool {
+        self.object_type == Some(SpaceObjectType::LongValue)
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SplitOperation {
+    // the split page, without node
+    pub node: NodeOperation,
+    pub split_type: u8,
+    pub new_page_number: u32,
+    pub parent_page_number: u32,
+    pub right_page_number: u32,
+    pub iline_split: u16,
+    pub split_page_flags: u32,
+    pub new_page_flags: u32,
+    pub parent_key: Vec<u8>,
+    // the key prefixes of the split and new pages
+    pub split_page_prefix: Vec<u8>,
+    pub new_page_prefix: Vec<u8>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MergeOperation {
+    // the merged page, without node
+    pub node: NodeOperation,
+    pub merge_type: u8,
+    pub right_page_number: u32,
+    pub left_page_number: u32,
+    pub parent_page_number: u32,
+    pub iline_merge: u16,
+    pub iline_parent: u16,
+    pub parent_key: Vec<u8>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LogOperation {
+    BeginTransaction { level: u8, trx: Option<u32> },
+    CommitTransaction { level: u8, trx: Option<u32> },
+    RollbackTransaction { level: u8 },
+    Insert(NodeOperation),
+    FlagInsert(NodeOperation),
+    FlagInsertAndReplaceData(NodeOperation),
+    Replace(NodeOperation),
+    // the data is the difference with the old data
+    ReplaceDiff(NodeOperation),
+    FlagDelete(NodeOperation),
+    Delete(NodeOperation),
+    // image of the page moved to the page number of the node operation
+    PageImage(NodeOperation),
+    Split(SplitOperation),
+    Merge(MergeOperation),
+    // a record of another type, decoded only by its size (record_layout), with the page
+    // it changes
+    Other { page_number: Option<u32> },
+}
+
+impl LogOperation {
+    pub fn node(&self) -> Option<&NodeOperation> {
+        match self {
+            LogOperation::Insert(node)
+            | LogOperation::FlagInsert(node)
+            | LogOperation::FlagInsertAndReplaceData(node)
+            | LogOperation::Replace(node)
+            | LogOperation::ReplaceDiff(node)
+            | LogOperation::FlagDelete(node)
+            | LogOperation::Delete(node)
+            | LogOperation::PageImage(node) => Some(node),
+            LogOperation::Split(split) => Some(&split.node),
+            LogOperation::Merge(merge) => Some(&merge.node),
+            _ => None,
+        }
+    }
+
+    pub fn node_mut(&mut self) -> Option<&mut NodeOperation> {
+        match self {
+            LogOperation::Insert(node)
+            | LogOperation::FlagInsert(node)
+            | LogOperation::FlagInsertAndReplaceData(node)
+            | LogOperation::Replace(node)
+            | LogOperation::ReplaceDiff(node)
+            | LogOperation::FlagDelete(node)
+            | LogOperation::Delete(node)
+            | LogOperation::PageImage(node) => Some(node),
+            LogOperation::Split(split) => Some(&mut split.node),
+            LogOperation::Merge(merge) => Some(&mut merge.node),
+            _ => None,
+        }
+    }
+
+    // the page the operation changes
+    pub fn page_number(&self) -> Option<u32> {
+        match self {
+            LogOperation::Other { page_number } => *page_number,
+            _ => self.node().map(|node| node.page_number),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LogRecord {
+    pub position: jet::LgPos,
+    pub lrtyp: u8,
+    // session of the record, 0 for the records without a session
+    pub procid: u16,
+    pub operation: LogOperation,
+}
+
+// Part of the log which was not decoded: the size of a record without a known layout
+// (or cut by the end of its range) is not known, so the rest of its checksum range is skipped
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TruncatedRange {
+    // of the first record not decoded
+    pub position: jet::LgPos,
+    // bytes skipped up to the next checksum range (or the end of the log)
+    pub size: usize,
+    pub error: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct LogFile {
+    pub header: LogFileHeader,
+    pub records: Vec<LogRecord>,
+    // the decoding resumes at the next checksum range
+    pub truncated: Vec<TruncatedRange>,
+}
+
+impl LogFile {
+    // all the records of the log were decoded
+    pub fn is_complete(&self) -> bool {
+        self.truncated.is_empty()
+    }
+}
+
+pub(crate) fn parse_struct<'a, S: Parse<&'a [u8]>>(
+    data: &'a [u8],
+    offset: usize,
+) -> Result<S, SimpleError> {
+    let (_, s) = S::parse_le(data.get(offset..).unwrap_or_default())
+        .map_err(|e: nom::Err<nom::error::Error<&[u8]>>| SimpleError::new(e.to_string()))?;
+    Ok(s)
+}
+
+fn bytes(data: &[u8], offset: usize, size: usize) -> Result<Vec<u8>, SimpleError> {
+    data.get(offset..offset + size)
+        .map(|b| b.to_vec())
+        .ok_or_else(|| {
+            SimpleError::new(format!(
+                "{} bytes at offset {} are out of the log file",
+                size, offset
+            ))
+        })
+}
+
+// Layout of the records decoded only by their size: a fixed part, with the session (procid)
+// after the record type when it has one, followed by data of the sum of the u16 lengths at
+// the offsets of the fixed part
+struct RecordLayout {
+    fixed: usize,
+    session: bool,
+    lengths: &'static [usize],
+    // starts with a page record of a page it changes
+    page: bool,
+}
+
+const PAGE_RECORD_SIZE: usize = mem::size_of::<LogPageRecord>();
+const NODE_RECORD_SIZE: usize = mem::size_of::<LogNodeRecord>();
+
+// Log time (LOGTIME) and the engine parameters of the init records (DBMS_PARAM: system and
+// log paths, 7 limits, flags and reserved bytes)
+const LOG_TIME_SIZE: usize = mem::size_of::<jet::DateTime>();
+const DBMS_PARAM_SIZE: usize = 2 * 260 + 7 * 4 + 1 + 15;
+
+// The record layouts follow the ESE record structures (LR*), they are not checked against
+// real logs; the types without one stop the decoding of their range
+fn record_layout(lrtyp: u8) -> Option<RecordLayout> {
+    let layout = |fixed: usize, session: bool, lengths: &'static [usize]| RecordLayout {
+        fixed,
+        session,
+        lengths,
+        page: false,
+    };
+    let page_layout = |fixed: usize, lengths: &'static [usize]| RecordLayout {
+        fixed,
+        session: true,
+        lengths,
+        page: true,
+    };
+    let signature = mem::size_of::<jet::Signature>();
+    let lgpos = mem::size_of::<jet::LgPos>();
+    Some(match lrtyp {
+        LRTYP_NOP | LRTYP_END | LRTYP_SHUTDOWN_MARK => layout(1, false, &[]),
+        LRTYP_INIT => layout(1 + LOG_TIME_SIZE + DBMS_PARAM_SIZE, false, &[]),
+        // log time, hard termination flag, position and redo position
+        LRTYP_TERM | LRTYP_RECOVERY_QUIT => layout(1 + LOG_TIME_SIZE + 1 + 2 * lgpos, false, &[]),
+        // forward link offset, checksum and sector
+        LRTYP_MS => layout(1 + 4 + 4 + 2, false, &[]),
+        LRTYP_RECOVERY_UNDO => layout(1 + LOG_TIME_SIZE, false, &[]),
+        // transaction time stamp
+        LRTYP_REFRESH => layout(1 + 2 + 4, true, &[]),
+        // dbtime
+        LRTYP_MACRO_BEGIN | LRTYP_MACRO_COMMIT | LRTYP_MACRO_ABORT => layout(1 + 2 + 8, true, &[]),
+        // dbid, flags, maximum size, database signature and the path length
+        LRTYP_CREATE_DB => layout(1 + 2 + 1 + 4 + 4 + signature + 2, true, &[40]),
+        // ... with the log signature and the consistent position
+        LRTYP_ATTACH_DB => layout(1 + 2 + 1 + 4 + 4 + 2 * signature + lgpos + 2, true, &[76]),
+        LRTYP_DETACH_DB => layout(1 + 2 + 1 + 4 + 2, true, &[8]),
+        // backup type, reserved byte and the path length
+        LRTYP_FULL_BACKUP | LRTYP_INC_BACKUP => layout(1 + 1 + 1 + 2, false, &[3]),
+        // operation
+        LRTYP_JET_OP => layout(1 + 2 + 1, true, &[]),
+        LRTYP_TRACE => layout(1 + 2 + 2, true, &[3]),
+        // parent FDP, primary extent size, owned and available extent roots and flags
+        LRTYP_CREATE_MULTIPLE_EXTENT_FDP => page_layout(PAGE_RECORD_SIZE + 5 * 4, &[]),
+        LRTYP_CREATE_SINGLE_EXTENT_FDP => page_layout(PAGE_RECORD_SIZE + 3 * 4, &[]),
+        // parent FDP, first secondary page, primary and secondary sizes, flags and free pages
+        LRTYP_CONVERT_FDP => page_layout(PAGE_RECORD_SIZE + 6 * 4, &[]),
+        // operation, maximum size, the lengths of the bookmark and of the before image;
+        // it doesn't change the page
+        LRTYP_UNDO_INFO => layout(NODE_RECORD_SIZE + 1 + 4 * 2, true, &[38, 40, 42]),
+        // the lengths of the bookmark, delta and offset of the counter
+        LRTYP_DELTA => page_layout(NODE_RECORD_SIZE + 2 * 2 + 4 + 2, &[35, 37]),
+        LRTYP_SET_EXTERNAL_HEADER => page_layout(PAGE_RECORD_SIZE + 2, &[33]),
+        // dbid, level, operation, page, object, dbtime and the lengths of the bookmark
+        LRTYP_UNDO => layout(1 + 2 + 1 + 1 + 1 + 4 + 4 + 8 + 2 * 2, true, &[22, 24]),
+        LRTYP_CHECKSUM => layout(mem::size_of::<LogChecksumRecord>(), false, &[]),
+        _ => return None,
+    })
+}
+
+// decodes the record at the offset, returns it with its size
+fn parse_record(
+    data: &[u8],
+    offset: usize,
+    position: jet::LgPos,
+) -> Result<(LogRecord, usize), SimpleError> {
+    let lrtyp = *data
+        .get(offset)
+        .ok_or_else(|| SimpleError::new("record is out of the log file"))?;
+    let record = |procid: u16, operation: LogOperation| LogRecord {
+        position,
+        lrtyp,
+        procid,
+        operation,
+    };
+    match lrtyp {
+        LRTYP_BEGIN | LRTYP_BEGIN0 | LRTYP_COMMIT | LRTYP_COMMIT0 | LRTYP_ROLLBACK => {
+            let tr: LogTransactionRecord = parse_struct(data, offset)?;
+            let mut size = mem::size_of::<LogTransactionRecord>();
+            let mut trx = None;
+            if lrtyp == LRTYP_BEGIN0 || lrtyp == LRTYP_COMMIT0 {
+                let b = bytes(data, offset + size, 4)?;
+                trx = Some(u32::from_le_bytes([b[0], b[1], b[2], b[3]]));
+                size += 4;
+            }
+            let level = tr.level;
+            let operation = match lrtyp {
+                LRTYP_BEGIN | LRTYP_BEGIN0 => LogOperation::BeginTransaction { level, trx },
+                LRTYP_COMMIT | LRTYP_COMMIT0 => LogOperation::CommitTransaction { level, trx },
+                _ => LogOperation::RollbackTransaction { level },
+            };
+            Ok((record(tr.procid, operation), size))
+        }
+        LRTYP_INSERT
+        | LRTYP_FLAG_INSERT
+        | LRTYP_FLAG_INSERT_AND_REPLACE_DATA
+        | LRTYP_FLAG_DELETE
+        | LRTYP_REPLACE
+        | LRTYP_REPLACE_D
+        | LRTYP_DELETE
+        | LRTYP_PAGE_MOVE => {
+            let nr: LogNodeRecord = parse_struct(data, offset)?;
+            let mut size = mem::size_of::<LogNodeRecord>();
+            let mut node = NodeOperation {
+                dbid: nr.dbid,
+                page_number: nr.page_number,
+                object_identifier: nr.object_identifier,
+                dbtime_before: nr.dbtime_before,
+                dbtime_after: nr.dbtime_after,
+                iline: nr.iline,
+                ..Default::default()
+            };
+            if lrtyp != LRTYP_FLAG_DELETE && lrtyp != LRTYP_DELETE {
+                let sizes: LogNodeDataSizes = parse_struct(data, offset + size)?;
+                size += mem::size_of::<LogNodeDataSizes>();
+                if matches!(
+                    lrtyp,
+                    LRTYP_INSERT | LRTYP_FLAG_INSERT | LRTYP_FLAG_INSERT_AND_REPLACE_DATA
+                ) {
+                    node.key_prefix_size = sizes.cb_prefix_or_old;
+                    node.key = bytes(data, offset + size, sizes.cb_suffix_or_new as usize)?;
+                    size += node.key.len();
+                } else {
+                    node.old_data_size = sizes.cb_prefix_or_old;
+                }
+                node.data = bytes(data, offset + size, sizes.cb_data as usize)?;
+                size += node.data.len();
+            }
+            let operation = match lrtyp {
+                LRTYP_INSERT => LogOperation::Insert(node),
+                LRTYP_FLAG_INSERT => LogOperation::FlagInsert(node),
+                LRTYP_FLAG_INSERT_AND_REPLACE_DATA => LogOperation::FlagInsertAndReplaceData(node),
+                LRTYP_FLAG_DELETE => LogOperation::FlagDelete(node),
+                LRTYP_REPLACE => LogOperation::Replace(node),
+                LRTYP_REPLACE_D => LogOperation::ReplaceDiff(node),
+                LRTYP_DELETE => LogOperation::Delete(node),
+                _ => LogOperation::PageImage(node),
+            };
+            Ok((record(nr.procid, operation), size))
+        }
+        LRTYP_SPLIT | LRTYP_MERGE => {
+            let pr: LogPageRecord = parse_struct(data, offset)?;
+            let mut size = mem::size_of::<LogPageRecord>();
+            let node = NodeOperation {
+                dbid: pr.dbid,
+                page_number: pr.page_number,
+                object_identifier: pr.object_identifier,
+                dbtime_before: pr.dbtime_before,
+                dbtime_after: pr.dbtime_after,
+                ..Default::default()
+            };
+            let take = |size: &mut usize, cb: u16| -> Result<Vec<u8>, SimpleError> {
+                let b = bytes(data, offset + *size, cb as usize)?;
+                *size += b.len();
+                Ok(b)
+            };
+            let operation = if lrtyp == LRTYP_SPLIT {
+                let sf: LogSplitFields = parse_struct(data, offset + size)?;
+                size += mem::size_of::<LogSplitFields>();
+                LogOperation::Split(SplitOperation {
+                    node,
+                    split_type: sf.split_type,
+                    new_page_number: sf.new_page_number,
+                    parent_page_number: sf.parent_page_number,
+                    right_page_number: sf.right_page_number,
+                    iline_split: sf.iline_split,
+                    split_page_flags: sf.split_page_flags,
+                    new_page_flags: sf.new_page_flags,
+                    parent_key: take(&mut size, sf.cb_key_parent)?,
+                    split_page_prefix: take(&mut size, sf.cb_prefix_split_old)?,
+                    new_page_prefix: take(&mut size, sf.cb_prefix_split_new)?,
+                })
+            } else {
+                let mf: LogMergeFields = parse_struct(data, offset + size)?;
+                size += mem::size_of::<LogMergeFields>();
+                LogOperation::Merge(MergeOperation {
+                    node,
+                    merge_type: mf.merge_type,
+                    right_page_number: mf.right_page_number,
+                    left_page_number: mf.left_page_number,
+                    parent_page_number: mf.parent_page_number,
+                    iline_merge: mf.iline_merge,
+                    iline_parent: mf.iline_parent,
+                    parent_key: take(&mut size, mf.cb_key_parent_separator)?,
+                })
+            };
+            Ok((record(pr.procid, operation), size))
+        }
+        _ => {
+            let layout = record_layout(lrtyp).ok_or_else(|| {
+                SimpleError::new(format!("record type {} has no known size", lrtyp))
+            })?;
+            let fixed = bytes(data, offset, layout.fixed)?;
+            let length = |o: usize| u16::from_le_bytes([fixed[o], fixed[o + 1]]) as usize;
+            let size = layout.fixed + layout.lengths.iter().map(|&o| length(o)).sum::<usize>();
+            bytes(data, offset, size)?;
+            let procid = if layout.session { length(1) as u16 } else { 0 };
+            let page_number = match layout.page {
+                true => Some(parse_struct::<LogPageRecord>(data, offset)?.page_number),
+                false => None,
+            };
+            Ok((record(procid, LogOperation::Other { page_number }), size))
+        }
+    }
+}
+
+// Parses the log file header and the records of the checksum ranges
+pub fn parse_log(data: &[u8]) -> Result<LogFile, SimpleError> {
+    if data.len() < LOG_FILE_HEADER_SIZE {
+        return Err(SimpleError::new(format!(
+            "log file of {} bytes is shorter than its header",
+            data.len()
+        )));
+    }
+    let header: LogFileHeader = parse_struct(data, 0)?;
+    let checksum = crate::utils::calc_crc32(&data[..LOG_FILE_HEADER_SIZE]);
+    if header.checksum != checksum {
+        return Err(SimpleError::new(format!(
+            "wrong log file header checksum: {}, calculated {}",
+            header.checksum, checksum
+        )));
+    }
+    if header.sector_size == 0 {
+        return Err(SimpleError::new("log file header: sector size is 0"));
+    }
+
+    let sector_size = header.sector_size as usize;
+    let position = |offset: usize| jet::LgPos {
+        ib: (offset % sector_size) as u16,
+        isec: (offset / sector_size) as u16,
+        l_generation: header.generation,
+    };
+    let mut log = LogFile {
+        header,
+        records: vec![],
+        truncated: vec![],
+    };
+    let mut offset = (header.header_sectors as usize * sector_size).max(LOG_FILE_HEADER_SIZE);
+    while offset < data.len() {
+        if data[offset] != LRTYP_CHECKSUM {
+            log.truncated.push(TruncatedRange {
+                position: position(offset),
+                size: data.len() - offset,
+                error: format!(
+                    "checksum record expected, found record type {}",
+                    data[offset]
+                ),
+            });
+            break;
+        }
+        let range: LogChecksumRecord = parse_struct(data, offset)?;
+        let start = offset + mem::size_of::<LogChecksumRecord>();
+        let end = (start + range.cb_forwards as usize).min(data.len());
+        let mut record_offset = start;
+        while record_offset < end {
+            // padding
+            if data[record_offset] == LRTYP_NOP {
+                record_offset += 1;
+                continue;
+            }
+            match parse_record(&data[..end], record_offset, position(record_offset)) {
+                Ok((record, size)) => {
+                    log.records.push(record);
+                    record_offset += size;
+                }
+                Err(e) => {
+                    log.truncated.push(TruncatedRange {
+                        position: position(record_offset),
+                        size: end - record_offset,
+                        error: e.to_string(),
+                    });
+                    break;
+                }
+            }
+        }
+        if range.cb_next == 0 {
+            break;
+        }
+        offset += range.cb_next as usize;
+    }
+    Ok(log)
+}
+
+pub fn load_log_file(path: impl AsRef<Path>) -> Result<LogFile, SimpleError> {
+    let path = path.as_ref();
+    let data = std::fs::read(path)
+        .map_err(|e| SimpleError::new(format!("can't read {}: {}", path.to_string_lossy(), e)))?;
+    parse_log(&data)
+}
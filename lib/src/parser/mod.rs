@@ -1,8 +1,15 @@
+pub mod carve;
+pub mod checkpoint;
 pub mod decomp;
 pub mod ese_both;
 pub mod ese_db;
 pub mod jet;
+pub mod key;
+pub mod log;
 pub mod reader;
+pub mod replay;
+pub mod space;
+pub mod timeline;
+pub mod verify;
 #[cfg(all(feature = "nt_comparison", target_os = "windows"))]
 pub mod win;
-
@@ -1,16 +1,18 @@
 //reader.rs
 use cache_2q::Cache;
+use nom_derive::Parse;
 use simple_error::SimpleError;
 use std::{
     array::TryFromSliceError,
     cell::RefCell,
-    collections::{hash_map::Entry, BTreeSet, HashMap},
+    collections::{hash_map::Entry, BTreeMap, BTreeSet, HashMap},
     convert::TryInto,
     io,
     io::{Read, Seek},
     mem,
 };
 
+use crate::ese_trait::SeekMode;
 use crate::parser::decomp::*;
 use crate::parser::ese_db;
 use crate::parser::ese_db::*;
@@ -30,13 +32,96 @@ pub trait ReadSeek: Read + Seek {
 
 impl<T: Read + Seek> ReadSeek for T {}
 
+// What to do with a page which fails its checksum
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ChecksumPolicy {
+    // fail the page load
+    #[default]
+    Strict,
+    // load the page, and record it in the corruption report
+    Warn,
+    // don't verify the checksums
+    Ignore,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct ReaderOptions {
+    pub cache_size: usize,
+    pub checksum_policy: ChecksumPolicy,
+    // also rebuild the tables dropped from the catalog out of its defunct entries
+    // (EseParser::get_dropped_tables), off by default
+    pub include_dropped: bool,
+}
+
+impl ReaderOptions {
+    pub fn new(cache_size: usize) -> ReaderOptions {
+        ReaderOptions {
+            cache_size,
+            checksum_policy: ChecksumPolicy::default(),
+            include_dropped: false,
+        }
+    }
+}
+
+// Page which failed its checksum
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PageCorruption {
+    pub page_number: u32,
+    pub error: String,
+}
+
+// Page whose flipped bits were corrected by the ECC checksums
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PageCorrection {
+    pub page_number: u32,
+    // bit offsets from the start of the page
+    pub bits: Vec<usize>,
+}
+
+// Pages loaded despite their checksum errors (ChecksumPolicy::Warn),
+// and the pages corrected, in page order
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CorruptionReport {
+    pub pages: Vec<PageCorruption>,
+    pub corrected: Vec<PageCorrection>,
+}
+
+impl CorruptionReport {
+    pub fn is_empty(&self) -> bool {
+        self.pages.is_empty() && self.corrected.is_empty()
+    }
+}
+
+fn page_checksum(page_data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(page_data[offset..offset + 8].try_into().unwrap_or_default())
+}
+
+fn is_new_record_format_page(page_data: &[u8]) -> bool {
+    let page_flags = u32::from_le_bytes(page_data[36..40].try_into().unwrap_or_default());
+    jet::PageFlags::from_bits_truncate(page_flags).intersects(jet::PageFlags::IS_NEW_RECORD_FORMAT)
+}
+
+// bits corrected by the ECC checksums, and the corrected page data
+type CorrectedPage = (Vec<usize>, Vec<u8>);
+
 pub struct Reader<T: ReadSeek> {
     file: RefCell<T>,
     cache: RefCell<Cache<u32, Vec<u8>>>,
+    checksum_policy: ChecksumPolicy,
+    // checksum errors by page number
+    corrupted_pages: RefCell<BTreeMap<u32, String>>,
+    // by page number, the corrected data is served instead of the file data
+    corrected_pages: RefCell<BTreeMap<u32, CorrectedPage>>,
+    // by page number, the pages with the log operations replayed (load_with_logs)
+    replayed_pages: RefCell<BTreeMap<u32, Vec<u8>>>,
     format_version: jet::FormatVersion,
     format_revision: jet::FormatRevision,
     page_size: u32,
     pub db_state: jet::DbState,
+    database_signature: jet::Signature,
+    log_signature: jet::Signature,
+    // generations of the logs needed to bring the database to a consistent state
+    required_log: (u32, u32),
 }
 
 impl<T: ReadSeek> Reader<T> {
@@ -44,6 +129,18 @@ impl<T: ReadSeek> Reader<T> {
         self.page_size <= 1024 * 8
     }
 
+    // size of the page headers, and whether they have the extended header of the large pages
+    pub(crate) fn page_header_size(&self) -> (usize, bool) {
+        let size = mem::size_of::<PageHeader0x11>() + mem::size_of::<PageHeaderCommon>();
+        if self.format_revision >= ESEDB_FORMAT_REVISION_EXTENDED_PAGE_HEADER
+            && !self.is_small_page()
+        {
+            (size + mem::size_of::<PageHeaderExt0x11>(), true)
+        } else {
+            (size, false)
+        }
+    }
+
     fn load_db_file_header(&mut self) -> Result<ese_db::FileHeader, SimpleError> {
         let (mut db_file_header, _) = ese_db::FileHeader::read(self, 0)?;
 
@@ -101,13 +198,25 @@ impl<T: ReadSeek> Reader<T> {
     }
 
     pub fn new(read_seek: T, cache_size: usize) -> Result<Reader<T>, SimpleError> {
+        Reader::with_options(read_seek, ReaderOptions::new(cache_size))
+    }
+
+    // the checksum policy applies to the database pages, the file header is always verified
+    pub fn with_options(read_seek: T, options: ReaderOptions) -> Result<Reader<T>, SimpleError> {
         let mut reader = Reader {
             file: RefCell::new(read_seek),
-            cache: RefCell::new(Cache::new(cache_size)),
+            cache: RefCell::new(Cache::new(options.cache_size)),
+            checksum_policy: options.checksum_policy,
+            corrupted_pages: RefCell::new(BTreeMap::new()),
+            corrected_pages: RefCell::new(BTreeMap::new()),
+            replayed_pages: RefCell::new(BTreeMap::new()),
             page_size: 2 * 1024, //just to read header
             format_version: 0,
             format_revision: 0,
             db_state: jet::DbState::impossible,
+            database_signature: jet::Signature::default(),
+            log_signature: jet::Signature::default(),
+            required_log: (0, 0),
         };
 
         let db_fh = reader.load_db_file_header()?;
@@ -115,11 +224,28 @@ impl<T: ReadSeek> Reader<T> {
         reader.format_revision = db_fh.format_revision;
         reader.page_size = db_fh.page_size;
         reader.db_state = db_fh.database_state;
+        reader.database_signature = db_fh.database_signature;
+        reader.log_signature = db_fh.log_signature;
+        let required_log = db_fh.required_log;
+        reader.required_log = (
+            u32::from_le_bytes(required_log[..4].try_into().unwrap_or_default()),
+            u32::from_le_bytes(required_log[4..].try_into().unwrap_or_default()),
+        );
         reader.cache.get_mut().clear();
 
         Ok(reader)
     }
 
+    // number of database pages in the file (without the header and its shadow copy)
+    pub fn page_count(&self) -> Result<u32, SimpleError> {
+        let size = self
+            .file
+            .borrow_mut()
+            .seek(io::SeekFrom::End(0))
+            .map_err(|e| SimpleError::new(format!("seek failed: {:?}", e)))?;
+        Ok((size / self.page_size as u64).saturating_sub(2) as u32)
+    }
+
     pub fn is_dirty(&self) -> bool {
         self.db_state == jet::DbState::DirtyShutdown
     }
@@ -135,6 +261,24 @@ impl<T: ReadSeek> Reader<T> {
         }
         let pg_no = (offset / self.page_size as u64) as u32;
         let mut c = self.cache.borrow_mut();
+        if !c.contains_key(&pg_no) {
+            // page with log operations replayed
+            if let Some(page_buf) = pg_no
+                .checked_sub(1)
+                .and_then(|page_number| self.replayed_pages.borrow().get(&page_number).cloned())
+            {
+                c.insert(pg_no, page_buf);
+            }
+        }
+        if !c.contains_key(&pg_no) {
+            // page corrected by the ECC checksums
+            if let Some((_, page_buf)) = pg_no
+                .checked_sub(1)
+                .and_then(|page_number| self.corrected_pages.borrow().get(&page_number).cloned())
+            {
+                c.insert(pg_no, page_buf);
+            }
+        }
         if !c.contains_key(&pg_no) {
             let mut page_buf = vec![0u8; self.page_size as usize];
             let f = &mut self.file.borrow_mut();
@@ -203,18 +347,80 @@ impl<T: ReadSeek> Reader<T> {
         Reader::new(read_seek, cache_size)
     }
 
+    pub fn load_db_with(read_seek: T, options: ReaderOptions) -> Result<Reader<T>, SimpleError> {
+        Reader::with_options(read_seek, options)
+    }
+
     pub fn page_size(&self) -> u32 {
         self.page_size
     }
 
+    pub fn database_signature(&self) -> jet::Signature {
+        self.database_signature
+    }
+
+    // signature of the transaction logs of the database
+    pub fn log_signature(&self) -> jet::Signature {
+        self.log_signature
+    }
+
+    // lowest and highest generations of the logs required to recover a dirty database
+    pub fn required_log_generations(&self) -> (u32, u32) {
+        self.required_log
+    }
+
+    // the replayed pages are served instead of the file data from then on
+    pub(crate) fn set_replayed_pages(&self, pages: BTreeMap<u32, Vec<u8>>) {
+        let mut cache = self.cache.borrow_mut();
+        for page_number in pages.keys() {
+            cache.remove(&(page_number + 1));
+        }
+        self.replayed_pages.borrow_mut().extend(pages);
+    }
+
+    pub fn checksum_policy(&self) -> ChecksumPolicy {
+        self.checksum_policy
+    }
+
+    // pages loaded so far despite their checksum errors
+    pub fn corruption_report(&self) -> CorruptionReport {
+        CorruptionReport {
+            pages: self
+                .corrupted_pages
+                .borrow()
+                .iter()
+                .map(|(page_number, error)| PageCorruption {
+                    page_number: *page_number,
+                    error: error.clone(),
+                })
+                .collect(),
+            corrected: self
+                .corrected_pages
+                .borrow()
+                .iter()
+                .map(|(page_number, (bits, _))| PageCorrection {
+                    page_number: *page_number,
+                    bits: bits.clone(),
+                })
+                .collect(),
+        }
+    }
+
     pub fn cache(&self) -> RefCell<Cache<u32, Vec<u8>>> {
         self.cache.clone()
     }
 
-    pub(crate) fn load_page_header(&self, page_number: u32) -> Result<PageHeader, SimpleError> {
+    pub fn verify_page_checksum(&self, page_number: u32) -> Result<(), SimpleError> {
         let page_offset = (page_number + 1) as u64 * (self.page_size) as u64;
         let page_data = self.read_bytes(page_offset, self.page_size as usize)?;
+        self.verify_page_data_checksum(page_number, &page_data)
+    }
 
+    fn verify_page_data_checksum(
+        &self,
+        page_number: u32,
+        page_data: &[u8],
+    ) -> Result<(), SimpleError> {
         fn calc_new_checksum_cmp_with(
             buffer: &[u8],
             page_number: u32,
@@ -230,15 +436,14 @@ impl<T: ReadSeek> Reader<T> {
             Ok(())
         }
 
-        let checksum = read_u64(self, page_offset)?;
-        let page_flags = read_u32(self, page_offset + 36)?;
-        if jet::PageFlags::from_bits_truncate(page_flags)
-            .intersects(jet::PageFlags::IS_NEW_RECORD_FORMAT)
-        {
+        let checksum = page_checksum(page_data, 0);
+        if is_new_record_format_page(page_data) {
             let mut block_len = page_data.len();
             if !self.is_small_page() {
                 block_len = page_data.len() / 4;
-                let ext = PageHeaderExt0x11::read(self, page_offset + 40)?;
+                let (_, ext) = PageHeaderExt0x11::parse_le(&page_data[40..]).map_err(
+                    |e: nom::Err<nom::error::Error<&[u8]>>| SimpleError::new(e.to_string()),
+                )?;
 
                 if ext.page_number != page_number as u64 {
                     return Err(SimpleError::new(format!(
@@ -267,13 +472,78 @@ impl<T: ReadSeek> Reader<T> {
             }
             calc_new_checksum_cmp_with(&page_data[..block_len], page_number, checksum, true)?;
         } else {
-            let calc_checksum = ((page_number as u64) << 32) | (calc_crc32(&page_data) as u64);
+            let calc_checksum = ((page_number as u64) << 32) | (calc_crc32(page_data) as u64);
             if calc_checksum != checksum {
                 return Err(SimpleError::new(format!(
                         "Page number: {}, calculated checksum 0x{:X} doesn't equal stored page header checksum 0x{:X}",
                         page_number, calc_checksum, checksum)));
             }
         }
+        Ok(())
+    }
+
+    // corrects a single flipped bit in every block of a new record format page
+    // (one block for pages up to 8k, four blocks for the larger ones) with the ECC checksums,
+    // the corrected page is served instead of the file data from then on
+    fn correct_page(&self, page_number: u32) -> Result<bool, SimpleError> {
+        let page_offset = (page_number + 1) as u64 * (self.page_size) as u64;
+        let mut page_data = self.read_bytes(page_offset, self.page_size as usize)?;
+        if !is_new_record_format_page(&page_data) {
+            return Ok(false);
+        }
+        let blocks = if self.is_small_page() { 1 } else { 4 };
+        let block_len = page_data.len() / blocks;
+        let mut bits = vec![];
+        // the first block holds the checksums of the others, it's corrected first
+        for block in 0..blocks {
+            let checksum_offset = if block == 0 { 0 } else { 32 + 8 * block };
+            let checksum = page_checksum(&page_data, checksum_offset);
+            let block_data = &mut page_data[block * block_len..(block + 1) * block_len];
+            if let Some(bit) =
+                correct_single_bit_error(block_data, page_number, checksum, block == 0)?
+            {
+                bits.push(block * block_len * 8 + bit);
+            }
+        }
+        if bits.is_empty()
+            || self
+                .verify_page_data_checksum(page_number, &page_data)
+                .is_err()
+        {
+            return Ok(false);
+        }
+
+        log::warn!("page {}: corrected bits {:?}", page_number, bits);
+        self.cache
+            .borrow_mut()
+            .insert(page_number + 1, page_data.clone());
+        self.corrected_pages
+            .borrow_mut()
+            .insert(page_number, (bits, page_data));
+        Ok(true)
+    }
+
+    pub(crate) fn load_page_header(&self, page_number: u32) -> Result<PageHeader, SimpleError> {
+        let page_offset = (page_number + 1) as u64 * (self.page_size) as u64;
+        if self.checksum_policy != ChecksumPolicy::Ignore {
+            let error = match self.verify_page_checksum(page_number) {
+                Err(e) if !self.correct_page(page_number)? => Some(e),
+                _ => None,
+            };
+            if let Some(e) = error {
+                if self.checksum_policy == ChecksumPolicy::Strict {
+                    return Err(e);
+                }
+                // zero filled pages were never written, they aren't corrupted
+                let page_data = self.read_bytes(page_offset, self.page_size as usize)?;
+                if page_data.iter().any(|b| *b != 0) {
+                    self.corrupted_pages
+                        .borrow_mut()
+                        .entry(page_number)
+                        .or_insert_with(|| e.to_string());
+                }
+            }
+        }
 
         if self.format_revision < ESEDB_FORMAT_REVISION_NEW_RECORD_FORMAT {
             let header = PageHeaderOld::read(self, page_offset)?;
@@ -389,19 +659,164 @@ impl<T: ReadSeek> Reader<T> {
     }
 
     pub fn load_catalog(&self) -> Result<Vec<jet::TableDefinition>, SimpleError> {
-        let db_page = jet::DbPage::new(self, jet::FixedPageNumber::Catalog as u32)?;
+        self.load_catalog_with(false)
+    }
 
-        let is_root = db_page.flags().contains(jet::PageFlags::IS_ROOT);
-        if is_root {
-            let _root_page_header = self.load_root_page_header(&db_page, db_page.tag(0)?)?;
+    // include_dropped: also rebuilds the tables dropped from the catalog out of its defunct
+    // entries, they follow the live tables and have the dropped flag set
+    pub fn load_catalog_with(
+        &self,
+        include_dropped: bool,
+    ) -> Result<Vec<jet::TableDefinition>, SimpleError> {
+        let (_, res) = self.load_any_catalog(include_dropped)?;
+        Ok(res)
+    }
+
+    // loads the catalog, or its shadow copy (MSysObjectsShadow) if the catalog is damaged,
+    // returns the root page of the loaded one
+    pub fn load_any_catalog(
+        &self,
+        include_dropped: bool,
+    ) -> Result<(jet::FixedPageNumber, Vec<jet::TableDefinition>), SimpleError> {
+        match self.load_catalog_from(jet::FixedPageNumber::Catalog, include_dropped) {
+            Ok(res) => Ok((jet::FixedPageNumber::Catalog, res)),
+            Err(e) => self
+                .load_catalog_from(jet::FixedPageNumber::CatalogBackup, include_dropped)
+                .map(|res| (jet::FixedPageNumber::CatalogBackup, res))
+                .map_err(|backup_e| {
+                    SimpleError::new(format!(
+                        "can't load the catalog: {}, nor its shadow copy: {}",
+                        e, backup_e
+                    ))
+                }),
         }
+    }
+
+    // catalog: the root page of the catalog or of its shadow copy
+    pub fn load_catalog_from(
+        &self,
+        catalog: jet::FixedPageNumber,
+        include_dropped: bool,
+    ) -> Result<Vec<jet::TableDefinition>, SimpleError> {
+        let (items, defunct_items) = self.load_catalog_items(catalog, include_dropped)?;
 
         let mut res: Vec<jet::TableDefinition> = vec![];
         let mut table_def: jet::TableDefinition = jet::TableDefinition {
             table_catalog_definition: None,
             column_catalog_definition_array: vec![],
+            index_definition_array: vec![],
             long_value_catalog_definition: None,
+            dropped: false,
+        };
+        for cat_item in items {
+            if cat_item.cat_type == jet::CatalogType::Table as u16 {
+                if table_def.table_catalog_definition.is_some() {
+                    res.push(table_def);
+                    table_def = jet::TableDefinition {
+                        table_catalog_definition: None,
+                        column_catalog_definition_array: vec![],
+                        index_definition_array: vec![],
+                        long_value_catalog_definition: None,
+                        dropped: false,
+                    };
+                } else if !table_def.column_catalog_definition_array.is_empty()
+                    || !table_def.index_definition_array.is_empty()
+                    || table_def.long_value_catalog_definition.is_some()
+                {
+                    return Err(SimpleError::new(
+                        "corrupted table detected: column/long definition is going before table",
+                    ));
+                }
+                table_def.table_catalog_definition = Some(cat_item);
+            } else if cat_item.cat_type == jet::CatalogType::Column as u16 {
+                table_def.column_catalog_definition_array.push(cat_item);
+            } else if cat_item.cat_type == jet::CatalogType::Index as u16 {
+                table_def
+                    .index_definition_array
+                    .push(jet::IndexDefinition::new(&cat_item, self.format_revision));
+            } else if cat_item.cat_type == jet::CatalogType::LongValue as u16 {
+                if table_def.long_value_catalog_definition.is_some() {
+                    return Err(SimpleError::new("long-value catalog definition duplicate?"));
+                }
+                table_def.long_value_catalog_definition = Some(cat_item);
+            }
+            // we knowingly ignore Callback Catalog type
+            else if cat_item.cat_type != jet::CatalogType::Callback as u16 {
+                return Err(SimpleError::new(format!(
+                    "TODO: Unhandled cat_item.cat_type {}",
+                    cat_item.cat_type
+                )));
+            }
+        }
+
+        if table_def.table_catalog_definition.is_some() {
+            res.push(table_def);
+        }
+
+        if include_dropped {
+            let dropped = load_dropped_tables(&res, defunct_items, self.format_revision);
+            res.extend(dropped);
+        }
+        Ok(res)
+    }
+
+    // compares the entries of the catalog and of its shadow copy by their key
+    pub fn compare_catalogs(&self) -> Result<Vec<jet::CatalogDifference>, SimpleError> {
+        let (catalog, _) = self.load_catalog_items(jet::FixedPageNumber::Catalog, false)?;
+        let (shadow, _) = self.load_catalog_items(jet::FixedPageNumber::CatalogBackup, false)?;
+        let key = |c: &jet::CatalogDefinition| {
+            (
+                c.father_data_page_object_identifier,
+                c.cat_type,
+                c.identifier,
+            )
         };
+        let mut entries: BTreeMap<_, (Option<_>, Option<_>)> = BTreeMap::new();
+        for c in catalog {
+            let k = key(&c);
+            entries.entry(k).or_default().0 = Some(c);
+        }
+        for c in shadow {
+            let k = key(&c);
+            entries.entry(k).or_default().1 = Some(c);
+        }
+
+        let mut differences = vec![];
+        for ((table_object_identifier, cat_type, identifier), (catalog, shadow)) in entries {
+            let fields = match (&catalog, &shadow) {
+                (Some(c), Some(s)) => c.differences(s),
+                _ => vec![],
+            };
+            if catalog.is_none() || shadow.is_none() || !fields.is_empty() {
+                differences.push(jet::CatalogDifference {
+                    table_object_identifier,
+                    cat_type,
+                    identifier,
+                    catalog,
+                    shadow,
+                    fields,
+                });
+            }
+        }
+        Ok(differences)
+    }
+
+    // entries of the catalog tree in the key order (table object identifier, type, identifier),
+    // and its defunct entries with include_defunct
+    pub fn load_catalog_items(
+        &self,
+        catalog: jet::FixedPageNumber,
+        include_defunct: bool,
+    ) -> Result<(Vec<jet::CatalogDefinition>, Vec<jet::CatalogDefinition>), SimpleError> {
+        let db_page = jet::DbPage::new(self, catalog as u32)?;
+
+        let is_root = db_page.flags().contains(jet::PageFlags::IS_ROOT);
+        if is_root {
+            let _root_page_header = self.load_root_page_header(&db_page, db_page.tag(0)?)?;
+        }
+
+        let mut items: Vec<jet::CatalogDefinition> = vec![];
+        let mut defunct_items: Vec<jet::CatalogDefinition> = vec![];
 
         let mut page_number;
         if db_page.flags().contains(jet::PageFlags::IS_PARENT) {
@@ -440,51 +855,20 @@ impl<T: ReadSeek> Reader<T> {
                 if jet::PageTagFlags::from_bits_truncate(pg_tag.flags)
                     .intersects(jet::PageTagFlags::FLAG_IS_DEFUNCT)
                 {
-                    continue;
-                }
-                let cat_item = self.load_catalog_item(&db_page, pg_tag)?;
-                if cat_item.cat_type == jet::CatalogType::Table as u16 {
-                    if table_def.table_catalog_definition.is_some() {
-                        res.push(table_def);
-                        table_def = jet::TableDefinition {
-                            table_catalog_definition: None,
-                            column_catalog_definition_array: vec![],
-                            long_value_catalog_definition: None,
-                        };
-                    } else if !table_def.column_catalog_definition_array.is_empty()
-                        || table_def.long_value_catalog_definition.is_some()
-                    {
-                        return Err(SimpleError::new(
-                            "corrupted table detected: column/long definition is going before table"));
-                    }
-                    table_def.table_catalog_definition = Some(cat_item);
-                } else if cat_item.cat_type == jet::CatalogType::Column as u16 {
-                    table_def.column_catalog_definition_array.push(cat_item);
-                } else if cat_item.cat_type == jet::CatalogType::LongValue as u16 {
-                    if table_def.long_value_catalog_definition.is_some() {
-                        return Err(SimpleError::new("long-value catalog definition duplicate?"));
+                    // the data of deleted entries may have been partially overwritten
+                    if include_defunct {
+                        if let Ok(cat_item) = self.load_catalog_item(&db_page, pg_tag) {
+                            defunct_items.push(cat_item);
+                        }
                     }
-                    table_def.long_value_catalog_definition = Some(cat_item);
-                }
-                // we knowingly ignore Index and Callback Catalog types
-                else if cat_item.cat_type != jet::CatalogType::Index as u16
-                    && cat_item.cat_type != jet::CatalogType::Callback as u16
-                {
-                    return Err(SimpleError::new(format!(
-                        "TODO: Unhandled cat_item.cat_type {}",
-                        cat_item.cat_type
-                    )));
+                    continue;
                 }
+                items.push(self.load_catalog_item(&db_page, pg_tag)?);
             }
             prev_page_number = page_number;
             page_number = db_page.next_page();
         }
-
-        if table_def.table_catalog_definition.is_some() {
-            res.push(table_def);
-        }
-
-        Ok(res)
+        Ok((items, defunct_items))
     }
 
     pub fn load_catalog_item(
@@ -534,10 +918,15 @@ impl<T: ReadSeek> Reader<T> {
         cat_def.flags = data_def.flags;
         if cat_def.cat_type == jet::CatalogType::Column as u16 {
             cat_def.codepage = data_def.pages_or_locale.codepage();
+        } else if cat_def.cat_type == jet::CatalogType::Index as u16 {
+            cat_def.locale_identifier = data_def.pages_or_locale.locale_identifier();
         }
         if ddh.last_fixed_size_data_type >= 10 {
             cat_def.lcmap_flags = data_def.lc_map_flags;
         }
+        if ddh.last_fixed_size_data_type >= 11 {
+            cat_def.key_most = data_def.key_most;
+        }
 
         if number_of_variable_size_data_types > 0 {
             let mut variable_size_data_types_offset = ddh.variable_size_data_types_offset as u32;
@@ -575,10 +964,24 @@ impl<T: ReadSeek> Reader<T> {
                             cat_def.default_value =
                                 self.read_bytes(offset_def, data_type_size as usize)?;
                         }
-                        132..=137 => {
-                            // 132 KeyFldIDs
+                        132 => {
+                            // KeyFldIDs
+                            let offset_key = offset_ddh
+                                + variable_size_data_type_value_data_offset as u64
+                                + previous_variable_size_data_type_size as u64;
+                            cat_def.key_field_ids =
+                                self.read_bytes(offset_key, data_type_size as usize)?;
+                        }
+                        134 => {
+                            // ConditionalColumns
+                            let offset_cond = offset_ddh
+                                + variable_size_data_type_value_data_offset as u64
+                                + previous_variable_size_data_type_size as u64;
+                            cat_def.conditional_columns =
+                                self.read_bytes(offset_cond, data_type_size as usize)?;
+                        }
+                        133 | 135..=137 => {
                             // 133 VarSegMac
-                            // 134 ConditionalColumns
                             // 135 TupleLimits
                             // 136 Version
                             // 137 iMSO_SortID (?)
@@ -634,6 +1037,442 @@ impl<T: ReadSeek> Reader<T> {
         }
     }
 
+    // returns the full key (common prefix + local key) of the page tag and the offset of its data
+    pub fn load_page_tag_key(
+        &self,
+        db_page: &jet::DbPage,
+        page_tag: &PageTag,
+    ) -> Result<(Vec<u8>, u64), SimpleError> {
+        // The upper 3-bits of the first 16-bit-value may contain the page tag flags (on any page type)
+        let clean_first_word = |data: u16| -> u16 {
+            if self.format_revision >= ESEDB_FORMAT_REVISION_EXTENDED_PAGE_HEADER
+                && self.page_size >= 16384
+            {
+                data & 0x1FFF
+            } else {
+                data
+            }
+        };
+
+        let mut offset = page_tag.offset(db_page);
+        let mut key: Vec<u8> = vec![];
+        let mut first_word_read = false;
+        if page_tag
+            .flags()
+            .intersects(jet::PageTagFlags::FLAG_HAS_COMMON_KEY_SIZE)
+        {
+            let common_page_key_size = clean_first_word(read_u16(self, offset)?);
+            first_word_read = true;
+            offset += 2;
+            if common_page_key_size > 0 {
+                let page_tag_0 = db_page.tag(0)?;
+                if common_page_key_size > page_tag_0.size {
+                    return Err(SimpleError::new(format!(
+                        "page {}: common key size {} is bigger than page prefix size {}",
+                        db_page.page_number, common_page_key_size, page_tag_0.size
+                    )));
+                }
+                key = self.read_bytes(page_tag_0.offset(db_page), common_page_key_size as usize)?;
+            }
+        }
+
+        let mut local_page_key_size: u16 = read_u16(self, offset)?;
+        if !first_word_read {
+            local_page_key_size = clean_first_word(local_page_key_size);
+        }
+        offset += 2;
+        if local_page_key_size > 0 {
+            key.append(&mut self.read_bytes(offset, local_page_key_size as usize)?);
+            offset += local_page_key_size as u64;
+        }
+        Ok((key, offset))
+    }
+
+    // descends the b-tree from page_number to the leaf page which may contain key
+    pub fn find_leaf_page(&self, mut page_number: u32, key: &[u8]) -> Result<u32, SimpleError> {
+        let mut visited_pages: BTreeSet<u32> = BTreeSet::new();
+        loop {
+            if visited_pages.contains(&page_number) {
+                return Err(SimpleError::new(format!(
+                    "Child page loop detected at page number {}, visited pages: {:?}",
+                    page_number, visited_pages
+                )));
+            }
+
+            let db_page = jet::DbPage::new(self, page_number)?;
+            if db_page.flags().contains(jet::PageFlags::IS_LEAF) {
+                return Ok(page_number);
+            } else {
+                visited_pages.insert(page_number);
+            }
+            if db_page.tags() < 2 {
+                return Err(SimpleError::new(format!(
+                    "branch page {} has no child pages",
+                    page_number
+                )));
+            }
+
+            // the separator key of a child is the first key of the next child,
+            // the last child has an empty separator (bigger than any key)
+            let mut child_tag = db_page.tag(db_page.tags() - 1)?;
+            for i in 1..db_page.tags() {
+                let page_tag = db_page.tag(i)?;
+                let (separator, _) = self.load_page_tag_key(&db_page, page_tag)?;
+                if separator.is_empty() || separator.as_slice() > key {
+                    child_tag = page_tag;
+                    break;
+                }
+            }
+            page_number = self.page_tag_get_branch_child_page_number(&db_page, child_tag)?;
+        }
+    }
+
+    pub fn find_last_leaf_page(&self, mut page_number: u32) -> Result<u32, SimpleError> {
+        let mut visited_pages: BTreeSet<u32> = BTreeSet::new();
+        loop {
+            if visited_pages.contains(&page_number) {
+                return Err(SimpleError::new(format!(
+                    "Child page loop detected at page number {}, visited pages: {:?}",
+                    page_number, visited_pages
+                )));
+            }
+
+            let db_page = jet::DbPage::new(self, page_number)?;
+            if db_page.flags().contains(jet::PageFlags::IS_LEAF) {
+                return Ok(page_number);
+            } else {
+                visited_pages.insert(page_number);
+            }
+            if db_page.tags() < 2 {
+                return Err(SimpleError::new(format!(
+                    "branch page {} has no child pages",
+                    page_number
+                )));
+            }
+
+            page_number = self.page_tag_get_branch_child_page_number(
+                &db_page,
+                db_page.tag(db_page.tags() - 1)?,
+            )?;
+        }
+    }
+
+    // descends to the leaf page at the fractional position (0.0 first, 1.0 last) of the b-tree,
+    // choosing children by the tag counts of the branch pages;
+    // returns the leaf page and the fractional position inside it
+    pub fn find_leaf_page_at(
+        &self,
+        mut page_number: u32,
+        mut fraction: f64,
+    ) -> Result<(u32, f64), SimpleError> {
+        let mut visited_pages: BTreeSet<u32> = BTreeSet::new();
+        loop {
+            if visited_pages.contains(&page_number) {
+                return Err(SimpleError::new(format!(
+                    "Child page loop detected at page number {}, visited pages: {:?}",
+                    page_number, visited_pages
+                )));
+            }
+
+            let db_page = jet::DbPage::new(self, page_number)?;
+            if db_page.flags().contains(jet::PageFlags::IS_LEAF) {
+                return Ok((page_number, fraction));
+            } else {
+                visited_pages.insert(page_number);
+            }
+            if db_page.tags() < 2 {
+                return Err(SimpleError::new(format!(
+                    "branch page {} has no child pages",
+                    page_number
+                )));
+            }
+
+            let children = (db_page.tags() - 1) as f64;
+            let child = (fraction * children).floor().min(children - 1.0);
+            fraction = fraction * children - child;
+            page_number = self.page_tag_get_branch_child_page_number(
+                &db_page,
+                db_page.tag(1 + child as usize)?,
+            )?;
+        }
+    }
+
+    // counts the live (not defunct) tags of all the leaf pages of the b-tree,
+    // only the page headers and tags are read
+    pub fn count_leaf_tags(&self, page_number: u32) -> Result<u64, SimpleError> {
+        let mut db_page = jet::DbPage::new(self, self.find_first_leaf_page(page_number)?)?;
+        let mut visited_pages: BTreeSet<u32> = BTreeSet::new();
+        let mut count = 0;
+        loop {
+            visited_pages.insert(db_page.page_number);
+            count += count_live_tags(&db_page)?;
+
+            let next_page = db_page.common().next_page;
+            if next_page == 0 {
+                return Ok(count);
+            }
+            if visited_pages.contains(&next_page) {
+                return Err(SimpleError::new(format!(
+                    "Circular page reference identified for page_number: {}",
+                    next_page
+                )));
+            }
+            db_page = jet::DbPage::new(self, next_page)?;
+        }
+    }
+
+    // estimates the number of live leaf tags of the b-tree from the fan-out of the branch pages
+    // on the path to the middle leaf and the tag counts of a few of its sibling leaves
+    pub fn estimate_leaf_tags(&self, mut page_number: u32) -> Result<u64, SimpleError> {
+        let mut visited_pages: BTreeSet<u32> = BTreeSet::new();
+        let mut pages: f64 = 1.0;
+        loop {
+            if visited_pages.contains(&page_number) {
+                return Err(SimpleError::new(format!(
+                    "Child page loop detected at page number {}, visited pages: {:?}",
+                    page_number, visited_pages
+                )));
+            }
+
+            let db_page = jet::DbPage::new(self, page_number)?;
+            if db_page.flags().contains(jet::PageFlags::IS_LEAF) {
+                // the root is the only leaf
+                return count_live_tags(&db_page);
+            } else {
+                visited_pages.insert(page_number);
+            }
+            if db_page.tags() < 2 {
+                return Err(SimpleError::new(format!(
+                    "branch page {} has no child pages",
+                    page_number
+                )));
+            }
+
+            let children = db_page.tags() - 1;
+            let child = |i: usize| -> Result<u32, SimpleError> {
+                self.page_tag_get_branch_child_page_number(&db_page, db_page.tag(i)?)
+            };
+            let middle = jet::DbPage::new(self, child(1 + children / 2)?)?;
+            if middle.flags().contains(jet::PageFlags::IS_LEAF) {
+                let mut samples = vec![1, 1 + children / 2, children];
+                samples.dedup();
+                let mut tags = 0;
+                for &i in samples.iter() {
+                    tags += count_live_tags(&jet::DbPage::new(self, child(i)?)?)?;
+                }
+                let average = tags as f64 / samples.len() as f64;
+                return Ok((pages * children as f64 * average).round() as u64);
+            }
+            pages *= children as f64;
+            page_number = middle.page_number;
+        }
+    }
+
+    // walks the leaf pages starting from tag i of db_page (forward or backward),
+    // skipping defunct tags, and returns the first tag accepted by accept
+    pub fn find_leaf_tag<F>(
+        &self,
+        mut db_page: jet::DbPage,
+        mut i: usize,
+        forward: bool,
+        mut accept: F,
+    ) -> Result<Option<(jet::DbPage, usize)>, SimpleError>
+    where
+        F: FnMut(&jet::DbPage, &PageTag) -> Result<bool, SimpleError>,
+    {
+        let mut visited_pages: BTreeSet<u32> = BTreeSet::new();
+        loop {
+            visited_pages.insert(db_page.page_number);
+            while i > 0 && i < db_page.tags() {
+                let page_tag = db_page.tag(i)?;
+                if !page_tag
+                    .flags()
+                    .intersects(jet::PageTagFlags::FLAG_IS_DEFUNCT)
+                    && accept(&db_page, page_tag)?
+                {
+                    return Ok(Some((db_page, i)));
+                }
+                if forward {
+                    i += 1;
+                } else {
+                    i -= 1;
+                }
+            }
+
+            let page_number = if forward {
+                db_page.common().next_page
+            } else {
+                db_page.common().previous_page
+            };
+            if page_number == 0 {
+                return Ok(None);
+            }
+            if visited_pages.contains(&page_number) {
+                return Err(SimpleError::new(format!(
+                    "Circular page reference identified for page_number: {}",
+                    page_number
+                )));
+            }
+            db_page = jet::DbPage::new(self, page_number)?;
+            i = if forward {
+                1
+            } else {
+                db_page.tags().saturating_sub(1)
+            };
+        }
+    }
+
+    // finds the leaf tag of the b-tree rooted at page_number matching key according to mode
+    pub fn seek_leaf_tag(
+        &self,
+        page_number: u32,
+        key: &[u8],
+        mode: SeekMode,
+    ) -> Result<Option<(jet::DbPage, usize)>, SimpleError> {
+        let db_page = jet::DbPage::new(self, self.find_leaf_page(page_number, key)?)?;
+        let found = match mode {
+            SeekMode::Eq | SeekMode::Ge => self.find_leaf_tag(db_page, 1, true, |p, t| {
+                Ok(self.load_page_tag_key(p, t)?.0.as_slice() >= key)
+            })?,
+            SeekMode::Le => {
+                let last = db_page.tags().saturating_sub(1);
+                self.find_leaf_tag(db_page, last, false, |p, t| {
+                    Ok(self.load_page_tag_key(p, t)?.0.as_slice() <= key)
+                })?
+            }
+        };
+        if let Some((db_page, i)) = &found {
+            if mode == SeekMode::Eq
+                && !self
+                    .load_page_tag_key(db_page, db_page.tag(*i)?)?
+                    .0
+                    .starts_with(key)
+            {
+                return Ok(None);
+            }
+        }
+        Ok(found)
+    }
+
+    // returns the key and the data (primary bookmark) of a secondary index leaf tag
+    pub fn load_index_entry(
+        &self,
+        db_page: &jet::DbPage,
+        page_tag: &PageTag,
+    ) -> Result<(Vec<u8>, Vec<u8>), SimpleError> {
+        let (key, data_offset) = self.load_page_tag_key(db_page, page_tag)?;
+        let end_offset = page_tag.offset(db_page) + page_tag.size as u64;
+        if data_offset > end_offset {
+            return Err(SimpleError::new(format!(
+                "page {}: index entry key is bigger than page tag {:?}",
+                db_page.page_number, page_tag
+            )));
+        }
+        let bookmark = self.read_bytes(data_offset, (end_offset - data_offset) as usize)?;
+        Ok((key, bookmark))
+    }
+
+    // full key (common page key prefix and local key) of the record in the leaf page tag
+    pub fn load_record_key(
+        &self,
+        db_page: &jet::DbPage,
+        page_tag_index: usize,
+    ) -> Result<Vec<u8>, SimpleError> {
+        if !db_page.flags().contains(jet::PageFlags::IS_LEAF) {
+            return Err(SimpleError::new(format!(
+                "expected leaf page, page_flags 0x{:?}",
+                db_page.flags()
+            )));
+        }
+
+        if page_tag_index == 0 || page_tag_index >= db_page.tags() {
+            return Err(SimpleError::new(format!(
+                "wrong page tag index: {}",
+                page_tag_index
+            )));
+        }
+
+        let page_tag = db_page.tag(page_tag_index)?;
+        let (key, _) = self.load_page_tag_key(db_page, page_tag)?;
+        Ok(key)
+    }
+
+    // long value keys referred by the tagged columns of the record in the leaf page tag
+    pub fn load_record_lv_keys(
+        &self,
+        db_page: &jet::DbPage,
+        page_tag_index: usize,
+    ) -> Result<Vec<u64>, SimpleError> {
+        use jet::TaggedDataTypeFlag;
+
+        let page_tag = db_page.tag(page_tag_index)?;
+        let (_, offset_ddh) = self.load_page_tag_key(db_page, page_tag)?;
+        let record_end = page_tag.offset(db_page) + page_tag.size as u64;
+        let ddh = ese_db::DataDefinitionHeader::read(self, offset_ddh)?;
+
+        // the tagged data follows the variable size data
+        let number_of_variable_size_data_types: u64 = if ddh.last_variable_size_data_type > 127 {
+            ddh.last_variable_size_data_type as u64 - 127
+        } else {
+            0
+        };
+        let variable_sizes_offset = offset_ddh + ddh.variable_size_data_types_offset as u64;
+        let mut variable_size = 0;
+        for i in 0..number_of_variable_size_data_types {
+            variable_size =
+                variable_size.max(read_u16(self, variable_sizes_offset + i * 2)? & 0x7fff);
+        }
+        let types_offset =
+            variable_sizes_offset + number_of_variable_size_data_types * 2 + variable_size as u64;
+        if types_offset + 4 > record_end {
+            return Ok(vec![]);
+        }
+
+        let large_page = self.format_revision >= ESEDB_FORMAT_REVISION_EXTENDED_PAGE_HEADER
+            && self.page_size >= 16384;
+        let tagged_data_type_offset_bitmask: u16 = if large_page { 0x7fff } else { 0x3fff };
+        let number_of_tagged_data_types =
+            (read_u16(self, types_offset + 2)? & tagged_data_type_offset_bitmask) as u64 / 4;
+        let mut keys = vec![];
+        for i in 0..number_of_tagged_data_types {
+            let type_offset = read_u16(self, types_offset + i * 4 + 2)?;
+            let value_offset =
+                types_offset + (type_offset & tagged_data_type_offset_bitmask) as u64;
+            let value_end = if i + 1 < number_of_tagged_data_types {
+                types_offset
+                    + (read_u16(self, types_offset + i * 4 + 6)? & tagged_data_type_offset_bitmask)
+                        as u64
+            } else {
+                record_end
+            };
+            if value_offset >= value_end || value_end > record_end {
+                continue;
+            }
+            if !large_page && type_offset & 0x4000 == 0 {
+                // no data type flags
+                continue;
+            }
+            let dtf = TaggedDataTypeFlag::from_bits_truncate(read_u8(self, value_offset)? as u16);
+            let value_offset = value_offset + 1;
+            if dtf.intersects(TaggedDataTypeFlag::LONG_VALUE) {
+                keys.push(self.read_lv_key(value_offset)?);
+            } else if dtf.intersects(TaggedDataTypeFlag::MULTI_VALUE_OFFSET) {
+                // two values split by the offset in the first byte, no long values
+                continue;
+            } else if dtf.intersects(TaggedDataTypeFlag::MULTI_VALUE) {
+                // offsets of the values, long values have the highest bit set
+                let number_of_value_entries = (read_u16(self, value_offset)? & 0x7fff) as u64 / 2;
+                for j in 0..number_of_value_entries {
+                    let value = read_u16(self, value_offset + j * 2)?;
+                    if value & 0x8000 != 0 {
+                        keys.push(self.read_lv_key(value_offset + (value & 0x7fff) as u64)?);
+                    }
+                }
+            }
+        }
+        Ok(keys)
+    }
+
     #[allow(clippy::too_many_arguments)]
     pub fn load_data(
         &self,
@@ -1272,6 +2111,52 @@ impl<T: ReadSeek> Reader<T> {
         Ok(tags)
     }
 
+    // reference count and size of the long values, from the roots of the long value tree
+    pub fn load_lv_roots(&self, page_number: u32) -> Result<LV_roots, SimpleError> {
+        let mut roots: LV_roots = HashMap::new();
+        let mut page_number = self.find_first_leaf_page(page_number)?;
+        let mut visited_pages: BTreeSet<u32> = BTreeSet::new();
+        while page_number != 0 {
+            if !visited_pages.insert(page_number) {
+                return Err(SimpleError::new(format!(
+                    "Circular page reference identified for page_number: {}",
+                    page_number
+                )));
+            }
+            let db_page = jet::DbPage::new(self, page_number)?;
+            for i in 1..db_page.tags() {
+                let page_tag = db_page.tag(i)?;
+                if page_tag
+                    .flags()
+                    .intersects(jet::PageTagFlags::FLAG_IS_DEFUNCT)
+                {
+                    continue;
+                }
+                let (key, data_offset) = self.load_page_tag_key(&db_page, page_tag)?;
+                let data_size =
+                    (page_tag.offset(&db_page) + page_tag.size as u64).saturating_sub(data_offset);
+                // a root is keyed by the long value identifier alone (LID32 or LID64),
+                // the chunks by the identifier and their offset
+                let long_value_key = match key.len() {
+                    4 => u32::from_be_bytes(key[..4].try_into().unwrap_or_default()) as u64,
+                    8 if data_size == 8 => {
+                        u64::from_be_bytes(key[..].try_into().unwrap_or_default())
+                    }
+                    _ => continue,
+                };
+                roots.insert(
+                    long_value_key,
+                    LV_root {
+                        reference_count: read_u32(self, data_offset)?,
+                        size: read_u32(self, data_offset + 4)?,
+                    },
+                );
+            }
+            page_number = db_page.next_page();
+        }
+        Ok(roots)
+    }
+
     pub fn load_lv_data(
         &self,
         lv_tags: &LV_tags,
@@ -1321,6 +2206,15 @@ pub struct LV_tag {
 
 pub type LV_tags = HashMap<u64 /*key*/, HashMap<u32 /*seg_offset*/, LV_tag>>;
 
+#[derive(Debug, Clone, Copy)]
+pub struct LV_root {
+    pub reference_count: u32,
+    // size of the long value data (decompressed)
+    pub size: u32,
+}
+
+pub type LV_roots = HashMap<u64 /*key*/, LV_root>;
+
 fn merge_lv_tags(tags: &mut LV_tags, new_tags: LV_tags) {
     for (new_key, new_segs) in new_tags {
         match tags.entry(new_key) {
@@ -1337,6 +2231,97 @@ fn merge_lv_tags(tags: &mut LV_tags, new_tags: LV_tags) {
     }
 }
 
+// Groups the defunct catalog entries by their table, the defunct entries of live tables
+// (dropped columns and indexes) are left out
+fn load_dropped_tables(
+    live_tables: &[jet::TableDefinition],
+    defunct_items: Vec<jet::CatalogDefinition>,
+    format_revision: u32,
+) -> Vec<jet::TableDefinition> {
+    let is_live = |object_identifier: u32| {
+        live_tables.iter().any(|t| {
+            t.table_catalog_definition
+                .as_ref()
+                .is_some_and(|table| table.identifier == object_identifier)
+        })
+    };
+    let mut dropped: Vec<jet::TableDefinition> = vec![];
+    for cat_item in defunct_items.iter() {
+        if cat_item.cat_type == jet::CatalogType::Table as u16
+            && !is_live(cat_item.identifier)
+            && !dropped.iter().any(|t| {
+                t.table_catalog_definition
+                    .as_ref()
+                    .is_some_and(|table| table.identifier == cat_item.identifier)
+            })
+        {
+            dropped.push(jet::TableDefinition {
+                table_catalog_definition: Some(cat_item.clone()),
+                column_catalog_definition_array: vec![],
+                index_definition_array: vec![],
+                long_value_catalog_definition: None,
+                dropped: true,
+            });
+        }
+    }
+    for table_def in dropped.iter_mut() {
+        let object_identifier = table_def
+            .table_catalog_definition
+            .as_ref()
+            .map(|table| table.identifier)
+            .unwrap_or_default();
+        for cat_item in defunct_items
+            .iter()
+            .filter(|c| c.father_data_page_object_identifier == object_identifier)
+        {
+            if cat_item.cat_type == jet::CatalogType::Column as u16 {
+                if !table_def
+                    .column_catalog_definition_array
+                    .iter()
+                    .any(|c| c.identifier == cat_item.identifier)
+                {
+                    table_def
+                        .column_catalog_definition_array
+                        .push(cat_item.clone());
+                }
+            } else if cat_item.cat_type == jet::CatalogType::Index as u16 {
+                if !table_def
+                    .index_definition_array
+                    .iter()
+                    .any(|i| i.identifier == cat_item.identifier)
+                {
+                    table_def
+                        .index_definition_array
+                        .push(jet::IndexDefinition::new(cat_item, format_revision));
+                }
+            } else if cat_item.cat_type == jet::CatalogType::LongValue as u16
+                && table_def.long_value_catalog_definition.is_none()
+            {
+                table_def.long_value_catalog_definition = Some(cat_item.clone());
+            }
+        }
+        // the records are decoded in the column identifier order
+        table_def
+            .column_catalog_definition_array
+            .sort_by_key(|c| c.identifier);
+    }
+    dropped
+}
+
+fn count_live_tags(db_page: &jet::DbPage) -> Result<u64, SimpleError> {
+    let mut count = 0;
+    for i in 1..db_page.tags() {
+        if !db_page
+            .tag(i)?
+            .flags()
+            .intersects(jet::PageTagFlags::FLAG_IS_DEFUNCT)
+        {
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
 #[macro_export]
 macro_rules! impl_read_struct {
     ($struct_type: ident) => {
@@ -1403,7 +2388,6 @@ macro_rules! impl_read_primitive {
 impl_read_primitive!(u8);
 impl_read_primitive!(u16);
 impl_read_primitive!(u32);
-impl_read_primitive!(u64);
 
 #[derive(Copy, Clone, Debug, Default)]
 pub struct TaggedDataState {
@@ -0,0 +1,914 @@
+//replay.rs
+use simple_error::SimpleError;
+use std::collections::btree_map::Entry;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::Path;
+
+use crate::parser::jet;
+use crate::parser::log::{
+    self, LogFile, LogOperation, MergeOperation, NodeOperation, SplitOperation,
+};
+use crate::parser::reader::{ReadSeek, Reader};
+use crate::utils::{calc_crc32, calc_new_crc};
+
+// Result of the replay of the logs of a database
+#[derive(Clone, Debug, Default)]
+pub struct ReplayReport {
+    // generations of the logs replayed, in order
+    pub generations: Vec<u32>,
+    // committed node operations applied to the pages
+    pub applied: usize,
+    // committed node operations already on the pages (page dbtime not older than the operation)
+    pub skipped: usize,
+    // node operations of rolled back or unfinished transactions
+    pub discarded: usize,
+    // pages served from the replayed data
+    pub pages: Vec<u32>,
+    // log files ignored
+    pub errors: Vec<String>,
+    // committed page operations of the records decoded only by their size, with their page:
+    // they are not applied, the pages may be out of date
+    pub unapplied: Vec<(jet::LgPos, u32)>,
+}
+
+impl ReplayReport {
+    // all the committed page operations were applied
+    pub fn is_complete(&self) -> bool {
+        self.unapplied.is_empty()
+    }
+}
+
+// Node of a page: the page tag flags and data
+// (the flags are kept out of the first word of the data of the large pages)
+struct Node {
+    flags: u8,
+    data: Vec<u8>,
+}
+
+// page header offsets of the dbtime, of the object identifier, and of the free space fields
+// (available data size, available uncommitted data size, available data offset and tag count)
+const PAGE_DBTIME_OFFSET: usize = 8;
+const PAGE_PREVIOUS_OFFSET: usize = 16;
+const PAGE_NEXT_OFFSET: usize = 20;
+const PAGE_OBJECT_IDENTIFIER_OFFSET: usize = 24;
+const PAGE_SPACE_OFFSET: usize = 28;
+const PAGE_FLAGS_OFFSET: usize = 36;
+// page number of the extended header of the large pages
+const PAGE_EXT_NUMBER_OFFSET: usize = 64;
+
+// ReplaceD data: differences applied in order to the old data, each a header word (offset in
+// the data in the low 13 bits, with the flags below) followed by the old size (unless the
+// new bytes replace as many old bytes), the new size and the new bytes
+const DIFF_OFFSET_MASK: u16 = 0x1fff;
+const DIFF_SAME_LENGTH: u16 = 0x2000;
+// the sizes take 2 bytes instead of 1
+const DIFF_TWO_BYTE_SIZES: u16 = 0x8000;
+
+fn read_u16_le(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([data[offset], data[offset + 1]])
+}
+
+fn read_u32_le(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ])
+}
+
+fn write_u32_le(data: &mut [u8], offset: usize, value: u32) {
+    data[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+fn page_dbtime(page: &[u8]) -> u64 {
+    u64::from_le_bytes(
+        page[PAGE_DBTIME_OFFSET..PAGE_DBTIME_OFFSET + 8]
+            .try_into()
+            .unwrap_or_default(),
+    )
+}
+
+fn set_page_dbtime(page: &mut [u8], dbtime: u64) {
+    page[PAGE_DBTIME_OFFSET..PAGE_DBTIME_OFFSET + 8].copy_from_slice(&dbtime.to_le_bytes());
+}
+
+// Collects the logs of the directory (edbNNNNN.log, edb.log, .jrs) which belong to the database,
+// by generation; the logs of another database are reported and ignored
+pub(crate) fn load_logs<T: ReadSeek>(
+    reader: &Reader<T>,
+    log_dir: &Path,
+    errors: &mut Vec<String>,
+) -> Result<BTreeMap<u32, LogFile>, SimpleError> {
+    let entries = std::fs::read_dir(log_dir).map_err(|e| {
+        SimpleError::new(format!("can't read {}: {}", log_dir.to_string_lossy(), e))
+    })?;
+    let mut logs = BTreeMap::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let extension = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        if extension != "log" && extension != "jrs" {
+            continue;
+        }
+        let log = match log::load_log_file(&path) {
+            Ok(log) => log,
+            Err(e) => {
+                errors.push(format!("{}: {}", path.to_string_lossy(), e));
+                continue;
+            }
+        };
+        if log.header.log_signature != reader.log_signature() {
+            errors.push(format!(
+                "{}: log signature doesn't match the database",
+                path.to_string_lossy()
+            ));
+            continue;
+        }
+        if logs.contains_key(&log.header.generation) {
+            errors.push(format!(
+                "{}: generation {} already loaded",
+                path.to_string_lossy(),
+                log.header.generation
+            ));
+            continue;
+        }
+        logs.insert(log.header.generation, log);
+    }
+    Ok(logs)
+}
+
+// Positions of the committed page operations: the operations of a session are kept
+// by transaction level until the commit to level 0, or dropped by a rollback;
+// the operations of a session whose transaction began before the first log are kept
+// like those of a transaction begun at level 1; the discarded operations are counted
+pub(crate) fn committed_operations(logs: &[LogFile]) -> (BTreeSet<jet::LgPos>, usize) {
+    let mut discarded = 0;
+    let mut sessions: HashMap<u16, Vec<Vec<jet::LgPos>>> = HashMap::new();
+    let mut committed = BTreeSet::new();
+    for record in logs.iter().flat_map(|log| log.records.iter()) {
+        let levels = sessions.entry(record.procid).or_default();
+        match &record.operation {
+            LogOperation::BeginTransaction { level, .. } => {
+                levels.push(vec![]);
+                while levels.len() < *level as usize {
+                    levels.push(vec![]);
+                }
+            }
+            LogOperation::CommitTransaction { level, .. } => {
+                while let Some(operations) = levels.pop() {
+                    match levels.last_mut() {
+                        Some(outer) => outer.extend(operations),
+                        None => committed.extend(operations),
+                    }
+                    if levels.len() <= *level as usize {
+                        break;
+                    }
+                }
+            }
+            LogOperation::RollbackTransaction { level } => {
+                while let Some(operations) = levels.pop() {
+                    discarded += operations.len();
+                    if levels.len() <= *level as usize {
+                        break;
+                    }
+                }
+            }
+            operation if operation.page_number().is_some() => {
+                if levels.is_empty() {
+                    levels.push(vec![]);
+                }
+                if let Some(operations) = levels.last_mut() {
+                    operations.push(record.position);
+                }
+            }
+            _ => {}
+        }
+    }
+    discarded += sessions
+        .values()
+        .flat_map(|levels| levels.iter())
+        .map(|operations| operations.len())
+        .sum::<usize>();
+    (committed, discarded)
+}
+
+// Replays the committed operations of the logs of the directory over the pages of the database,
+// from the lowest generation required by a dirty database (or the first log) up to the last log
+// of the sequence; fails if a log of the sequence has records which can't be decoded, or if
+// a committed operation can't be applied to its pages; the pages are rebuilt in memory, the
+// files are not modified
+pub fn replay_logs<T: ReadSeek>(
+    reader: &Reader<T>,
+    log_dir: impl AsRef<Path>,
+) -> Result<ReplayReport, SimpleError> {
+    let mut report = ReplayReport::default();
+    let mut logs = load_logs(reader, log_dir.as_ref(), &mut report.errors)?;
+
+    // a clean database keeps the required generations of its last attachment
+    let (min_required, max_required) = if reader.is_dirty() {
+        reader.required_log_generations()
+    } else {
+        (0, 0)
+    };
+    let first = if min_required > 0 {
+        min_required
+    } else {
+        match logs.keys().next() {
+            Some(generation) => *generation,
+            None => return Ok(report),
+        }
+    };
+    let mut sequence = vec![];
+    let mut generation = first;
+    while let Some(log) = logs.remove(&generation) {
+        report.generations.push(generation);
+        sequence.push(log);
+        generation += 1;
+    }
+    if generation <= max_required || (min_required > 0 && sequence.is_empty()) {
+        return Err(SimpleError::new(format!(
+            "required log generation {} is missing (required {} to {})",
+            generation, min_required, max_required
+        )));
+    }
+    if !logs.is_empty() {
+        report.errors.push(format!(
+            "log generations {:?} are out of the sequence {} to {}",
+            logs.keys().collect::<Vec<_>>(),
+            first,
+            generation - 1
+        ));
+    }
+    // the operations of the records left undecoded would be missing from the pages
+    if let Some((log, truncated)) = sequence
+        .iter()
+        .find_map(|log| log.truncated.first().map(|truncated| (log, truncated)))
+    {
+        return Err(SimpleError::new(format!(
+            "log generation {} is not completely decoded, at log position {:?}: {}",
+            { log.header.generation },
+            truncated.position,
+            truncated.error
+        )));
+    }
+
+    let (committed, discarded) = committed_operations(&sequence);
+    report.discarded = discarded;
+    let mut pages = BTreeMap::new();
+    for record in sequence.iter().flat_map(|log| log.records.iter()) {
+        if !committed.contains(&record.position) {
+            continue;
+        }
+        if let LogOperation::Other {
+            page_number: Some(page_number),
+        } = record.operation
+        {
+            report.unapplied.push((record.position, page_number));
+            continue;
+        }
+        let Some(node) = record.operation.node() else {
+            continue;
+        };
+        match replay_operation(reader, &mut pages, &record.operation, node) {
+            Ok(true) => report.applied += 1,
+            Ok(false) => report.skipped += 1,
+            Err(e) => {
+                return Err(SimpleError::new(format!(
+                    "log position {:?}, page {}: {}",
+                    record.position, node.page_number, e
+                )))
+            }
+        }
+    }
+    for (page_number, page) in pages.iter_mut() {
+        update_checksums(page, *page_number, reader.page_size() > 8 * 1024)?;
+    }
+    report.pages = pages.keys().cloned().collect();
+    reader.set_replayed_pages(pages);
+    Ok(report)
+}
+
+// Applies a page operation, returns false if the page already has it
+fn replay_operation<T: ReadSeek>(
+    reader: &Reader<T>,
+    pages: &mut BTreeMap<u32, Vec<u8>>,
+    operation: &LogOperation,
+    node: &NodeOperation,
+) -> Result<bool, SimpleError> {
+    let page_size = reader.page_size() as usize;
+    // the page may be new to the replay, its header isn't read from the file
+    let (header_size, large_page) = reader.page_header_size();
+    match operation {
+        LogOperation::Split(split) => {
+            return replay_split(reader, pages, header_size, large_page, split)
+        }
+        LogOperation::Merge(merge) => {
+            return replay_merge(reader, pages, header_size, large_page, merge)
+        }
+        _ => {}
+    }
+    let page = replay_page(reader, pages, node.page_number)?;
+
+    if page_dbtime(page) >= node.dbtime_after {
+        return Ok(false);
+    }
+    if let LogOperation::PageImage(_) = operation {
+        if node.data.len() != page_size {
+            return Err(SimpleError::new(format!(
+                "page image of {} bytes, the page size is {}",
+                node.data.len(),
+                page_size
+            )));
+        }
+        page.copy_from_slice(&node.data);
+        set_page_dbtime(page, node.dbtime_after);
+        return Ok(true);
+    }
+    check_object(page, node.object_identifier)?;
+
+    let mut nodes = load_nodes(page, header_size, large_page)?;
+    let index = node.iline as usize + 1;
+    let node_count = nodes.len().saturating_sub(1);
+    let out_of_page = || {
+        SimpleError::new(format!(
+            "node {} is out of the page nodes ({})",
+            node.iline, node_count
+        ))
+    };
+    let defunct = jet::PageTagFlags::FLAG_IS_DEFUNCT.bits();
+    match operation {
+        LogOperation::Insert(_) => {
+            if index > nodes.len() {
+                return Err(out_of_page());
+            }
+            let mut data = vec![];
+            let mut flags = 0;
+            if node.key_prefix_size > 0 {
+                flags |= jet::PageTagFlags::FLAG_HAS_COMMON_KEY_SIZE.bits();
+                data.extend_from_slice(&node.key_prefix_size.to_le_bytes());
+            }
+            data.extend_from_slice(&(node.key.len() as u16).to_le_bytes());
+            data.extend_from_slice(&node.key);
+            data.extend_from_slice(&node.data);
+            nodes.insert(index, Node { flags, data });
+        }
+        LogOperation::FlagInsert(_) => {
+            nodes.get_mut(index).ok_or_else(out_of_page)?.flags &= !defunct;
+        }
+        LogOperation::FlagInsertAndReplaceData(_) | LogOperation::Replace(_) => {
+            let n = nodes.get_mut(index).ok_or_else(out_of_page)?;
+            let key_size = node_key_size(n);
+            if key_size > n.data.len() {
+                return Err(SimpleError::new(format!(
+                    "key of {} bytes is out of the node data",
+                    key_size
+                )));
+            }
+            n.data.truncate(key_size);
+            n.data.extend_from_slice(&node.data);
+            n.flags &= !defunct;
+        }
+        LogOperation::ReplaceDiff(_) => {
+            let n = nodes.get_mut(index).ok_or_else(out_of_page)?;
+            let key_size = node_key_size(n);
+            let old = n.data.get(key_size..).ok_or_else(|| {
+                SimpleError::new(format!("key of {} bytes is out of the node data", key_size))
+            })?;
+            if old.len() != node.old_data_size as usize {
+                return Err(SimpleError::new(format!(
+                    "node data of {} bytes, the operation expects {}",
+                    old.len(),
+                    node.old_data_size
+                )));
+            }
+            let data = apply_diffs(old, &node.data)?;
+            n.data.truncate(key_size);
+            n.data.extend_from_slice(&data);
+            n.flags &= !defunct;
+        }
+        LogOperation::FlagDelete(_) => {
+            nodes.get_mut(index).ok_or_else(out_of_page)?.flags |= defunct;
+        }
+        LogOperation::Delete(_) => {
+            if index >= nodes.len() {
+                return Err(out_of_page());
+            }
+            nodes.remove(index);
+        }
+        _ => {
+            return Err(SimpleError::new(format!(
+                "record type {:?} can't be replayed",
+                operation
+            )))
+        }
+    }
+    store_nodes(page, header_size, large_page, &nodes)?;
+    set_page_dbtime(page, node.dbtime_after);
+    Ok(true)
+}
+
+// Applies a split: the nodes of the page from iline_split move to the new page, linked
+// between the page and its right page, and the parent gets the separator key of the page
+// with the node of the page repointed to the new page; a vertical split moves all the nodes
+// of the root to the new page, and the root then points to it
+fn replay_split<T: ReadSeek>(
+    reader: &Reader<T>,
+    pages: &mut BTreeMap<u32, Vec<u8>>,
+    header_size: usize,
+    large_page: bool,
+    split: &SplitOperation,
+) -> Result<bool, SimpleError> {
+    let node = &split.node;
+    let page = replay_page(reader, pages, node.page_number)?;
+    if page_dbtime(page) >= node.dbtime_after {
+        return Ok(false);
+    }
+    check_object(page, node.object_identifier)?;
+    let root = page_flags(page).contains(jet::PageFlags::IS_ROOT);
+    let mut nodes = load_nodes(page, header_size, large_page)?;
+    let first = match split.split_type {
+        log::SPLIT_TYPE_VERTICAL => 1,
+        log::SPLIT_TYPE_RIGHT | log::SPLIT_TYPE_APPEND => split.iline_split as usize + 1,
+        split_type => {
+            return Err(SimpleError::new(format!(
+                "split type {} can't be replayed",
+                split_type
+            )))
+        }
+    };
+    if first > nodes.len() || nodes.is_empty() {
+        return Err(SimpleError::new(format!(
+            "split node {} is out of the page nodes ({})",
+            split.iline_split,
+            nodes.len().saturating_sub(1)
+        )));
+    }
+    let old_prefix = node_prefix(&nodes, root);
+    let moved = nodes
+        .split_off(first)
+        .into_iter()
+        .map(|n| recompress_node(&n, &old_prefix, &split.new_page_prefix))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if split.split_type == log::SPLIT_TYPE_VERTICAL {
+        // the root keeps its header and a node of an empty key for the new page
+        nodes.push(key_node(0, &[], &[], &split.new_page_number.to_le_bytes()));
+    } else {
+        let mut kept = vec![Node {
+            flags: nodes[0].flags,
+            data: split.split_page_prefix.clone(),
+        }];
+        for n in &nodes[1..] {
+            kept.push(recompress_node(n, &old_prefix, &split.split_page_prefix)?);
+        }
+        nodes = kept;
+    }
+    store_nodes(page, header_size, large_page, &nodes)?;
+    set_page_flags(page, split.split_page_flags);
+    let (previous, next) = match split.split_type {
+        log::SPLIT_TYPE_VERTICAL => (0, 0),
+        _ => {
+            write_u32_le(page, PAGE_NEXT_OFFSET, split.new_page_number);
+            (node.page_number, split.right_page_number)
+        }
+    };
+    set_page_dbtime(page, node.dbtime_after);
+
+    // the new page gets the header of the split page
+    let header = page[..header_size].to_vec();
+    let new_page = replay_page(reader, pages, split.new_page_number)?;
+    new_page[..header_size].copy_from_slice(&header);
+    write_u32_le(new_page, PAGE_PREVIOUS_OFFSET, previous);
+    write_u32_le(new_page, PAGE_NEXT_OFFSET, next);
+    if large_page {
+        new_page[PAGE_EXT_NUMBER_OFFSET..PAGE_EXT_NUMBER_OFFSET + 8]
+            .copy_from_slice(&(split.new_page_number as u64).to_le_bytes());
+    }
+    let mut new_nodes = vec![Node {
+        flags: 0,
+        data: split.new_page_prefix.clone(),
+    }];
+    new_nodes.extend(moved);
+    store_nodes(new_page, header_size, large_page, &new_nodes)?;
+    set_page_flags(new_page, split.new_page_flags);
+    if split.split_type == log::SPLIT_TYPE_VERTICAL {
+        return Ok(true);
+    }
+
+    if split.right_page_number != 0 {
+        let right = replay_page(reader, pages, split.right_page_number)?;
+        write_u32_le(right, PAGE_PREVIOUS_OFFSET, split.new_page_number);
+        set_page_dbtime(right, node.dbtime_after);
+    }
+    let parent = replay_page(reader, pages, split.parent_page_number)?;
+    let parent_root = page_flags(parent).contains(jet::PageFlags::IS_ROOT);
+    let mut parent_nodes = load_nodes(parent, header_size, large_page)?;
+    let index = child_node(&parent_nodes, node.page_number, split.parent_page_number)?;
+    let prefix = node_prefix(&parent_nodes, parent_root);
+    set_child(&mut parent_nodes[index], split.new_page_number)?;
+    let separator = compress_key(&split.parent_key, &prefix);
+    parent_nodes.insert(
+        index,
+        key_node(
+            0,
+            &separator.0,
+            &separator.1,
+            &node.page_number.to_le_bytes(),
+        ),
+    );
+    store_nodes(parent, header_size, large_page, &parent_nodes)?;
+    set_page_dbtime(parent, node.dbtime_after);
+    Ok(true)
+}
+
+// Applies a merge: the nodes of the page from iline_merge move to the start of its right page;
+// an empty or fully moved page is unlinked from its siblings, emptied, and its node of the
+// parent deleted, else the node of the parent gets the new separator key of the page
+fn replay_merge<T: ReadSeek>(
+    reader: &Reader<T>,
+    pages: &mut BTreeMap<u32, Vec<u8>>,
+    header_size: usize,
+    large_page: bool,
+    merge: &MergeOperation,
+) -> Result<bool, SimpleError> {
+    let node = &merge.node;
+    let page = replay_page(reader, pages, node.page_number)?;
+    if page_dbtime(page) >= node.dbtime_after {
+        return Ok(false);
+    }
+    check_object(page, node.object_identifier)?;
+    let first = match merge.merge_type {
+        log::MERGE_TYPE_EMPTY_PAGE | log::MERGE_TYPE_FULL_RIGHT => 1,
+        log::MERGE_TYPE_PARTIAL_RIGHT => merge.iline_merge as usize + 1,
+        merge_type => {
+            return Err(SimpleError::new(format!(
+                "merge type {} can't be replayed",
+                merge_type
+            )))
+        }
+    };
+    let mut nodes = load_nodes(page, header_size, large_page)?;
+    if first > nodes.len() || nodes.is_empty() {
+        return Err(SimpleError::new(format!(
+            "merge node {} is out of the page nodes ({})",
+            merge.iline_merge,
+            nodes.len().saturating_sub(1)
+        )));
+    }
+    let prefix = node_prefix(&nodes, false);
+    let moved = nodes.split_off(first);
+    let unlinked = merge.merge_type != log::MERGE_TYPE_PARTIAL_RIGHT;
+    store_nodes(page, header_size, large_page, &nodes)?;
+    if unlinked {
+        let flags = page_flags(page) | jet::PageFlags::IS_EMPTY;
+        set_page_flags(page, flags.bits());
+    }
+    set_page_dbtime(page, node.dbtime_after);
+
+    if merge.right_page_number != 0 {
+        let right = replay_page(reader, pages, merge.right_page_number)?;
+        let mut right_nodes = load_nodes(right, header_size, large_page)?;
+        if right_nodes.is_empty() && !moved.is_empty() {
+            return Err(SimpleError::new(format!(
+                "right page {} has no nodes",
+                merge.right_page_number
+            )));
+        }
+        let right_prefix = node_prefix(&right_nodes, false);
+        let moved = moved
+            .iter()
+            .map(|n| recompress_node(n, &prefix, &right_prefix))
+            .collect::<Result<Vec<_>, _>>()?;
+        right_nodes.splice(1..1, moved);
+        store_nodes(right, header_size, large_page, &right_nodes)?;
+        if unlinked {
+            write_u32_le(right, PAGE_PREVIOUS_OFFSET, merge.left_page_number);
+        }
+        set_page_dbtime(right, node.dbtime_after);
+    } else if !moved.is_empty() {
+        return Err(SimpleError::new("nodes merged into no right page"));
+    }
+    if unlinked && merge.left_page_number != 0 {
+        let left = replay_page(reader, pages, merge.left_page_number)?;
+        write_u32_le(left, PAGE_NEXT_OFFSET, merge.right_page_number);
+        set_page_dbtime(left, node.dbtime_after);
+    }
+
+    let parent = replay_page(reader, pages, merge.parent_page_number)?;
+    let parent_root = page_flags(parent).contains(jet::PageFlags::IS_ROOT);
+    let mut parent_nodes = load_nodes(parent, header_size, large_page)?;
+    let index = merge.iline_parent as usize + 1;
+    if parent_nodes.get(index).and_then(node_child) != Some(node.page_number) {
+        return Err(SimpleError::new(format!(
+            "node {} of the parent page {} doesn't point to the page",
+            merge.iline_parent, merge.parent_page_number
+        )));
+    }
+    if unlinked {
+        parent_nodes.remove(index);
+    } else {
+        let prefix = node_prefix(&parent_nodes, parent_root);
+        let (common, local) = compress_key(&merge.parent_key, &prefix);
+        let n = &parent_nodes[index];
+        let flags = n.flags & !jet::PageTagFlags::FLAG_HAS_COMMON_KEY_SIZE.bits();
+        let data = n.data[node_key_size(n)..].to_vec();
+        parent_nodes[index] = key_node(flags, &common, &local, &data);
+    }
+    store_nodes(parent, header_size, large_page, &parent_nodes)?;
+    set_page_dbtime(parent, node.dbtime_after);
+    Ok(true)
+}
+
+fn page_flags(page: &[u8]) -> jet::PageFlags {
+    jet::PageFlags::from_bits_truncate(read_u32_le(page, PAGE_FLAGS_OFFSET))
+}
+
+fn set_page_flags(page: &mut [u8], flags: u32) {
+    write_u32_le(page, PAGE_FLAGS_OFFSET, flags);
+}
+
+fn check_object(page: &[u8], object_identifier: u32) -> Result<(), SimpleError> {
+    let page_object_identifier = read_u32_le(page, PAGE_OBJECT_IDENTIFIER_OFFSET);
+    if page_object_identifier != object_identifier {
+        return Err(SimpleError::new(format!(
+            "page belongs to object {}, the operation to object {}",
+            page_object_identifier, object_identifier
+        )));
+    }
+    Ok(())
+}
+
+// the key prefix of the page nodes: the data of the node 0, the root header on root pages
+fn node_prefix(nodes: &[Node], root: bool) -> Vec<u8> {
+    match nodes.first() {
+        Some(n) if !root => n.data.clone(),
+        _ => vec![],
+    }
+}
+
+// the full key of a node, with its common part taken from the page key prefix
+fn node_key(node: &Node, prefix: &[u8]) -> Result<Vec<u8>, SimpleError> {
+    let key_size = node_key_size(node);
+    let (common, local) = match node.flags & jet::PageTagFlags::FLAG_HAS_COMMON_KEY_SIZE.bits() {
+        0 => (0, 2..key_size.min(node.data.len())),
+        _ if node.data.len() >= 2 => (
+            read_u16_le(&node.data, 0) as usize,
+            4..key_size.min(node.data.len()),
+        ),
+        _ => (usize::MAX, 0..0),
+    };
+    if common > prefix.len() || key_size > node.data.len() {
+        return Err(SimpleError::new(format!(
+            "node key of {} bytes is out of the node data, or its common key of the page prefix",
+            key_size
+        )));
+    }
+    let mut key = prefix[..common].to_vec();
+    key.extend_from_slice(&node.data[local]);
+    Ok(key)
+}
+
+// the part of the key shared with the page key prefix, and the rest
+fn compress_key(key: &[u8], prefix: &[u8]) -> (Vec<u8>, Vec<u8>) {
+    let common = key.iter().zip(prefix).take_while(|(a, b)| a == b).count();
+    (key[..common].to_vec(), key[common..].to_vec())
+}
+
+// a node of the key (common part and local part) and data
+fn key_node(flags: u8, common: &[u8], local: &[u8], data: &[u8]) -> Node {
+    let mut node = Node {
+        flags,
+        data: vec![],
+    };
+    if !common.is_empty() {
+        node.flags |= jet::PageTagFlags::FLAG_HAS_COMMON_KEY_SIZE.bits();
+        node.data
+            .extend_from_slice(&(common.len() as u16).to_le_bytes());
+    }
+    node.data
+        .extend_from_slice(&(local.len() as u16).to_le_bytes());
+    node.data.extend_from_slice(local);
+    node.data.extend_from_slice(data);
+    node
+}
+
+// the node moved to a page of another key prefix
+fn recompress_node(node: &Node, old_prefix: &[u8], new_prefix: &[u8]) -> Result<Node, SimpleError> {
+    let key = node_key(node, old_prefix)?;
+    let (common, local) = compress_key(&key, new_prefix);
+    let flags = node.flags & !jet::PageTagFlags::FLAG_HAS_COMMON_KEY_SIZE.bits();
+    Ok(key_node(
+        flags,
+        &common,
+        &local,
+        &node.data[node_key_size(node)..],
+    ))
+}
+
+// the index of the node of a parent page which points to the child page
+fn child_node(nodes: &[Node], child: u32, parent_page_number: u32) -> Result<usize, SimpleError> {
+    nodes
+        .iter()
+        .enumerate()
+        .skip(1)
+        .find(|(_, n)| node_child(n) == Some(child))
+        .map(|(i, _)| i)
+        .ok_or_else(|| {
+            SimpleError::new(format!(
+                "parent page {} has no node of the page {}",
+                parent_page_number, child
+            ))
+        })
+}
+
+// the child page a node of a parent page points to
+fn node_child(node: &Node) -> Option<u32> {
+    let key_size = node_key_size(node);
+    match node.data.len() >= key_size.saturating_add(4) {
+        true => Some(read_u32_le(&node.data, key_size)),
+        false => None,
+    }
+}
+
+fn set_child(node: &mut Node, child: u32) -> Result<(), SimpleError> {
+    let key_size = node_key_size(node);
+    if node.data.len() < key_size.saturating_add(4) {
+        return Err(SimpleError::new("parent node has no child page"));
+    }
+    write_u32_le(&mut node.data, key_size, child);
+    Ok(())
+}
+
+// a page of the replay, read from the file on its first use
+fn replay_page<'a, T: ReadSeek>(
+    reader: &Reader<T>,
+    pages: &'a mut BTreeMap<u32, Vec<u8>>,
+    page_number: u32,
+) -> Result<&'a mut Vec<u8>, SimpleError> {
+    Ok(match pages.entry(page_number) {
+        Entry::Occupied(entry) => entry.into_mut(),
+        Entry::Vacant(entry) => {
+            let page_size = reader.page_size() as usize;
+            let offset = (page_number as u64 + 1) * page_size as u64;
+            entry.insert(reader.read_bytes(offset, page_size)?)
+        }
+    })
+}
+
+fn apply_diffs(old: &[u8], diffs: &[u8]) -> Result<Vec<u8>, SimpleError> {
+    let mut data = old.to_vec();
+    let mut pos = 0;
+    let truncated = || SimpleError::new("difference is out of the record data");
+    while pos < diffs.len() {
+        let header = u16::from_le_bytes([diffs[pos], *diffs.get(pos + 1).ok_or_else(truncated)?]);
+        pos += 2;
+        let mut size = || -> Result<usize, SimpleError> {
+            let size = if header & DIFF_TWO_BYTE_SIZES != 0 {
+                let b = diffs.get(pos..pos + 2).ok_or_else(truncated)?;
+                pos += 2;
+                u16::from_le_bytes([b[0], b[1]]) as usize
+            } else {
+                pos += 1;
+                *diffs.get(pos - 1).ok_or_else(truncated)? as usize
+            };
+            Ok(size)
+        };
+        let old_size = match header & DIFF_SAME_LENGTH {
+            0 => Some(size()?),
+            _ => None,
+        };
+        let new_size = size()?;
+        let offset = (header & DIFF_OFFSET_MASK) as usize;
+        let end = offset + old_size.unwrap_or(new_size);
+        let new = diffs.get(pos..pos + new_size).ok_or_else(truncated)?;
+        if end > data.len() {
+            return Err(truncated());
+        }
+        data.splice(offset..end, new.iter().copied());
+        pos += new_size;
+    }
+    Ok(data)
+}
+
+// the checksums of a page (of each block of the large new record format pages)
+fn update_checksums(
+    page: &mut [u8],
+    page_number: u32,
+    large_page: bool,
+) -> Result<(), SimpleError> {
+    if !page_flags(page).contains(jet::PageFlags::IS_NEW_RECORD_FORMAT) {
+        let checksum = ((page_number as u64) << 32) | calc_crc32(page) as u64;
+        page[..8].copy_from_slice(&checksum.to_le_bytes());
+        return Ok(());
+    }
+    let blocks = if large_page { 4 } else { 1 };
+    let block_len = page.len() / blocks;
+    // the first block holds the checksums of the others
+    for block in (0..blocks).rev() {
+        let checksum_offset = if block == 0 { 0 } else { 32 + 8 * block };
+        let checksum = calc_new_crc(
+            &page[block * block_len..(block + 1) * block_len],
+            page_number,
+            block == 0,
+        )?;
+        page[checksum_offset..checksum_offset + 8].copy_from_slice(&checksum.to_le_bytes());
+    }
+    Ok(())
+}
+
+// size of the key part of the node data (common key size, local key size and local key)
+fn node_key_size(node: &Node) -> usize {
+    let mut size = 0;
+    if node.flags & jet::PageTagFlags::FLAG_HAS_COMMON_KEY_SIZE.bits() != 0 {
+        size += 2;
+    }
+    if node.data.len() < size + 2 {
+        return usize::MAX;
+    }
+    size + 2 + read_u16_le(&node.data, size) as usize
+}
+
+fn load_nodes(page: &[u8], header_size: usize, large_page: bool) -> Result<Vec<Node>, SimpleError> {
+    let page_size = page.len();
+    let tags = read_u16_le(page, PAGE_SPACE_OFFSET + 6) as usize;
+    let mut nodes = Vec::with_capacity(tags);
+    for i in 0..tags {
+        let mut offset = read_u16_le(page, page_size - 4 * i - 2);
+        let mut size = read_u16_le(page, page_size - 4 * i - 4);
+        let mut flags = 0;
+        if large_page {
+            offset &= 0x7fff;
+            size &= 0x7fff;
+        } else {
+            flags = (offset >> 13) as u8;
+            offset &= 0x1fff;
+            size &= 0x1fff;
+        }
+        let start = header_size + offset as usize;
+        let mut data = page
+            .get(start..start + size as usize)
+            .ok_or_else(|| {
+                SimpleError::new(format!(
+                    "tag {} (offset {}, size {}) is out of the page",
+                    i, offset, size
+                ))
+            })?
+            .to_vec();
+        // the upper 3 bits of the first word of the data hold the tag flags
+        if large_page && data.len() >= 2 {
+            flags = data[1] >> 5;
+            data[1] &= 0x1f;
+        }
+        nodes.push(Node { flags, data });
+    }
+    Ok(nodes)
+}
+
+// lays out the node data from the end of the header and the tags from the end of the page,
+// and updates the free space of the page header
+fn store_nodes(
+    page: &mut [u8],
+    header_size: usize,
+    large_page: bool,
+    nodes: &[Node],
+) -> Result<(), SimpleError> {
+    let page_size = page.len();
+    let data_size: usize = nodes.iter().map(|n| n.data.len()).sum();
+    if header_size + data_size + 4 * nodes.len() > page_size {
+        return Err(SimpleError::new(format!(
+            "{} nodes of {} bytes don't fit in the page",
+            nodes.len(),
+            data_size
+        )));
+    }
+
+    page[header_size..].fill(0);
+    let mut offset = 0;
+    for (i, node) in nodes.iter().enumerate() {
+        let start = header_size + offset;
+        page[start..start + node.data.len()].copy_from_slice(&node.data);
+        let mut offset_word = offset as u16;
+        if large_page {
+            if node.data.len() >= 2 {
+                page[start + 1] |= node.flags << 5;
+            }
+        } else {
+            offset_word |= (node.flags as u16) << 13;
+        }
+        let tag = page_size - 4 * i - 4;
+        page[tag..tag + 2].copy_from_slice(&(node.data.len() as u16).to_le_bytes());
+        page[tag + 2..tag + 4].copy_from_slice(&offset_word.to_le_bytes());
+        offset += node.data.len();
+    }
+
+    let available = page_size - header_size - data_size - 4 * nodes.len();
+    let space = PAGE_SPACE_OFFSET;
+    page[space..space + 2].copy_from_slice(&(available as u16).to_le_bytes());
+    page[space + 2..space + 4].copy_from_slice(&0u16.to_le_bytes());
+    page[space + 4..space + 6].copy_from_slice(&(data_size as u16).to_le_bytes());
+    page[space + 6..space + 8].copy_from_slice(&(nodes.len() as u16).to_le_bytes());
+    Ok(())
+}
@@ -0,0 +1,346 @@
+//space.rs
+use simple_error::SimpleError;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::parser::jet;
+use crate::parser::reader::{read_u32, ReadSeek, Reader};
+
+// Space of an object is either a single extent, described by the root page header
+// (with a bitmap of its available pages), or owned (OE) and available (AE) extent trees
+const SPACE_FLAG_MULTIPLE_EXTENT: u32 = 0x00000001;
+
+// The available-extent tree root follows the owned-extent tree root
+const AVAILABLE_EXTENT_TREE_OFFSET: u32 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Extent {
+    pub first_page: u32,
+    pub page_count: u32,
+}
+
+impl Extent {
+    pub fn last_page(&self) -> u32 {
+        self.first_page + self.page_count - 1
+    }
+
+    pub fn contains(&self, page_number: u32) -> bool {
+        page_number >= self.first_page && page_number <= self.last_page()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SpaceObjectType {
+    Database,
+    Table,
+    Index,
+    LongValue,
+}
+
+#[derive(Clone, Debug)]
+pub struct ObjectSpace {
+    pub object_type: SpaceObjectType,
+    pub object_identifier: u32,
+    // table of the object (empty for the database)
+    pub table: String,
+    // catalog name of the object (table, index or long value tree)
+    pub name: String,
+    pub father_data_page_number: u32,
+    // father data page of the object the space was allocated from
+    pub parent_father_data_page_number: u32,
+    // extent tree root pages, None for single extent space
+    pub space_tree_page_numbers: Option<(u32, u32)>,
+    pub owned: Vec<Extent>,
+    pub available: Vec<Extent>,
+}
+
+impl ObjectSpace {
+    pub fn owns(&self, page_number: u32) -> bool {
+        self.owned.iter().any(|e| e.contains(page_number))
+    }
+
+    pub fn is_available(&self, page_number: u32) -> bool {
+        self.available.iter().any(|e| e.contains(page_number))
+    }
+}
+
+// Extent tree leaf entries: the key is the big-endian last page number of the extent,
+// the data starts with the little-endian number of its pages
+fn load_extent_tree<T: ReadSeek>(
+    reader: &Reader<T>,
+    page_number: u32,
+) -> Result<Vec<Extent>, SimpleError> {
+    let mut extents: Vec<Extent> = vec![];
+    let mut db_page = jet::DbPage::new(reader, reader.find_first_leaf_page(page_number)?)?;
+    let mut visited_pages: BTreeSet<u32> = BTreeSet::new();
+    loop {
+        if !db_page.flags().contains(jet::PageFlags::IS_SPACE_TREE) {
+            return Err(SimpleError::new(format!(
+                "page {} is not a space tree page, page_flags 0x{:?}",
+                db_page.page_number,
+                db_page.flags()
+            )));
+        }
+        visited_pages.insert(db_page.page_number);
+        for i in 1..db_page.tags() {
+            let page_tag = db_page.tag(i)?;
+            if page_tag
+                .flags()
+                .intersects(jet::PageTagFlags::FLAG_IS_DEFUNCT)
+            {
+                continue;
+            }
+            let (key, data_offset) = reader.load_page_tag_key(&db_page, page_tag)?;
+            if key.len() < 4 {
+                return Err(SimpleError::new(format!(
+                    "page {}: wrong extent key size {}",
+                    db_page.page_number,
+                    key.len()
+                )));
+            }
+            let last_page = u32::from_be_bytes(key[key.len() - 4..].try_into().unwrap());
+            let page_count = read_u32(reader, data_offset)?;
+            if page_count == 0 {
+                continue;
+            }
+            if page_count > last_page {
+                return Err(SimpleError::new(format!(
+                    "page {}: extent of {} pages ending at page {}",
+                    db_page.page_number, page_count, last_page
+                )));
+            }
+            extents.push(Extent {
+                first_page: last_page - page_count + 1,
+                page_count,
+            });
+        }
+
+        let next_page = db_page.common().next_page;
+        if next_page == 0 {
+            break;
+        }
+        if visited_pages.contains(&next_page) {
+            return Err(SimpleError::new(format!(
+                "Circular page reference identified for page_number: {}",
+                next_page
+            )));
+        }
+        db_page = jet::DbPage::new(reader, next_page)?;
+    }
+    extents.sort();
+    Ok(extents)
+}
+
+pub(crate) fn load_object_space<T: ReadSeek>(
+    reader: &Reader<T>,
+    object_type: SpaceObjectType,
+    object_identifier: u32,
+    table: &str,
+    name: &str,
+    father_data_page_number: u32,
+) -> Result<ObjectSpace, SimpleError> {
+    let db_page = jet::DbPage::new(reader, father_data_page_number)?;
+    if !db_page.flags().contains(jet::PageFlags::IS_ROOT) {
+        return Err(SimpleError::new(format!(
+            "{} {}: father data page {} is not a root page, page_flags 0x{:?}",
+            table,
+            name,
+            father_data_page_number,
+            db_page.flags()
+        )));
+    }
+    let root_page_header = reader.load_root_page_header(&db_page, db_page.tag(0)?)?;
+    let mut space = ObjectSpace {
+        object_type,
+        object_identifier,
+        table: table.to_string(),
+        name: name.to_string(),
+        father_data_page_number,
+        parent_father_data_page_number: root_page_header.parent_fdp(),
+        space_tree_page_numbers: None,
+        owned: vec![],
+        available: vec![],
+    };
+
+    if root_page_header.extent_space() & SPACE_FLAG_MULTIPLE_EXTENT != 0 {
+        let owned_extent_tree = root_page_header.space_tree_page_number();
+        let available_extent_tree = owned_extent_tree + AVAILABLE_EXTENT_TREE_OFFSET;
+        space.space_tree_page_numbers = Some((owned_extent_tree, available_extent_tree));
+        space.owned = load_extent_tree(reader, owned_extent_tree)?;
+        space.available = load_extent_tree(reader, available_extent_tree)?;
+    } else {
+        let page_count = root_page_header.initial_number_of_pages();
+        if page_count > 0 {
+            space.owned.push(Extent {
+                first_page: father_data_page_number,
+                page_count,
+            });
+        }
+        // the field of the extent tree page number holds the bitmap of the available pages
+        // following the father data page
+        let available_bitmap = root_page_header.space_tree_page_number();
+        for bit in 0..u32::BITS.min(page_count.saturating_sub(1)) {
+            if available_bitmap & (1 << bit) != 0 {
+                let page_number = father_data_page_number + 1 + bit;
+                match space.available.last_mut() {
+                    Some(e) if e.last_page() + 1 == page_number => e.page_count += 1,
+                    _ => space.available.push(Extent {
+                        first_page: page_number,
+                        page_count: 1,
+                    }),
+                }
+            }
+        }
+    }
+    Ok(space)
+}
+
+// Loads the owned and available extents of the database and of every table, index
+// and long value tree of the catalog
+pub fn load_space<T: ReadSeek>(reader: &Reader<T>) -> Result<Vec<ObjectSpace>, SimpleError> {
+    let mut spaces = vec![load_object_space(
+        reader,
+        SpaceObjectType::Database,
+        jet::FixedPageNumber::Database as u32,
+        "",
+        "",
+        jet::FixedPageNumber::Database as u32,
+    )?];
+
+    for tbl_def in reader.load_catalog()? {
+        let table = match &tbl_def.table_catalog_definition {
+            Some(table) => table,
+            None => continue,
+        };
+        spaces.push(load_object_space(
+            reader,
+            SpaceObjectType::Table,
+            table.identifier,
+            &table.name,
+            &table.name,
+            table.father_data_page_number,
+        )?);
+        for index in tbl_def.index_definition_array.iter() {
+            // the primary index is the table b-tree itself
+            if index.is_primary() || index.father_data_page_number == table.father_data_page_number
+            {
+                continue;
+            }
+            spaces.push(load_object_space(
+                reader,
+                SpaceObjectType::Index,
+                index.identifier,
+                &table.name,
+                &index.name,
+                index.father_data_page_number,
+            )?);
+        }
+        if let Some(lv) = &tbl_def.long_value_catalog_definition {
+            spaces.push(load_object_space(
+                reader,
+                SpaceObjectType::LongValue,
+                lv.identifier,
+                &table.name,
+                &lv.name,
+                lv.father_data_page_number,
+            )?);
+        }
+    }
+    Ok(spaces)
+}
+
+// Owner of a database page, as recorded in its header
+#[derive(Clone, Debug)]
+pub struct PageOwner {
+    pub page_number: u32,
+    // father data page object identifier of the page header, 0 for never used pages
+    pub object_identifier: u32,
+    // None if the object is not in the catalog (never used pages, dropped objects)
+    pub object_type: Option<SpaceObjectType>,
+    pub table: String,
+    pub name: String,
+    // leaf, parent (branch), root, space tree, empty, ...
+    pub flags: jet::PageFlags,
+    // the page header could not be loaded (not set for zero filled pages)
+    pub error: Option<String>,
+}
+
+// Scans the header of every page of the file and resolves its owner through the catalog
+pub fn load_page_owners<T: ReadSeek>(
+    reader: &Reader<T>,
+) -> Result<BTreeMap<u32, PageOwner>, SimpleError> {
+    let mut objects: HashMap<u32, (SpaceObjectType, String, String)> = HashMap::new();
+    objects.insert(
+        jet::FixedPageNumber::Database as u32,
+        (SpaceObjectType::Database, String::new(), String::new()),
+    );
+    for tbl_def in reader.load_catalog()? {
+        let table = match &tbl_def.table_catalog_definition {
+            Some(table) => table,
+            None => continue,
+        };
+        objects.insert(
+            table.identifier,
+            (
+                SpaceObjectType::Table,
+                table.name.clone(),
+                table.name.clone(),
+            ),
+        );
+        for index in tbl_def.index_definition_array.iter() {
+            if index.identifier != table.identifier {
+                objects.insert(
+                    index.identifier,
+                    (
+                        SpaceObjectType::Index,
+                        table.name.clone(),
+                        index.name.clone(),
+                    ),
+                );
+            }
+        }
+        if let Some(lv) = &tbl_def.long_value_catalog_definition {
+            objects.insert(
+                lv.identifier,
+                (
+                    SpaceObjectType::LongValue,
+                    table.name.clone(),
+                    lv.name.clone(),
+                ),
+            );
+        }
+    }
+
+    let mut owners: BTreeMap<u32, PageOwner> = BTreeMap::new();
+    for page_number in 1..=reader.page_count()? {
+        let mut owner = PageOwner {
+            page_number,
+            object_identifier: 0,
+            object_type: None,
+            table: String::new(),
+            name: String::new(),
+            flags: jet::PageFlags::empty(),
+            error: None,
+        };
+        match jet::DbPage::new(reader, page_number) {
+            Ok(db_page) => {
+                owner.object_identifier = db_page.common().father_data_page_object_identifier;
+                owner.flags = db_page.flags();
+                if let Some((object_type, table, name)) = objects.get(&owner.object_identifier) {
+                    owner.object_type = Some(*object_type);
+                    owner.table = table.clone();
+                    owner.name = name.clone();
+                }
+            }
+            Err(e) => {
+                // never written pages are zero filled and have no valid checksum
+                let page_offset = (page_number + 1) as u64 * reader.page_size() as u64;
+                let page_data = reader.read_bytes(page_offset, reader.page_size() as usize)?;
+                if page_data.iter().any(|b| *b != 0) {
+                    owner.error = Some(e.to_string());
+                }
+            }
+        }
+        owners.insert(page_number, owner);
+    }
+    Ok(owners)
+}
@@ -0,0 +1,192 @@
+//timeline.rs
+use simple_error::SimpleError;
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::parser::carve::decode_record;
+use crate::parser::jet;
+use crate::parser::log::{LogFile, LogOperation, NodeOperation, TruncatedRange};
+use crate::parser::reader::{ReadSeek, Reader};
+use crate::parser::replay;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChangeOperation {
+    Insert,
+    Update,
+    Delete,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ColumnValue {
+    pub identifier: u32,
+    pub name: String,
+    // None for NULL values
+    pub value: Option<Vec<u8>>,
+}
+
+// Change of a record of a table, from a log record
+#[derive(Clone, Debug)]
+pub struct Change {
+    pub position: jet::LgPos,
+    // creation time of the log generation of the change
+    pub time: jet::DateTime,
+    pub table: String,
+    // the key of inserts is carried by the log record, the key of updates and deletes is
+    // the key of the node in the database (empty if the page no longer has it)
+    pub key: Vec<u8>,
+    pub operation: ChangeOperation,
+    // fixed and variable size columns of the record data carried by inserts and updates,
+    // empty for deletes and for updates logged as a difference
+    pub columns: Vec<ColumnValue>,
+    // false for the changes of rolled back or unfinished transactions
+    pub committed: bool,
+}
+
+// Changes of the logs; the changes of the parts of the logs which could not be decoded
+// are missing
+#[derive(Clone, Debug, Default)]
+pub struct Timeline {
+    pub changes: Vec<Change>,
+    pub truncated: Vec<TruncatedRange>,
+    // log files left out (load_timeline)
+    pub errors: Vec<String>,
+}
+
+impl Timeline {
+    // all the records of the logs were decoded
+    pub fn is_complete(&self) -> bool {
+        self.truncated.is_empty()
+    }
+}
+
+// key of the node of the database page, if the page still belongs to the table
+fn database_node_key<T: ReadSeek>(reader: &Reader<T>, node: &NodeOperation) -> Option<Vec<u8>> {
+    let db_page = jet::DbPage::new(reader, node.page_number).ok()?;
+    if db_page.common().father_data_page_object_identifier != node.object_identifier {
+        return None;
+    }
+    let page_tag = db_page.tag(node.iline as usize + 1).ok()?;
+    let (key, _) = reader.load_page_tag_key(&db_page, page_tag).ok()?;
+    Some(key)
+}
+
+// key prefix of the database page followed by the key suffix of the insert
+fn inserted_key<T: ReadSeek>(reader: &Reader<T>, node: &NodeOperation) -> Vec<u8> {
+    let mut key = vec![];
+    if node.key_prefix_size > 0 {
+        if let Ok(db_page) = jet::DbPage::new(reader, node.page_number) {
+            if let Ok(page_tag_0) = db_page.tag(0) {
+                if node.key_prefix_size <= page_tag_0.size {
+                    key = reader
+                        .read_bytes(page_tag_0.offset(&db_page), node.key_prefix_size as usize)
+                        .unwrap_or_default();
+                }
+            }
+        }
+    }
+    key.extend_from_slice(&node.key);
+    key
+}
+
+// Turns the log records of the table b-trees into changes of their records, in log order;
+// the records of the indexes and long value trees are left out
+pub fn build_timeline<T: ReadSeek>(
+    reader: &Reader<T>,
+    logs: &[LogFile],
+) -> Result<Timeline, SimpleError> {
+    let catalog = reader.load_catalog()?;
+    let tables: HashMap<u32, &jet::TableDefinition> = catalog
+        .iter()
+        .filter_map(|tbl_def| {
+            tbl_def
+                .table_catalog_definition
+                .as_ref()
+                .map(|table| (table.identifier, tbl_def))
+        })
+        .collect();
+    let (committed, _) = replay::committed_operations(logs);
+
+    let mut changes = vec![];
+    let records = logs.iter().flat_map(|log| {
+        log.records
+            .iter()
+            .map(|record| (log.header.create_time, record))
+    });
+    for (time, record) in records {
+        let node = match record.operation.node() {
+            Some(node) => node,
+            None => continue,
+        };
+        let tbl_def = match tables.get(&node.object_identifier) {
+            Some(tbl_def) => *tbl_def,
+            None => continue,
+        };
+        let (operation, data) = match &record.operation {
+            LogOperation::Insert(_) | LogOperation::FlagInsertAndReplaceData(_) => {
+                (ChangeOperation::Insert, Some(&node.data))
+            }
+            LogOperation::FlagInsert(_) => (ChangeOperation::Insert, None),
+            LogOperation::Replace(_) => (ChangeOperation::Update, Some(&node.data)),
+            LogOperation::ReplaceDiff(_) => (ChangeOperation::Update, None),
+            LogOperation::FlagDelete(_) => (ChangeOperation::Delete, None),
+            // cleanup of a flag deleted node, page images, splits and merges
+            _ => continue,
+        };
+        let key = match &record.operation {
+            LogOperation::Insert(_) => inserted_key(reader, node),
+            _ => database_node_key(reader, node).unwrap_or_default(),
+        };
+        let columns = data
+            .and_then(|data| decode_record(tbl_def, data, 0))
+            .map(|(_, values)| {
+                values
+                    .into_iter()
+                    .map(|(identifier, value)| ColumnValue {
+                        identifier,
+                        name: tbl_def
+                            .column_catalog_definition_array
+                            .iter()
+                            .find(|col| col.identifier == identifier)
+                            .map(|col| col.name.clone())
+                            .unwrap_or_default(),
+                        value,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        changes.push(Change {
+            position: record.position,
+            time,
+            table: tbl_def
+                .table_catalog_definition
+                .as_ref()
+                .map(|table| table.name.clone())
+                .unwrap_or_default(),
+            key,
+            operation,
+            columns,
+            committed: committed.contains(&record.position),
+        });
+    }
+    Ok(Timeline {
+        changes,
+        truncated: logs
+            .iter()
+            .flat_map(|log| log.truncated.iter().cloned())
+            .collect(),
+        errors: vec![],
+    })
+}
+
+// Timeline of the logs of the directory which belong to the database, in generation order;
+// the logs which can't be loaded and those of other databases are left out, and reported
+pub fn load_timeline<T: ReadSeek>(
+    reader: &Reader<T>,
+    log_dir: impl AsRef<Path>,
+) -> Result<Timeline, SimpleError> {
+    let mut errors = vec![];
+    let logs = replay::load_logs(reader, log_dir.as_ref(), &mut errors)?;
+    let mut timeline = build_timeline(reader, &logs.into_values().collect::<Vec<_>>())?;
+    timeline.errors = errors;
+    Ok(timeline)
+}
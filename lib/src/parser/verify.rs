@@ -0,0 +1,651 @@
+//verify.rs
+use simple_error::SimpleError;
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::parser::decomp::decompress_size;
+use crate::parser::jet;
+use crate::parser::reader::{read_u32, PageCorrection, ReadSeek, Reader};
+use crate::parser::space::{self, ObjectSpace, SpaceObjectType};
+
+// Page flags telling the kind of b-tree a page belongs to
+const TREE_PAGE_FLAGS: jet::PageFlags = jet::PageFlags::IS_SPACE_TREE
+    .union(jet::PageFlags::IS_INDEX)
+    .union(jet::PageFlags::IS_LONG_VALUE);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum IntegrityErrorKind {
+    // the page fails its checksum
+    Checksum,
+    // the page header can't be loaded, or belongs to another object
+    PageHeader,
+    // leaf and parent flags, or tree type flags, inconsistent with the position of the page
+    PageFlags,
+    // tag outside of the page data, or overlapping another tag
+    TagBounds,
+    // previous or next page different from the neighbour of the page on its level
+    SiblingLink,
+    // keys of a page out of order, or outside of the range of the parent separator keys
+    KeyOrder,
+    // child page already visited
+    CircularReference,
+    // long value with missing or overlapping chunks, or chunks without a root
+    LongValue,
+    // page outside of the owned extents of its object, marked available,
+    // or in the trees of two objects
+    Space,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IntegrityError {
+    pub kind: IntegrityErrorKind,
+    // 0 if the error is not about a single page
+    pub page_number: u32,
+    // table of the b-tree (empty for the database)
+    pub table: String,
+    // catalog name of the b-tree (table, index or long value tree)
+    pub name: String,
+    pub message: String,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct IntegrityReport {
+    pub page_count: u32,
+    // data, index, long value and space trees walked
+    pub tree_count: usize,
+    pub errors: Vec<IntegrityError>,
+    // pages whose single bit errors were corrected by the ECC checksums
+    pub corrected: Vec<PageCorrection>,
+}
+
+impl IntegrityReport {
+    pub fn is_consistent(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+// B-tree of an object: its data tree, or one of its space trees
+struct Tree<'a> {
+    space: &'a ObjectSpace,
+    root: u32,
+    // the tree type flags its pages have
+    flags: jet::PageFlags,
+    // the chunks of a long value tree are compressed
+    compressed: bool,
+}
+
+struct LongValueChunk {
+    page_number: u32,
+    offset: u32,
+    // stored size
+    size: u32,
+    // 0 if the chunk is not compressed
+    decompressed_size: u32,
+}
+
+struct Verifier<'a, T: ReadSeek> {
+    reader: &'a Reader<T>,
+    report: IntegrityReport,
+    // pages failing their checksum
+    bad_pages: BTreeSet<u32>,
+    // pages of the walked trees, with the object identifier of their tree
+    tree_pages: BTreeMap<u32, u32>,
+}
+
+impl<'a, T: ReadSeek> Verifier<'a, T> {
+    fn error(
+        &mut self,
+        kind: IntegrityErrorKind,
+        page_number: u32,
+        space: Option<&ObjectSpace>,
+        message: String,
+    ) {
+        self.report.errors.push(IntegrityError {
+            kind,
+            page_number,
+            table: space.map(|s| s.table.clone()).unwrap_or_default(),
+            name: space.map(|s| s.name.clone()).unwrap_or_default(),
+            message,
+        });
+    }
+
+    fn check_checksums(&mut self) -> Result<(), SimpleError> {
+        let page_size = self.reader.page_size() as usize;
+        for page_number in 1..=self.report.page_count {
+            if let Err(e) = self.reader.verify_page_checksum(page_number) {
+                // single bit errors are corrected on load
+                if jet::DbPage::new(self.reader, page_number).is_ok()
+                    && self.reader.verify_page_checksum(page_number).is_ok()
+                {
+                    continue;
+                }
+                // never written pages are zero filled and have no valid checksum
+                let page_offset = (page_number + 1) as u64 * page_size as u64;
+                let page_data = self.reader.read_bytes(page_offset, page_size)?;
+                if page_data.iter().any(|b| *b != 0) {
+                    self.bad_pages.insert(page_number);
+                    self.error(
+                        IntegrityErrorKind::Checksum,
+                        page_number,
+                        None,
+                        e.to_string(),
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // the tags must be inside the data area of the page, between the header and the tag array
+    fn check_tags(&mut self, db_page: &jet::DbPage, space: &ObjectSpace) {
+        let page_size = self.reader.page_size() as usize;
+        let tag_array_size = db_page.tags() * 4;
+        if db_page.size() + tag_array_size > page_size {
+            self.error(
+                IntegrityErrorKind::TagBounds,
+                db_page.page_number,
+                Some(space),
+                format!("{} tags don't fit in the page", db_page.tags()),
+            );
+            return;
+        }
+        let data_size = page_size - db_page.size() - tag_array_size;
+        let mut ranges = vec![];
+        for i in 0..db_page.tags() {
+            let tag = match db_page.tag(i) {
+                Ok(tag) => tag,
+                Err(e) => {
+                    self.error(
+                        IntegrityErrorKind::TagBounds,
+                        db_page.page_number,
+                        Some(space),
+                        e.to_string(),
+                    );
+                    return;
+                }
+            };
+            let (start, end) = (tag.offset as usize, tag.offset as usize + tag.size as usize);
+            if end > data_size {
+                self.error(
+                    IntegrityErrorKind::TagBounds,
+                    db_page.page_number,
+                    Some(space),
+                    format!(
+                        "tag {} ({}..{}) is out of the page data (size {})",
+                        i, start, end, data_size
+                    ),
+                );
+            } else if start < end {
+                ranges.push((start, end, i));
+            }
+        }
+        ranges.sort();
+        for pair in ranges.windows(2) {
+            if pair[1].0 < pair[0].1 {
+                self.error(
+                    IntegrityErrorKind::TagBounds,
+                    db_page.page_number,
+                    Some(space),
+                    format!("tags {} and {} overlap", pair[0].2, pair[1].2),
+                );
+            }
+        }
+    }
+
+    // loads the keys and data offsets of the live tags of the page
+    fn load_entries(
+        &mut self,
+        db_page: &jet::DbPage,
+        space: &ObjectSpace,
+    ) -> Vec<(Vec<u8>, u64, usize)> {
+        let mut entries = vec![];
+        for i in 1..db_page.tags() {
+            let tag = match db_page.tag(i) {
+                Ok(tag) => tag,
+                Err(_) => break,
+            };
+            if tag.flags().intersects(jet::PageTagFlags::FLAG_IS_DEFUNCT) {
+                continue;
+            }
+            match self.reader.load_page_tag_key(db_page, tag) {
+                Ok((key, data_offset)) => entries.push((key, data_offset, i)),
+                Err(e) => self.error(
+                    IntegrityErrorKind::TagBounds,
+                    db_page.page_number,
+                    Some(space),
+                    format!("tag {}: {}", i, e),
+                ),
+            }
+        }
+        entries
+    }
+
+    // walks the tree level by level, from the root to the leaf pages; the children of
+    // a branch page are bounded by its separator keys: the separator of a child is bigger
+    // than its keys, the last child has an empty separator (bigger than any key)
+    fn check_tree(&mut self, tree: &Tree) -> Result<(), SimpleError> {
+        let space = tree.space;
+        let mut visited_pages: BTreeSet<u32> = BTreeSet::new();
+        let mut lv_entries: Vec<(u32, Vec<u8>, u64, usize)> = vec![];
+        // (page number, lower key bound, upper key bound, empty for none)
+        let mut level: Vec<(u32, Vec<u8>, Vec<u8>)> = vec![(tree.root, vec![], vec![])];
+        let mut depth = 0;
+        while !level.is_empty() {
+            let mut next_level = vec![];
+            // None for the pages which can't be loaded
+            let mut pages: Vec<(u32, Option<jet::DbPage>)> = vec![];
+            let mut is_leaf_level = None;
+            for (page_number, lower, upper) in level {
+                if !visited_pages.insert(page_number) {
+                    self.error(
+                        IntegrityErrorKind::CircularReference,
+                        page_number,
+                        Some(space),
+                        format!("page {} is already in the tree", page_number),
+                    );
+                    continue;
+                }
+                match self.tree_pages.insert(page_number, space.object_identifier) {
+                    Some(objid) if objid != space.object_identifier => self.error(
+                        IntegrityErrorKind::Space,
+                        page_number,
+                        Some(space),
+                        format!("page is in the tree of the object {} too", objid),
+                    ),
+                    _ => (),
+                }
+                if !space.owns(page_number) {
+                    self.error(
+                        IntegrityErrorKind::Space,
+                        page_number,
+                        Some(space),
+                        "page is not in the owned extents of the object".to_string(),
+                    );
+                } else if space.is_available(page_number) {
+                    self.error(
+                        IntegrityErrorKind::Space,
+                        page_number,
+                        Some(space),
+                        "page is in the available extents of the object".to_string(),
+                    );
+                }
+
+                let db_page = match jet::DbPage::new(self.reader, page_number) {
+                    Ok(db_page) => db_page,
+                    Err(e) => {
+                        if !self.bad_pages.contains(&page_number) {
+                            self.error(
+                                IntegrityErrorKind::PageHeader,
+                                page_number,
+                                Some(space),
+                                e.to_string(),
+                            );
+                        }
+                        pages.push((page_number, None));
+                        continue;
+                    }
+                };
+                let objid = db_page.common().father_data_page_object_identifier;
+                if objid != space.object_identifier {
+                    self.error(
+                        IntegrityErrorKind::PageHeader,
+                        page_number,
+                        Some(space),
+                        format!("page belongs to the object {}", objid),
+                    );
+                }
+
+                let flags = db_page.flags();
+                let is_leaf = flags.contains(jet::PageFlags::IS_LEAF);
+                if is_leaf == flags.contains(jet::PageFlags::IS_PARENT) {
+                    self.error(
+                        IntegrityErrorKind::PageFlags,
+                        page_number,
+                        Some(space),
+                        format!("page is either both leaf and parent or none, {:?}", flags),
+                    );
+                }
+                if flags.contains(jet::PageFlags::IS_ROOT) != (depth == 0) {
+                    self.error(
+                        IntegrityErrorKind::PageFlags,
+                        page_number,
+                        Some(space),
+                        format!("wrong root flag at depth {}, {:?}", depth, flags),
+                    );
+                }
+                if flags.intersection(TREE_PAGE_FLAGS) != tree.flags {
+                    self.error(
+                        IntegrityErrorKind::PageFlags,
+                        page_number,
+                        Some(space),
+                        format!("tree type flags {:?} expected, {:?}", tree.flags, flags),
+                    );
+                }
+                match is_leaf_level {
+                    Some(leaf_level) if leaf_level != is_leaf => self.error(
+                        IntegrityErrorKind::PageFlags,
+                        page_number,
+                        Some(space),
+                        format!("leaf and branch pages at depth {}", depth),
+                    ),
+                    _ => is_leaf_level = Some(is_leaf),
+                }
+
+                self.check_tags(&db_page, space);
+                let entries = self.load_entries(&db_page, space);
+                let mut previous: Option<&Vec<u8>> = None;
+                for (key, _, i) in entries.iter() {
+                    // the last separator of a branch page is empty
+                    if !is_leaf && key.is_empty() {
+                        continue;
+                    }
+                    let in_range = key >= &lower
+                        && (upper.is_empty() || if is_leaf { key < &upper } else { key <= &upper });
+                    if !in_range {
+                        self.error(
+                            IntegrityErrorKind::KeyOrder,
+                            page_number,
+                            Some(space),
+                            format!(
+                                "tag {}: key is out of the range of the parent separators",
+                                i
+                            ),
+                        );
+                    }
+                    if previous.is_some_and(|previous| previous > key) {
+                        self.error(
+                            IntegrityErrorKind::KeyOrder,
+                            page_number,
+                            Some(space),
+                            format!("tag {}: key is smaller than the previous key", i),
+                        );
+                    }
+                    previous = Some(key);
+                }
+
+                if is_leaf {
+                    if tree.flags == jet::PageFlags::IS_LONG_VALUE {
+                        for (key, data_offset, i) in entries {
+                            lv_entries.push((page_number, key, data_offset, i));
+                        }
+                    }
+                } else {
+                    let mut child_lower = lower.clone();
+                    for (key, _, i) in entries {
+                        let tag = db_page.tag(i)?;
+                        let child_upper = if key.is_empty() { upper.clone() } else { key };
+                        match self
+                            .reader
+                            .page_tag_get_branch_child_page_number(&db_page, tag)
+                        {
+                            Ok(child) => next_level.push((child, child_lower, child_upper.clone())),
+                            Err(e) => self.error(
+                                IntegrityErrorKind::TagBounds,
+                                page_number,
+                                Some(space),
+                                format!("tag {}: {}", i, e),
+                            ),
+                        }
+                        child_lower = child_upper;
+                    }
+                }
+                pages.push((page_number, Some(db_page)));
+            }
+            self.check_siblings(&pages, space);
+            level = next_level;
+            depth += 1;
+        }
+
+        if tree.flags == jet::PageFlags::IS_LONG_VALUE {
+            self.check_long_values(&lv_entries, tree)?;
+        }
+        Ok(())
+    }
+
+    // the pages of a level are linked in their key order
+    fn check_siblings(&mut self, pages: &[(u32, Option<jet::DbPage>)], space: &ObjectSpace) {
+        for (i, (_, db_page)) in pages.iter().enumerate() {
+            let db_page = match db_page {
+                Some(db_page) => db_page,
+                None => continue,
+            };
+            let previous = if i > 0 { pages[i - 1].0 } else { 0 };
+            let next = pages.get(i + 1).map_or(0, |p| p.0);
+            if db_page.prev_page() != previous {
+                self.error(
+                    IntegrityErrorKind::SiblingLink,
+                    db_page.page_number,
+                    Some(space),
+                    format!(
+                        "previous page {}, expected {}",
+                        db_page.prev_page(),
+                        previous
+                    ),
+                );
+            }
+            if db_page.next_page() != next {
+                self.error(
+                    IntegrityErrorKind::SiblingLink,
+                    db_page.page_number,
+                    Some(space),
+                    format!("next page {}, expected {}", db_page.next_page(), next),
+                );
+            }
+        }
+    }
+
+    // Long value roots are keyed by the long value identifier (4 or 8 bytes) and hold
+    // its reference count and size, its chunks are keyed by the identifier and the offset
+    // of the chunk (4 bytes); the chunks must cover the size of the long value
+    fn check_long_values(
+        &mut self,
+        entries: &[(u32, Vec<u8>, u64, usize)],
+        tree: &Tree,
+    ) -> Result<(), SimpleError> {
+        let space = tree.space;
+        // long value key: (page number of the root, size)
+        let mut roots: BTreeMap<Vec<u8>, (u32, u32)> = BTreeMap::new();
+        let mut chunks: BTreeMap<Vec<u8>, Vec<LongValueChunk>> = BTreeMap::new();
+        for (page_number, key, data_offset, i) in entries.iter() {
+            let db_page = jet::DbPage::new(self.reader, *page_number)?;
+            let tag = db_page.tag(*i)?;
+            let data_size = (tag.offset(&db_page) + tag.size as u64).saturating_sub(*data_offset);
+            let is_root = key.len() == 4 || (key.len() == 8 && data_size == 8);
+            if is_root {
+                let size = read_u32(self.reader, data_offset + 4)?;
+                roots.insert(key.clone(), (*page_number, size));
+            } else if key.len() == 8 || key.len() == 12 {
+                let (lid, offset) = key.split_at(key.len() - 4);
+                let mut decompressed_size = 0;
+                if tree.compressed {
+                    let data = self.reader.read_bytes(*data_offset, data_size as usize)?;
+                    decompressed_size = decompress_size(&data) as u32;
+                }
+                chunks
+                    .entry(lid.to_vec())
+                    .or_default()
+                    .push(LongValueChunk {
+                        page_number: *page_number,
+                        offset: u32::from_be_bytes(offset.try_into().unwrap_or_default()),
+                        size: data_size as u32,
+                        decompressed_size,
+                    });
+            } else {
+                self.error(
+                    IntegrityErrorKind::LongValue,
+                    *page_number,
+                    Some(space),
+                    format!("tag {}: wrong long value key size {}", i, key.len()),
+                );
+            }
+        }
+
+        for (lid, (page_number, size)) in roots.iter() {
+            let lid_chunks = chunks.remove(lid).unwrap_or_default();
+            let mut end = 0;
+            for (k, chunk) in lid_chunks.iter().enumerate() {
+                if chunk.offset != end {
+                    self.error(
+                        IntegrityErrorKind::LongValue,
+                        chunk.page_number,
+                        Some(space),
+                        format!(
+                            "long value {:02x?}: chunk at offset {}, expected {}",
+                            lid, chunk.offset, end
+                        ),
+                    );
+                }
+                // the data of a compressed chunk is shorter than the chunk, unless
+                // it isn't compressed after all
+                let next = lid_chunks.get(k + 1).map_or(*size, |c| c.offset);
+                end = if chunk.decompressed_size > 0 && chunk.offset + chunk.size != next {
+                    chunk.offset + chunk.decompressed_size
+                } else {
+                    chunk.offset + chunk.size
+                };
+            }
+            if end != *size {
+                self.error(
+                    IntegrityErrorKind::LongValue,
+                    *page_number,
+                    Some(space),
+                    format!(
+                        "long value {:02x?}: chunks cover {} bytes of {}",
+                        lid, end, size
+                    ),
+                );
+            }
+        }
+        for (lid, lid_chunks) in chunks {
+            self.error(
+                IntegrityErrorKind::LongValue,
+                lid_chunks[0].page_number,
+                Some(space),
+                format!("long value {:02x?}: chunks without a root", lid),
+            );
+        }
+        Ok(())
+    }
+}
+
+// Checks the checksum of every page, and walks the data, index, long value and space trees
+// of the database and of every catalog table: sibling links, key order, page flags,
+// tag bounds, long value chunks, and the agreement of the space trees with the pages of
+// the trees. Like esentutl /k and /g, the errors found are reported rather than returned.
+pub fn verify_database<T: ReadSeek>(reader: &Reader<T>) -> Result<IntegrityReport, SimpleError> {
+    let mut verifier = Verifier {
+        reader,
+        report: IntegrityReport {
+            page_count: reader.page_count()?,
+            ..Default::default()
+        },
+        bad_pages: BTreeSet::new(),
+        tree_pages: BTreeMap::new(),
+    };
+    verifier.check_checksums()?;
+
+    // (object type, identifier, table, name, father data page, long values compressed)
+    let mut objects = vec![(
+        SpaceObjectType::Database,
+        jet::FixedPageNumber::Database as u32,
+        String::new(),
+        String::new(),
+        jet::FixedPageNumber::Database as u32,
+        false,
+    )];
+    for tbl_def in reader.load_catalog()? {
+        let table = match &tbl_def.table_catalog_definition {
+            Some(table) => table,
+            None => continue,
+        };
+        objects.push((
+            SpaceObjectType::Table,
+            table.identifier,
+            table.name.clone(),
+            table.name.clone(),
+            table.father_data_page_number,
+            false,
+        ));
+        for index in tbl_def.index_definition_array.iter() {
+            // the primary index is the table b-tree itself
+            if index.is_primary() || index.father_data_page_number == table.father_data_page_number
+            {
+                continue;
+            }
+            objects.push((
+                SpaceObjectType::Index,
+                index.identifier,
+                table.name.clone(),
+                index.name.clone(),
+                index.father_data_page_number,
+                false,
+            ));
+        }
+        if let Some(lv) = &tbl_def.long_value_catalog_definition {
+            let compressed = tbl_def.column_catalog_definition_array.iter().any(|col| {
+                jet::ColumnFlags::from_bits_truncate(col.flags)
+                    .intersects(jet::ColumnFlags::Compressed)
+            });
+            objects.push((
+                SpaceObjectType::LongValue,
+                lv.identifier,
+                table.name.clone(),
+                lv.name.clone(),
+                lv.father_data_page_number,
+                compressed,
+            ));
+        }
+    }
+
+    for (object_type, object_identifier, table, name, father_data_page_number, compressed) in
+        objects
+    {
+        let space = match space::load_object_space(
+            reader,
+            object_type,
+            object_identifier,
+            &table,
+            &name,
+            father_data_page_number,
+        ) {
+            Ok(space) => space,
+            Err(e) => {
+                verifier.report.errors.push(IntegrityError {
+                    kind: IntegrityErrorKind::Space,
+                    page_number: father_data_page_number,
+                    table,
+                    name,
+                    message: e.to_string(),
+                });
+                continue;
+            }
+        };
+        let flags = match object_type {
+            SpaceObjectType::Index => jet::PageFlags::IS_INDEX,
+            SpaceObjectType::LongValue => jet::PageFlags::IS_LONG_VALUE,
+            _ => jet::PageFlags::empty(),
+        };
+        let mut trees = vec![Tree {
+            space: &space,
+            root: father_data_page_number,
+            flags,
+            compressed,
+        }];
+        if let Some((owned_extent_tree, available_extent_tree)) = space.space_tree_page_numbers {
+            for root in [owned_extent_tree, available_extent_tree] {
+                trees.push(Tree {
+                    space: &space,
+                    root,
+                    flags: flags | jet::PageFlags::IS_SPACE_TREE,
+                    compressed: false,
+                });
+            }
+        }
+        for tree in trees {
+            verifier.check_tree(&tree)?;
+            verifier.report.tree_count += 1;
+        }
+    }
+
+    verifier.report.corrected = reader.corruption_report().corrected;
+    Ok(verifier.report)
+}
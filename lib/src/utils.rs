@@ -115,6 +115,43 @@ pub fn calc_new_crc(pb: &[u8], pgno: u32, skip_header: bool) -> Result<u64, Simp
     ))
 }
 
+// A single flipped bit changes the XOR checksum by the bit in its dword,
+// and the ECC checksum by its index (low word) and the complement of the index (high word).
+// Returns the index of the bit corrected in the buffer
+pub fn correct_single_bit_error(
+    pb: &mut [u8],
+    pgno: u32,
+    checksum: u64,
+    skip_header: bool,
+) -> Result<Option<usize>, SimpleError> {
+    let diff = calc_new_crc(pb, pgno, skip_header)? ^ checksum;
+    let ecc_diff = (diff >> 32) as u32;
+    let xor_diff = diff as u32;
+    if diff == 0 || xor_diff.count_ones() != 1 {
+        return Ok(None);
+    }
+    // the high word of the ECC difference must be the complement of the low word
+    // over the bits of a bit index, else more than one bit changed
+    let bit_mask = (pb.len() * 8).next_power_of_two() as u32 - 1;
+    if ((ecc_diff >> 16) ^ ecc_diff) & bit_mask != bit_mask {
+        return Ok(None);
+    }
+    let bit = (ecc_diff & bit_mask) as usize;
+    if bit >= pb.len() * 8 || bit % 32 != xor_diff.trailing_zeros() as usize {
+        return Ok(None);
+    }
+    // the checksum itself isn't covered
+    if skip_header && bit < 64 {
+        return Ok(None);
+    }
+    pb[bit / 8] ^= 1 << (bit % 8);
+    if calc_new_crc(pb, pgno, skip_header)? != checksum {
+        pb[bit / 8] ^= 1 << (bit % 8);
+        return Ok(None);
+    }
+    Ok(Some(bit))
+}
+
 fn MakeChecksumFromECCXORAndPgno(eccChecksum: u32, xorChecksum: u32, pgno: u32) -> u64 {
     let low = (xorChecksum ^ pgno) as u64;
     let high = (eccChecksum as u64) << 32;
@@ -189,6 +226,45 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_correct_single_bit_error() {
+        let input = fs::read("testdata/checksum_buffer_12050322830504531039.bin").unwrap();
+        let checksum = 12050322830504531039;
+        let mut buffer = input.clone();
+        assert_eq!(
+            correct_single_bit_error(&mut buffer, 1793, checksum, true),
+            Ok(None)
+        );
+        for bit in [64, 95, 1000, input.len() * 8 - 1] {
+            buffer[bit / 8] ^= 1 << (bit % 8);
+            assert_eq!(
+                correct_single_bit_error(&mut buffer, 1793, checksum, true),
+                Ok(Some(bit))
+            );
+            assert_eq!(buffer, input);
+        }
+        // two flipped bits aren't correctable
+        buffer[100] ^= 1;
+        buffer[200] ^= 1;
+        let corrupted = buffer.clone();
+        assert_eq!(
+            correct_single_bit_error(&mut buffer, 1793, checksum, true),
+            Ok(None)
+        );
+        assert_eq!(buffer, corrupted);
+        // nor a difference naming a single bit without the complement of its index
+        let mut buffer = input.clone();
+        let bit = 1000;
+        buffer[bit / 8] ^= 1 << (bit % 8);
+        let corrupted = buffer.clone();
+        let checksum = checksum ^ (1 << 48);
+        assert_eq!(
+            correct_single_bit_error(&mut buffer, 1793, checksum, true),
+            Ok(None)
+        );
+        assert_eq!(buffer, corrupted);
+    }
+
     #[test]
     fn test_calc_new_crc_good_empty() {
         assert_eq!(Ok(0), calc_new_crc(&[0; 32], 0, false));